@@ -0,0 +1,1241 @@
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::START_ADDRESS;
+
+// Assembles a practical subset of Octo, enough to write test ROMs inline:
+//
+//     : main
+//         i := smiley
+//         v0 := 10
+//         loop
+//             sprite v0 v1 4
+//             v0 += 1
+//             if v0 == 40 then v0 := 0
+//         again
+//     : smiley
+//         0x24 0x00 0x81 0x7E
+//
+// Supported are labels (`: name` and `:next`), `:const`, `:calc`, `:alias`,
+// `:org`, `:byte`, `:call`, `:unpack`, `:macro` and `:stringmode`, bare
+// numbers as data bytes, a bare label as a subroutine call,
+// `loop`/`while`/`again`, `if ... then` and `if ... begin ... else ... end`
+// with all of Octo's comparisons, and the CHIP-8, SUPER-CHIP and XO-CHIP
+// statements. `:breakpoint` and `:monitor` only matter to Octo's debugger
+// and are skipped. `:calc` evaluates right to left like Octo's, but in
+// integers and without its math functions.
+//
+// The source is loaded at 0x200. As in Octo, it must define `main`, and
+// starts with a jump there unless `: main` comes before any code.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut asm = Assembler::new(source);
+    asm.run()?;
+    asm.resolve()?;
+    Ok(asm.out)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsmError {
+    UnexpectedEnd { line: usize },
+    UnexpectedToken { line: usize, token: String },
+    UndefinedName { line: usize, name: String },
+    Redefined { line: usize, name: String },
+    ValueOutOfRange { line: usize, value: i64 },
+    UnbalancedBlock { line: usize },
+    // A macro expands without end, or a `:calc` expression nests too deep.
+    TooDeep { line: usize },
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsmError::UnexpectedEnd { line } => write!(f, "line {}: unexpected end of input", line),
+            AsmError::UnexpectedToken { line, token } => {
+                write!(f, "line {}: unexpected '{}'", line, token)
+            }
+            AsmError::UndefinedName { line, name } => {
+                write!(f, "line {}: '{}' is not defined", line, name)
+            }
+            AsmError::Redefined { line, name } => {
+                write!(f, "line {}: '{}' is already defined", line, name)
+            }
+            AsmError::ValueOutOfRange { line, value } => {
+                write!(f, "line {}: {} is out of range", line, value)
+            }
+            AsmError::UnbalancedBlock { line } => {
+                write!(f, "line {}: unbalanced loop or if block", line)
+            }
+            AsmError::TooDeep { line } => write!(f, "line {}: nested too deeply", line),
+        }
+    }
+}
+
+impl core::error::Error for AsmError {}
+
+// Bounds recursive macros, which would otherwise expand forever, and the
+// recursion `:calc` takes for each operator and parenthesis.
+const MAX_EXPANSIONS: usize = 10_000;
+const MAX_CALC_DEPTH: usize = 256;
+
+#[derive(Clone)]
+struct Token<'a> {
+    // Source text, or a number a string mode substituted in.
+    text: Cow<'a, str>,
+    line: usize,
+}
+
+struct Macro<'a> {
+    params: Vec<Cow<'a, str>>,
+    body: Vec<Token<'a>>,
+}
+
+#[derive(Clone, Copy)]
+enum FixupKind {
+    // Low 12 bits of the opcode at the position.
+    Addr12,
+    // The full word at the position (F000 NNNN).
+    Word,
+    // Low nibble of the opcode, set to the address's top nibble (`:unpack`).
+    High4,
+    // Low byte of the opcode, set to the address's high byte.
+    High8,
+    // Low byte of the opcode, set to the address's low byte.
+    Low8,
+}
+
+struct Fixup {
+    at: usize,
+    name: String,
+    line: usize,
+    kind: FixupKind,
+}
+
+enum Block {
+    Loop { start: u16, exits: Vec<usize> },
+    If { jump_at: usize, line: usize },
+}
+
+#[derive(Clone, Copy)]
+enum Operand {
+    Reg(u8),
+    Imm(u8),
+}
+
+#[derive(Clone, Copy)]
+enum Cond {
+    Eq(u8, Operand),
+    Ne(u8, Operand),
+    Key(u8),
+    NotKey(u8),
+}
+
+impl Cond {
+    fn negate(self) -> Cond {
+        match self {
+            Cond::Eq(x, rhs) => Cond::Ne(x, rhs),
+            Cond::Ne(x, rhs) => Cond::Eq(x, rhs),
+            Cond::Key(x) => Cond::NotKey(x),
+            Cond::NotKey(x) => Cond::Key(x),
+        }
+    }
+
+    // The opcode that skips the next instruction unless the condition
+    // holds, which is what `if cond then` compiles to.
+    fn then_opcode(self) -> u16 {
+        let x = |x: u8| (x as u16) << 8;
+        match self {
+            Cond::Eq(vx, Operand::Imm(nn)) => 0x4000 | x(vx) | nn as u16,
+            Cond::Ne(vx, Operand::Imm(nn)) => 0x3000 | x(vx) | nn as u16,
+            Cond::Eq(vx, Operand::Reg(vy)) => 0x9000 | x(vx) | (vy as u16) << 4,
+            Cond::Ne(vx, Operand::Reg(vy)) => 0x5000 | x(vx) | (vy as u16) << 4,
+            Cond::Key(vx) => 0xE0A1 | x(vx),
+            Cond::NotKey(vx) => 0xE09E | x(vx),
+        }
+    }
+}
+
+struct Assembler<'a> {
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+    out: Vec<u8>,
+    origin: u16,
+    labels: BTreeMap<String, u16>,
+    consts: BTreeMap<String, i64>,
+    aliases: BTreeMap<String, u8>,
+    fixups: Vec<Fixup>,
+    blocks: Vec<Block>,
+    macros: BTreeMap<String, Macro<'a>>,
+    // The bodies for each character a string mode covers, with the
+    // character's index in the alphabet it was declared with.
+    string_modes: BTreeMap<String, BTreeMap<char, (usize, Vec<Token<'a>>)>>,
+    expansions: usize,
+    // Whether the output starts with the jump to `main`.
+    entry_jump: bool,
+}
+
+impl<'a> Assembler<'a> {
+    fn new(source: &'a str) -> Self {
+        let tokens = source
+            .lines()
+            .enumerate()
+            .flat_map(|(idx, line)| {
+                tokenize(line).into_iter().map(move |text| Token {
+                    text: Cow::Borrowed(text),
+                    line: idx + 1,
+                })
+            })
+            .collect();
+
+        Self {
+            tokens,
+            pos: 0,
+            // Room for the jump to `main`
+            out: vec![0; 2],
+            origin: START_ADDRESS,
+            labels: BTreeMap::new(),
+            consts: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+            fixups: Vec::new(),
+            blocks: Vec::new(),
+            macros: BTreeMap::new(),
+            string_modes: BTreeMap::new(),
+            expansions: 0,
+            entry_jump: true,
+        }
+    }
+
+    fn run(&mut self) -> Result<(), AsmError> {
+        while self.pos < self.tokens.len() {
+            self.statement()?;
+        }
+        match self.blocks.last() {
+            Some(Block::If { line, .. }) => Err(AsmError::UnbalancedBlock { line: *line }),
+            Some(Block::Loop { .. }) => Err(AsmError::UnbalancedBlock {
+                line: self.last_line(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn resolve(&mut self) -> Result<(), AsmError> {
+        if self.entry_jump {
+            self.fixups.push(Fixup {
+                at: 0,
+                name: "main".to_string(),
+                line: 1,
+                kind: FixupKind::Addr12,
+            });
+            self.patch_jump(0, 0);
+        }
+
+        for fixup in &self.fixups {
+            let addr = match self.labels.get(&fixup.name) {
+                Some(&addr) => addr,
+                None => {
+                    return Err(AsmError::UndefinedName {
+                        line: fixup.line,
+                        name: fixup.name.clone(),
+                    })
+                }
+            };
+            let at = fixup.at;
+            let word = u16::from_be_bytes([self.out[at], self.out[at + 1]]);
+            let word = match fixup.kind {
+                FixupKind::Addr12 => (word & 0xF000) | (addr & 0x0FFF),
+                FixupKind::Word => addr,
+                FixupKind::High4 => (word & 0xFFF0) | ((addr >> 8) & 0x000F),
+                FixupKind::High8 => (word & 0xFF00) | (addr >> 8),
+                FixupKind::Low8 => (word & 0xFF00) | (addr & 0x00FF),
+            };
+            self.out[at..at + 2].copy_from_slice(&word.to_be_bytes());
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self) -> Result<(), AsmError> {
+        let (tok, line) = self.next()?;
+
+        match &*tok {
+            ":" => {
+                let (name, line) = self.next()?;
+                self.define_label(&name, line)?;
+            }
+            ":next" => {
+                // Names the second byte of the next instruction, for code
+                // that rewrites its own operands
+                let (name, line) = self.next()?;
+                let addr = self.here().wrapping_add(1);
+                self.define_label_at(&name, addr, line)?;
+            }
+            ":const" => {
+                let (name, line) = self.next()?;
+                let value = self.number()?;
+                if self.consts.insert(name.to_string(), value).is_some() {
+                    return Err(AsmError::Redefined {
+                        line,
+                        name: name.to_string(),
+                    });
+                }
+            }
+            ":calc" => {
+                // Unlike `:const`, may redefine a name
+                let (name, _) = self.next()?;
+                self.expect("{")?;
+                let value = self.calc(0)?;
+                self.expect("}")?;
+                self.consts.insert(name.to_string(), value);
+            }
+            ":alias" => {
+                let (name, _) = self.next()?;
+                let reg = self.register()?;
+                self.aliases.insert(name.to_string(), reg);
+            }
+            ":macro" => self.define_macro()?,
+            ":stringmode" => self.define_string_mode()?,
+            ":call" => self.address_op(0x2000)?,
+            ":unpack" => {
+                // v0 and v1 := the address, with a nibble on top or `long`
+                let (top, top_line) = self.next()?;
+                let (hi, hi_kind) = if top == "long" {
+                    (0x6000, FixupKind::High8)
+                } else {
+                    let top = self.resolve_number(&top, top_line)?;
+                    let top = self.check(top, 0xF, top_line)? as u16;
+                    (0x6000 | top << 4, FixupKind::High4)
+                };
+                let (target, line) = self.next()?;
+                match self.lookup_number(&target) {
+                    Some(value) => {
+                        let addr = self.check(value, 0xFFFF, line)? as u16;
+                        let hi = match hi_kind {
+                            FixupKind::High8 => hi | addr >> 8,
+                            _ => hi | (addr >> 8 & 0xF),
+                        };
+                        self.emit(hi);
+                        self.emit(0x6100 | (addr & 0xFF));
+                    }
+                    None => {
+                        self.fixup(&target, line, hi_kind);
+                        self.emit(hi);
+                        self.fixup(&target, line, FixupKind::Low8);
+                        self.emit(0x6100);
+                    }
+                }
+            }
+            ":breakpoint" => {
+                self.next()?;
+            }
+            ":monitor" => {
+                // The address, then a length or format string
+                self.next()?;
+                self.next()?;
+            }
+            ":org" => {
+                // Only moves forward, padding the gap with zeros
+                let value = self.number()?;
+                let offset = value - self.origin as i64;
+                if offset < self.out.len() as i64 || value > 0xFFFF {
+                    return Err(AsmError::ValueOutOfRange { line, value });
+                }
+                self.out.resize(offset as usize, 0);
+            }
+            ":byte" => {
+                let value = if self.peek() == Some("{") {
+                    self.pos += 1;
+                    let value = self.calc(0)?;
+                    self.expect("}")?;
+                    value
+                } else {
+                    self.number()?
+                };
+                let byte = self.byte(value, line)?;
+                self.out.push(byte);
+            }
+            "clear" => self.emit(0x00E0),
+            "return" | ";" => self.emit(0x00EE),
+            "exit" => self.emit(0x00FD),
+            "lores" => self.emit(0x00FE),
+            "hires" => self.emit(0x00FF),
+            "scroll-right" => self.emit(0x00FB),
+            "scroll-left" => self.emit(0x00FC),
+            "scroll-down" => {
+                let n = self.nibble()?;
+                self.emit(0x00C0 | n);
+            }
+            "scroll-up" => {
+                let n = self.nibble()?;
+                self.emit(0x00D0 | n);
+            }
+            "jump" => self.address_op(0x1000)?,
+            "jump0" => self.address_op(0xB000)?,
+            "bcd" => self.vx_op(0xF033)?,
+            "save" | "load" => {
+                let x = self.register()? as u16;
+                let (base, range_base) = if tok == "save" {
+                    (0xF055, 0x5002)
+                } else {
+                    (0xF065, 0x5003)
+                };
+                if self.peek() == Some("-") {
+                    self.pos += 1;
+                    let y = self.register()? as u16;
+                    self.emit(range_base | x << 8 | y << 4);
+                } else {
+                    self.emit(base | x << 8);
+                }
+            }
+            "saveflags" => self.vx_op(0xF075)?,
+            "loadflags" => self.vx_op(0xF085)?,
+            "sprite" => {
+                let x = self.register()? as u16;
+                let y = self.register()? as u16;
+                let n = self.nibble()?;
+                self.emit(0xD000 | x << 8 | y << 4 | n);
+            }
+            "plane" => {
+                let n = self.nibble()?;
+                self.emit(0xF001 | n << 8);
+            }
+            "audio" => self.emit(0xF002),
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let op = match &*tok {
+                    "delay" => 0xF015,
+                    "buzzer" => 0xF018,
+                    _ => 0xF03A,
+                };
+                self.vx_op(op)?;
+            }
+            "i" => self.i_statement()?,
+            "if" => self.if_statement(line)?,
+            "loop" => self.blocks.push(Block::Loop {
+                start: self.here(),
+                exits: Vec::new(),
+            }),
+            "while" => {
+                let cond = self.condition()?;
+                let at = self.out.len() + 2;
+                self.emit(cond.negate().then_opcode());
+                self.emit(0x1000);
+                match self.blocks.iter_mut().rev().find_map(|block| match block {
+                    Block::Loop { exits, .. } => Some(exits),
+                    _ => None,
+                }) {
+                    Some(exits) => exits.push(at),
+                    None => return Err(AsmError::UnbalancedBlock { line }),
+                }
+            }
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop { start, exits }) => {
+                    self.emit(0x1000 | (start & 0x0FFF));
+                    let here = self.here();
+                    for at in exits {
+                        self.patch_jump(at, here);
+                    }
+                }
+                _ => return Err(AsmError::UnbalancedBlock { line }),
+            },
+            "else" => match self.blocks.pop() {
+                Some(Block::If { jump_at, .. }) => {
+                    let at = self.out.len();
+                    self.emit(0x1000);
+                    let here = self.here();
+                    self.patch_jump(jump_at, here);
+                    self.blocks.push(Block::If { jump_at: at, line });
+                }
+                _ => return Err(AsmError::UnbalancedBlock { line }),
+            },
+            "end" => match self.blocks.pop() {
+                Some(Block::If { jump_at, .. }) => {
+                    let here = self.here();
+                    self.patch_jump(jump_at, here);
+                }
+                _ => return Err(AsmError::UnbalancedBlock { line }),
+            },
+            _ => {
+                if self.macros.contains_key(&*tok) {
+                    return self.expand_macro(&tok, line);
+                }
+                if self.string_modes.contains_key(&*tok) {
+                    return self.expand_string_mode(&tok, line);
+                }
+                if let Some(x) = self.lookup_register(&tok) {
+                    return self.register_statement(x);
+                }
+                if let Some(value) = self.lookup_number(&tok) {
+                    let byte = self.byte(value, line)?;
+                    self.out.push(byte);
+                    return Ok(());
+                }
+                if is_identifier(&tok) {
+                    // A bare name calls the subroutine with that label
+                    self.fixup(&tok, line, FixupKind::Addr12);
+                    self.emit(0x2000);
+                    return Ok(());
+                }
+                return Err(AsmError::UnexpectedToken {
+                    line,
+                    token: tok.to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn i_statement(&mut self) -> Result<(), AsmError> {
+        let (op, line) = self.next()?;
+        match &*op {
+            ":=" => match self.peek() {
+                Some("hex") => {
+                    self.pos += 1;
+                    self.vx_op(0xF029)
+                }
+                Some("bighex") => {
+                    self.pos += 1;
+                    self.vx_op(0xF030)
+                }
+                Some("long") => {
+                    self.pos += 1;
+                    self.emit(0xF000);
+                    let (tok, line) = self.next()?;
+                    let addr = match self.lookup_number(&tok) {
+                        Some(value) => self.check(value, 0xFFFF, line)? as u16,
+                        None => {
+                            self.fixup(&tok, line, FixupKind::Word);
+                            0
+                        }
+                    };
+                    self.emit(addr);
+                    Ok(())
+                }
+                _ => self.address_op(0xA000),
+            },
+            "+=" => self.vx_op(0xF01E),
+            _ => Err(AsmError::UnexpectedToken {
+                line,
+                token: op.to_string(),
+            }),
+        }
+    }
+
+    fn register_statement(&mut self, x: u8) -> Result<(), AsmError> {
+        let x16 = (x as u16) << 8;
+        let (op, line) = self.next()?;
+        let (rhs, rhs_line) = self.next()?;
+
+        if op == ":=" {
+            match &*rhs {
+                "random" => {
+                    let nn = self.immediate()?;
+                    self.emit(0xC000 | x16 | nn as u16);
+                    return Ok(());
+                }
+                "delay" => {
+                    self.emit(0xF007 | x16);
+                    return Ok(());
+                }
+                "key" => {
+                    self.emit(0xF00A | x16);
+                    return Ok(());
+                }
+                _ => (),
+            }
+        }
+
+        if let Some(y) = self.lookup_register(&rhs) {
+            let n = match &*op {
+                ":=" => 0x0,
+                "|=" => 0x1,
+                "&=" => 0x2,
+                "^=" => 0x3,
+                "+=" => 0x4,
+                "-=" => 0x5,
+                ">>=" => 0x6,
+                "=-" => 0x7,
+                "<<=" => 0xE,
+                _ => {
+                    return Err(AsmError::UnexpectedToken {
+                        line,
+                        token: op.to_string(),
+                    })
+                }
+            };
+            self.emit(0x8000 | x16 | (y as u16) << 4 | n);
+            return Ok(());
+        }
+
+        let value = self.resolve_number(&rhs, rhs_line)?;
+        let base = match &*op {
+            ":=" => 0x6000,
+            "+=" => 0x7000,
+            // Octo's `vX -= N` adds the two's complement
+            "-=" => {
+                let nn = self.byte(value, rhs_line)?;
+                self.emit(0x7000 | x16 | nn.wrapping_neg() as u16);
+                return Ok(());
+            }
+            _ => {
+                return Err(AsmError::UnexpectedToken {
+                    line,
+                    token: op.to_string(),
+                })
+            }
+        };
+        let nn = self.byte(value, rhs_line)?;
+        self.emit(base | x16 | nn as u16);
+        Ok(())
+    }
+
+    fn if_statement(&mut self, line: usize) -> Result<(), AsmError> {
+        let cond = self.condition()?;
+        let (tok, tok_line) = self.next()?;
+
+        match &*tok {
+            "then" => {
+                self.emit(cond.then_opcode());
+                Ok(())
+            }
+            "begin" => {
+                self.emit(cond.negate().then_opcode());
+                let at = self.out.len();
+                self.emit(0x1000);
+                self.blocks.push(Block::If { jump_at: at, line });
+                Ok(())
+            }
+            _ => Err(AsmError::UnexpectedToken {
+                line: tok_line,
+                token: tok.to_string(),
+            }),
+        }
+    }
+
+    fn condition(&mut self) -> Result<Cond, AsmError> {
+        let x = self.register()?;
+        let (op, line) = self.next()?;
+
+        match &*op {
+            "key" => return Ok(Cond::Key(x)),
+            "-key" => return Ok(Cond::NotKey(x)),
+            _ => (),
+        }
+
+        let (rhs, rhs_line) = self.next()?;
+        let rhs = match self.lookup_register(&rhs) {
+            Some(y) => Operand::Reg(y),
+            None => {
+                let value = self.resolve_number(&rhs, rhs_line)?;
+                Operand::Imm(self.byte(value, rhs_line)?)
+            }
+        };
+
+        // Ordering goes through vF as Octo's does: vF := rhs, then a
+        // subtraction whose no-borrow flag answers the comparison
+        let ordering = match &*op {
+            ">" => Some((0x5, 0)),
+            "<=" => Some((0x5, 1)),
+            "<" => Some((0x7, 0)),
+            ">=" => Some((0x7, 1)),
+            _ => None,
+        };
+        if let Some((n, flag)) = ordering {
+            match rhs {
+                Operand::Reg(y) => self.emit(0x8F00 | (y as u16) << 4),
+                Operand::Imm(nn) => self.emit(0x6F00 | nn as u16),
+            }
+            self.emit(0x8F00 | (x as u16) << 4 | n);
+            return Ok(Cond::Eq(0xF, Operand::Imm(flag)));
+        }
+
+        match &*op {
+            "==" => Ok(Cond::Eq(x, rhs)),
+            "!=" => Ok(Cond::Ne(x, rhs)),
+            _ => Err(AsmError::UnexpectedToken {
+                line,
+                token: op.to_string(),
+            }),
+        }
+    }
+
+    fn address_op(&mut self, base: u16) -> Result<(), AsmError> {
+        let (tok, line) = self.next()?;
+        match self.lookup_number(&tok) {
+            Some(value) => {
+                let addr = self.check(value, 0x0FFF, line)? as u16;
+                self.emit(base | addr);
+            }
+            None => {
+                self.fixup(&tok, line, FixupKind::Addr12);
+                self.emit(base);
+            }
+        }
+        Ok(())
+    }
+
+    fn vx_op(&mut self, base: u16) -> Result<(), AsmError> {
+        let x = self.register()? as u16;
+        self.emit(base | x << 8);
+        Ok(())
+    }
+
+    fn define_macro(&mut self) -> Result<(), AsmError> {
+        let (name, line) = self.next()?;
+        let mut params = Vec::new();
+        loop {
+            let (tok, _) = self.next()?;
+            if tok == "{" {
+                break;
+            }
+            params.push(tok);
+        }
+        let body = self.block_body()?;
+        if !is_identifier(&name)
+            || self
+                .macros
+                .insert(name.to_string(), Macro { params, body })
+                .is_some()
+        {
+            return Err(AsmError::Redefined {
+                line,
+                name: name.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    // Declarations add to a string mode, so its characters can have
+    // different bodies.
+    fn define_string_mode(&mut self) -> Result<(), AsmError> {
+        let (name, _) = self.next()?;
+        let alphabet = self.string()?;
+        self.expect("{")?;
+        let body = self.block_body()?;
+        let mode = self.string_modes.entry(name.to_string()).or_default();
+        for (value, c) in alphabet.chars().enumerate() {
+            mode.insert(c, (value, body.clone()));
+        }
+        Ok(())
+    }
+
+    fn expand_macro(&mut self, name: &str, line: usize) -> Result<(), AsmError> {
+        let arity = self.macros[name].params.len();
+        let mut args = Vec::with_capacity(arity);
+        for _ in 0..arity {
+            args.push(self.next()?.0);
+        }
+
+        let mac = &self.macros[name];
+        let body = mac
+            .body
+            .iter()
+            .map(|tok| Token {
+                text: match mac.params.iter().position(|param| *param == tok.text) {
+                    Some(idx) => args[idx].clone(),
+                    None => tok.text.clone(),
+                },
+                line: tok.line,
+            })
+            .collect();
+        self.splice(body, line)
+    }
+
+    // Expands the body for each character of the string that follows,
+    // with `CHAR` its code, `INDEX` its position in the string and `VALUE`
+    // its position in the string mode's alphabet.
+    fn expand_string_mode(&mut self, name: &str, line: usize) -> Result<(), AsmError> {
+        let text = self.string()?;
+        let mode = &self.string_modes[name];
+        let mut tokens = Vec::new();
+        for (index, c) in text.chars().enumerate() {
+            let (value, body) = mode.get(&c).ok_or_else(|| AsmError::UndefinedName {
+                line,
+                name: c.to_string(),
+            })?;
+            tokens.extend(body.iter().map(|tok| {
+                let number = match &*tok.text {
+                    "CHAR" => Some(c as usize),
+                    "INDEX" => Some(index),
+                    "VALUE" => Some(*value),
+                    _ => None,
+                };
+                Token {
+                    text: number.map_or_else(|| tok.text.clone(), |n| Cow::Owned(n.to_string())),
+                    line: tok.line,
+                }
+            }));
+        }
+        self.splice(tokens, line)
+    }
+
+    // Queues an expansion to be assembled next.
+    fn splice(&mut self, tokens: Vec<Token<'a>>, line: usize) -> Result<(), AsmError> {
+        self.expansions += 1;
+        if self.expansions > MAX_EXPANSIONS {
+            return Err(AsmError::TooDeep { line });
+        }
+        self.tokens.splice(self.pos..self.pos, tokens);
+        Ok(())
+    }
+
+    // The tokens up to the `}` that closes an already read `{`.
+    fn block_body(&mut self) -> Result<Vec<Token<'a>>, AsmError> {
+        let start = self.pos;
+        let mut depth = 0;
+        loop {
+            let (tok, _) = self.next()?;
+            match &*tok {
+                "{" => depth += 1,
+                "}" if depth == 0 => return Ok(self.tokens[start..self.pos - 1].to_vec()),
+                "}" => depth -= 1,
+                _ => (),
+            }
+        }
+    }
+
+    // A `:calc` expression. Operators have no precedence and apply right
+    // to left, so `2 * 3 + 4` is 14; parentheses group.
+    fn calc(&mut self, depth: usize) -> Result<i64, AsmError> {
+        let lhs = self.calc_term(depth)?;
+        let (op, line) = match self.peek() {
+            Some(
+                "+" | "-" | "*" | "/" | "%" | "&" | "|" | "^" | "<<" | ">>" | "pow" | "min" | "max"
+                | "<" | "<=" | "==" | "!=" | ">=" | ">",
+            ) => self.next()?,
+            _ => return Ok(lhs),
+        };
+        if depth >= MAX_CALC_DEPTH {
+            return Err(AsmError::TooDeep { line });
+        }
+        let rhs = self.calc(depth + 1)?;
+
+        Ok(match &*op {
+            "+" => lhs.wrapping_add(rhs),
+            "-" => lhs.wrapping_sub(rhs),
+            "*" => lhs.wrapping_mul(rhs),
+            "/" | "%" if rhs == 0 => return Err(AsmError::ValueOutOfRange { line, value: rhs }),
+            "/" => lhs.wrapping_div(rhs),
+            "%" => lhs.wrapping_rem(rhs),
+            "&" => lhs & rhs,
+            "|" => lhs | rhs,
+            "^" => lhs ^ rhs,
+            "<<" => lhs.wrapping_shl(rhs as u32),
+            ">>" => lhs.wrapping_shr(rhs as u32),
+            "pow" => lhs.wrapping_pow(rhs as u32),
+            "min" => lhs.min(rhs),
+            "max" => lhs.max(rhs),
+            "<" => (lhs < rhs) as i64,
+            "<=" => (lhs <= rhs) as i64,
+            "==" => (lhs == rhs) as i64,
+            "!=" => (lhs != rhs) as i64,
+            ">=" => (lhs >= rhs) as i64,
+            _ => (lhs > rhs) as i64,
+        })
+    }
+
+    fn calc_term(&mut self, depth: usize) -> Result<i64, AsmError> {
+        let (tok, line) = self.next()?;
+        if depth >= MAX_CALC_DEPTH {
+            return Err(AsmError::TooDeep { line });
+        }
+        match &*tok {
+            "(" => {
+                let value = self.calc(depth + 1)?;
+                self.expect(")")?;
+                Ok(value)
+            }
+            "-" => Ok(self.calc_term(depth + 1)?.wrapping_neg()),
+            "~" => Ok(!self.calc_term(depth + 1)?),
+            "!" => Ok((self.calc_term(depth + 1)? == 0) as i64),
+            "HERE" => Ok(self.here() as i64),
+            _ => self.resolve_number(&tok, line),
+        }
+    }
+
+    fn define_label(&mut self, name: &str, line: usize) -> Result<(), AsmError> {
+        if name == "main" && self.entry_jump && self.out.len() == 2 {
+            // Nothing precedes main, so drop the jump to it
+            self.entry_jump = false;
+            self.out.clear();
+            for addr in self.labels.values_mut() {
+                *addr -= 2;
+            }
+        }
+        self.define_label_at(name, self.here(), line)
+    }
+
+    fn define_label_at(&mut self, name: &str, addr: u16, line: usize) -> Result<(), AsmError> {
+        if !is_identifier(name) || self.labels.insert(name.to_string(), addr).is_some() {
+            return Err(AsmError::Redefined {
+                line,
+                name: name.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn fixup(&mut self, name: &str, line: usize, kind: FixupKind) {
+        self.fixups.push(Fixup {
+            at: self.out.len(),
+            name: name.to_string(),
+            line,
+            kind,
+        });
+    }
+
+    fn patch_jump(&mut self, at: usize, target: u16) {
+        let word = 0x1000 | (target & 0x0FFF);
+        self.out[at..at + 2].copy_from_slice(&word.to_be_bytes());
+    }
+
+    fn here(&self) -> u16 {
+        self.origin.wrapping_add(self.out.len() as u16)
+    }
+
+    fn emit(&mut self, word: u16) {
+        self.out.extend_from_slice(&word.to_be_bytes());
+    }
+
+    fn next(&mut self) -> Result<(Cow<'a, str>, usize), AsmError> {
+        match self.tokens.get(self.pos) {
+            Some(tok) => {
+                self.pos += 1;
+                Ok((tok.text.clone(), tok.line))
+            }
+            None => Err(AsmError::UnexpectedEnd {
+                line: self.last_line(),
+            }),
+        }
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|tok| &*tok.text)
+    }
+
+    fn last_line(&self) -> usize {
+        self.tokens.last().map_or(1, |tok| tok.line)
+    }
+
+    fn expect(&mut self, want: &str) -> Result<(), AsmError> {
+        let (tok, line) = self.next()?;
+        if tok != want {
+            return Err(AsmError::UnexpectedToken {
+                line,
+                token: tok.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn string(&mut self) -> Result<String, AsmError> {
+        let (tok, line) = self.next()?;
+        tok.strip_prefix('"')
+            .and_then(|text| text.strip_suffix('"'))
+            .and_then(unescape)
+            .ok_or_else(|| AsmError::UnexpectedToken {
+                line,
+                token: tok.to_string(),
+            })
+    }
+
+    fn register(&mut self) -> Result<u8, AsmError> {
+        let (tok, line) = self.next()?;
+        self.lookup_register(&tok)
+            .ok_or_else(|| AsmError::UnexpectedToken {
+                line,
+                token: tok.to_string(),
+            })
+    }
+
+    fn number(&mut self) -> Result<i64, AsmError> {
+        let (tok, line) = self.next()?;
+        self.resolve_number(&tok, line)
+    }
+
+    fn immediate(&mut self) -> Result<u8, AsmError> {
+        let (tok, line) = self.next()?;
+        let value = self.resolve_number(&tok, line)?;
+        self.byte(value, line)
+    }
+
+    fn nibble(&mut self) -> Result<u16, AsmError> {
+        let (tok, line) = self.next()?;
+        let value = self.resolve_number(&tok, line)?;
+        Ok(self.check(value, 0xF, line)? as u16)
+    }
+
+    fn resolve_number(&self, tok: &str, line: usize) -> Result<i64, AsmError> {
+        self.lookup_number(tok).ok_or_else(|| {
+            if is_identifier(tok) {
+                AsmError::UndefinedName {
+                    line,
+                    name: tok.to_string(),
+                }
+            } else {
+                AsmError::UnexpectedToken {
+                    line,
+                    token: tok.to_string(),
+                }
+            }
+        })
+    }
+
+    // Numbers, constants and labels already defined.
+    fn lookup_number(&self, tok: &str) -> Option<i64> {
+        parse_number(tok)
+            .or_else(|| self.consts.get(tok).copied())
+            .or_else(|| self.labels.get(tok).map(|&addr| addr as i64))
+    }
+
+    fn lookup_register(&self, tok: &str) -> Option<u8> {
+        if let Some(&reg) = self.aliases.get(tok) {
+            return Some(reg);
+        }
+        let digit = tok.strip_prefix('v').or_else(|| tok.strip_prefix('V'))?;
+        if digit.len() != 1 {
+            return None;
+        }
+        u8::from_str_radix(digit, 16).ok()
+    }
+
+    // Bytes may be given signed, as Octo allows.
+    fn byte(&self, value: i64, line: usize) -> Result<u8, AsmError> {
+        if !(-128..=255).contains(&value) {
+            return Err(AsmError::ValueOutOfRange { line, value });
+        }
+        Ok(value as u8)
+    }
+
+    fn check(&self, value: i64, max: i64, line: usize) -> Result<i64, AsmError> {
+        if !(0..=max).contains(&value) {
+            return Err(AsmError::ValueOutOfRange { line, value });
+        }
+        Ok(value)
+    }
+}
+
+fn parse_number(tok: &str) -> Option<i64> {
+    let (negative, digits) = match tok.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, tok),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(bin) = digits.strip_prefix("0b") {
+        i64::from_str_radix(bin, 2).ok()?
+    } else {
+        digits.parse().ok()?
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn is_identifier(tok: &str) -> bool {
+    let mut chars = tok.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+// Splits a line into tokens up to a `#` comment. Strings keep their quotes
+// and may hold spaces.
+fn tokenize(line: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut rest = line.trim_start();
+    while !rest.is_empty() && !rest.starts_with('#') {
+        let len = if rest.starts_with('"') {
+            string_len(rest)
+        } else {
+            rest.find(|c: char| c.is_whitespace() || c == '#')
+                .unwrap_or(rest.len())
+        };
+        tokens.push(&rest[..len]);
+        rest = rest[len..].trim_start();
+    }
+    tokens
+}
+
+// The length of the string literal `text` starts with, quotes included.
+fn string_len(text: &str) -> usize {
+    let mut escaped = false;
+    for (idx, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return idx + 1,
+            _ => (),
+        }
+    }
+    text.len()
+}
+
+fn unescape(text: &str) -> Option<String> {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        out.push(match c {
+            '\\' => match chars.next()? {
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                'v' => '\u{b}',
+                '0' => '\0',
+                c @ ('\\' | '"') => c,
+                _ => return None,
+            },
+            '"' => return None,
+            c => c,
+        });
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm::disassemble_rom;
+
+    fn listing(source: &str) -> Vec<String> {
+        let rom = assemble(source).unwrap();
+        disassemble_rom(&rom, START_ADDRESS)
+            .into_iter()
+            .map(|line| line.text)
+            .collect()
+    }
+
+    #[test]
+    fn round_trips_through_disasm() {
+        let source = "
+            : main
+                clear
+                v0 := 0x12
+                v1 += 3
+                v2 := v0
+                v3 ^= v1
+                i := sprite
+                sprite v0 v1 4
+                i := long sprite
+                if v0 != 5 then v1 := 0
+                draw
+                jump main
+            : draw
+                return
+            : sprite
+                0xFF 0xFF
+        ";
+        let lines = listing(source);
+        assert_eq!(
+            lines,
+            [
+                "CLS",
+                "LD V0, 0x12",
+                "ADD V1, 0x03",
+                "LD V2, V0",
+                "XOR V3, V1",
+                "LD I, 0x21C",
+                "DRW V0, V1, 4",
+                "LD I, long 0x021C",
+                "SE V0, 0x05",
+                "LD V1, 0x00",
+                "CALL 0x21A",
+                "JP 0x200",
+                "RET",
+                "DW 0xFFFF",
+            ]
+        );
+    }
+
+    #[test]
+    fn jumps_to_main_unless_it_comes_first() {
+        assert_eq!(listing(": main v0 := 1"), ["LD V0, 0x01"]);
+        assert_eq!(
+            listing(": data 1 2 : main jump data"),
+            ["JP 0x204", "DW 0x0102", "JP 0x202"]
+        );
+        assert_eq!(
+            assemble("v0 := 1"),
+            Err(AsmError::UndefinedName {
+                line: 1,
+                name: "main".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn orders_through_vf() {
+        // vF := 5, vF -= v1: no borrow unless v1 > 5
+        assert_eq!(
+            listing(": main if v1 > 5 then v2 := 1"),
+            ["LD VF, 0x05", "SUB VF, V1", "SNE VF, 0x00", "LD V2, 0x01"]
+        );
+        assert_eq!(
+            listing(": main if v1 >= v3 then v2 := 1"),
+            ["LD VF, V3", "SUBN VF, V1", "SNE VF, 0x01", "LD V2, 0x01"]
+        );
+    }
+
+    #[test]
+    fn expands_macros_and_calc() {
+        let source = "
+            :macro set REG VALUE { REG := VALUE }
+            :calc TOP { 2 * 3 + 4 }
+            :calc BOTTOM { ( 2 * 3 ) + 4 }
+            : main
+                set v0 TOP
+                set v1 BOTTOM
+        ";
+        // Right to left: 2 * (3 + 4)
+        assert_eq!(listing(source), ["LD V0, 0x0E", "LD V1, 0x0A"]);
+    }
+
+    #[test]
+    fn expands_string_modes() {
+        let source = r#"
+            :stringmode text "AB " { :byte { VALUE + INDEX * 16 } }
+            : main
+                text "B A"
+        "#;
+        assert_eq!(assemble(source).unwrap(), [0x01, 0x12, 0x20]);
+    }
+
+    #[test]
+    fn unpacks_and_names_operands() {
+        let source = "
+            : main
+                :unpack 0xA data
+                :next operand
+                v2 := 0
+            : data
+        ";
+        assert_eq!(
+            listing(source),
+            ["LD V0, 0xA2", "LD V1, 0x06", "LD V2, 0x00"]
+        );
+        assert_eq!(
+            listing(": main :unpack long 0x1234"),
+            ["LD V0, 0x12", "LD V1, 0x34"]
+        );
+    }
+
+    #[test]
+    fn rejects_runaway_expansion() {
+        assert_eq!(
+            assemble(":macro forever { forever } : main forever"),
+            Err(AsmError::TooDeep { line: 1 })
+        );
+        let deep = alloc::format!(":calc X {{ {}1 }} : main", "1 + ".repeat(1000));
+        assert_eq!(assemble(&deep), Err(AsmError::TooDeep { line: 1 }));
+    }
+
+    #[test]
+    fn reports_the_line_of_an_error() {
+        assert_eq!(
+            assemble(": main\n  v0 := 1\n  v0 := 300"),
+            Err(AsmError::ValueOutOfRange {
+                line: 3,
+                value: 300,
+            })
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+use crate::Hachi;
+
+// A page of RAM above where ROMs normally live. Programs written for the
+// battery extension keep their save data here.
+pub const DEFAULT_BATTERY_RAM: Range<u16> = 0xE00..0xF00;
+
+// Where the battery-backed region is persisted between sessions: a file,
+// browser localStorage, flash on a handheld.
+pub trait BatteryStorage {
+    // Fills `buf` with the saved contents. Returns false if nothing has been
+    // saved yet, in which case the region starts zeroed.
+    fn load(&mut self, buf: &mut [u8]) -> bool;
+
+    fn save(&mut self, data: &[u8]);
+}
+
+// Keeps the region in memory only; useful for tests and as a template.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBattery {
+    pub data: Option<Vec<u8>>,
+}
+
+impl BatteryStorage for MemoryBattery {
+    fn load(&mut self, buf: &mut [u8]) -> bool {
+        match &self.data {
+            Some(data) => {
+                let len = data.len().min(buf.len());
+                buf[..len].copy_from_slice(&data[..len]);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn save(&mut self, data: &[u8]) {
+        self.data = Some(data.to_vec());
+    }
+}
+
+pub(crate) struct BatteryRam {
+    range: Range<usize>,
+    storage: Box<dyn BatteryStorage>,
+    dirty: bool,
+}
+
+impl fmt::Debug for BatteryRam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BatteryRam")
+            .field("range", &self.range)
+            .field("dirty", &self.dirty)
+            .finish()
+    }
+}
+
+impl Hachi {
+    // Opts in to the battery RAM extension: `range` is loaded from
+    // `storage` now and written back whenever the program changes it (at
+    // most once per frame) or on `flush_battery_ram`.
+    pub fn enable_battery_ram(&mut self, range: Range<u16>, storage: Box<dyn BatteryStorage>) {
+        let start = (range.start as usize).min(self.ram.len());
+        let end = (range.end as usize).clamp(start, self.ram.len());
+
+        self.battery = Some(BatteryRam {
+            range: start..end,
+            storage,
+            dirty: false,
+        });
+        self.reload_battery_ram();
+    }
+
+    // Flushes and detaches the storage, handing it back to the caller.
+    pub fn disable_battery_ram(&mut self) -> Option<Box<dyn BatteryStorage>> {
+        self.flush_battery_ram();
+        self.battery.take().map(|battery| battery.storage)
+    }
+
+    pub fn battery_ram_range(&self) -> Option<Range<u16>> {
+        self.battery
+            .as_ref()
+            .map(|battery| battery.range.start as u16..battery.range.end as u16)
+    }
+
+    pub fn flush_battery_ram(&mut self) {
+        if let Some(battery) = &mut self.battery {
+            if battery.dirty {
+                battery.storage.save(&self.ram[battery.range.clone()]);
+                battery.dirty = false;
+            }
+        }
+    }
+
+    // Brings the region back after RAM has been wiped.
+    pub(crate) fn reload_battery_ram(&mut self) {
+        if let Some(battery) = &mut self.battery {
+            let region = &mut self.ram[battery.range.clone()];
+            if !battery.storage.load(region) {
+                region.fill(0);
+            }
+        }
+    }
+
+    // Keeps the region inside RAM after it shrinks.
+    pub(crate) fn clamp_battery_ram(&mut self, len: usize) {
+        if let Some(battery) = &mut self.battery {
+            battery.range.end = battery.range.end.min(len);
+            battery.range.start = battery.range.start.min(battery.range.end);
+        }
+    }
+
+    // Called after the program writes `len` bytes at `addr`.
+    pub(crate) fn note_ram_write(&mut self, addr: usize, len: usize) {
+        if let Some(battery) = &mut self.battery {
+            if addr < battery.range.end && addr + len > battery.range.start {
+                battery.dirty = true;
+            }
+        }
+    }
+}
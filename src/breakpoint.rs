@@ -0,0 +1,37 @@
+use crate::{Hachi, TickStatus};
+
+impl Hachi {
+    // Stops `tick` before the instruction at `addr` is executed.
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    // Reports a breakpoint at PC the first time it is reached, letting the
+    // following tick step past it.
+    pub(crate) fn check_breakpoint(&mut self) -> Option<TickStatus> {
+        let pc = self.program_counter;
+        if self.breakpoints.is_empty() || !self.breakpoints.contains(&pc) {
+            self.stopped_at = None;
+            return None;
+        }
+        if self.stopped_at == Some(pc) {
+            self.stopped_at = None;
+            return None;
+        }
+
+        self.stopped_at = Some(pc);
+        Some(TickStatus::Breakpoint { pc })
+    }
+}
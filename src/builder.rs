@@ -0,0 +1,215 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::host::Xorshift;
+use crate::{ErrorPolicy, Hachi, LoadError, Quirks, Variant, DEFAULT_TIMER_HZ, MAX_STACK_DEPTH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildError {
+    // Both a per-frame instruction count and a clock rate were given.
+    ConflictingSpeeds,
+    ZeroSpeed,
+    ZeroTimerFrequency,
+    ZeroStackDepth,
+    StackDepthOutOfRange { depth: usize },
+    StartAddressOutOfRange { addr: u16 },
+    RamSizeOutOfRange { len: usize },
+    Rom(LoadError),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            BuildError::ConflictingSpeeds => write!(
+                f,
+                "set either instructions per frame or instructions per second, not both"
+            ),
+            BuildError::ZeroSpeed => write!(f, "CPU speed must be at least one instruction"),
+            BuildError::ZeroTimerFrequency => write!(f, "timer frequency must be non-zero"),
+            BuildError::ZeroStackDepth => write!(f, "stack must hold at least one call"),
+            BuildError::StackDepthOutOfRange { depth } => {
+                write!(f, "stack depth {} is over {}", depth, MAX_STACK_DEPTH)
+            }
+            BuildError::StartAddressOutOfRange { addr } => {
+                write!(f, "start address {:03X} is outside RAM", addr)
+            }
+            BuildError::RamSizeOutOfRange { len } => {
+                write!(f, "RAM size {} bytes is too small or over 64 KB", len)
+            }
+            BuildError::Rom(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl core::error::Error for BuildError {}
+
+// Collects construction-time options and checks them together, instead of
+// each setter quietly clamping bad values:
+//
+//     let hachi = Hachi::builder()
+//         .variant(Variant::SuperChip)
+//         .instructions_per_second(1000)
+//         .seed(7)
+//         .rom(&rom)
+//         .build()?;
+#[derive(Debug, Clone, Default)]
+pub struct HachiBuilder {
+    variant: Variant,
+    quirks: Option<Quirks>,
+    instructions_per_frame: Option<u32>,
+    instructions_per_second: Option<u32>,
+    timer_hz: Option<u32>,
+    stack_depth: Option<usize>,
+    seed: Option<u64>,
+    start_address: Option<u16>,
+    ram_size: Option<usize>,
+    error_policy: ErrorPolicy,
+    rom: Option<Vec<u8>>,
+}
+
+impl HachiBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn variant(mut self, variant: Variant) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    // Defaults to `Quirks::for_variant` of the chosen variant.
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = Some(quirks);
+        self
+    }
+
+    pub fn instructions_per_frame(mut self, ipf: u32) -> Self {
+        self.instructions_per_frame = Some(ipf);
+        self
+    }
+
+    pub fn instructions_per_second(mut self, hz: u32) -> Self {
+        self.instructions_per_second = Some(hz);
+        self
+    }
+
+    pub fn timer_frequency(mut self, hz: u32) -> Self {
+        self.timer_hz = Some(hz);
+        self
+    }
+
+    pub fn stack_depth(mut self, depth: usize) -> Self {
+        self.stack_depth = Some(depth);
+        self
+    }
+
+    // Uses a `Xorshift` source seeded with `seed` for CXNN.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    // Defaults to the variant's own start address.
+    pub fn start_address(mut self, addr: u16) -> Self {
+        self.start_address = Some(addr);
+        self
+    }
+
+    // Defaults to the variant's own size.
+    pub fn ram_size(mut self, len: usize) -> Self {
+        self.ram_size = Some(len);
+        self
+    }
+
+    pub fn error_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.error_policy = policy;
+        self
+    }
+
+    // Loaded after everything else is set up, so it may use the RAM the
+    // chosen variant provides.
+    pub fn rom(mut self, rom: &[u8]) -> Self {
+        self.rom = Some(rom.to_vec());
+        self
+    }
+
+    pub fn build(self) -> Result<Hachi, BuildError> {
+        if self.instructions_per_frame.is_some() && self.instructions_per_second.is_some() {
+            return Err(BuildError::ConflictingSpeeds);
+        }
+        if self.instructions_per_frame == Some(0) || self.instructions_per_second == Some(0) {
+            return Err(BuildError::ZeroSpeed);
+        }
+        if self.timer_hz == Some(0) {
+            return Err(BuildError::ZeroTimerFrequency);
+        }
+        if self.stack_depth == Some(0) {
+            return Err(BuildError::ZeroStackDepth);
+        }
+
+        let quirks = self
+            .quirks
+            .unwrap_or_else(|| Quirks::for_variant(self.variant));
+        let mut hachi = Hachi::new_with_quirks(quirks);
+        hachi.set_variant(self.variant);
+        hachi.set_timer_frequency(self.timer_hz.unwrap_or(DEFAULT_TIMER_HZ));
+        if let Some(ipf) = self.instructions_per_frame {
+            hachi.set_instructions_per_frame(ipf);
+        }
+        if let Some(hz) = self.instructions_per_second {
+            hachi.set_instructions_per_second(hz);
+        }
+        if let Some(depth) = self.stack_depth {
+            if !hachi.set_stack_depth(depth) {
+                return Err(BuildError::StackDepthOutOfRange { depth });
+            }
+        }
+        if let Some(seed) = self.seed {
+            hachi.set_random_source(Box::new(Xorshift::new(seed)));
+        }
+        if let Some(len) = self.ram_size {
+            if !hachi.set_ram_size(Some(len)) {
+                return Err(BuildError::RamSizeOutOfRange { len });
+            }
+        }
+        if let Some(addr) = self.start_address {
+            if !hachi.set_start_address(addr) {
+                return Err(BuildError::StartAddressOutOfRange { addr });
+            }
+        }
+        hachi.set_error_policy(self.error_policy);
+        if let Some(rom) = &self.rom {
+            hachi.load(rom).map_err(BuildError::Rom)?;
+        }
+
+        Ok(hachi)
+    }
+}
+
+impl Hachi {
+    pub fn builder() -> HachiBuilder {
+        HachiBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_quirks_to_the_variants_own() {
+        for variant in [Variant::Chip8, Variant::SuperChip, Variant::XoChip] {
+            let hachi = Hachi::builder().variant(variant).build().unwrap();
+            assert_eq!(hachi.quirks(), Quirks::for_variant(variant));
+        }
+
+        let quirks = Quirks::schip_modern();
+        let hachi = Hachi::builder()
+            .variant(Variant::XoChip)
+            .quirks(quirks)
+            .build()
+            .unwrap();
+        assert_eq!(hachi.quirks(), quirks);
+    }
+}
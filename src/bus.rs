@@ -0,0 +1,183 @@
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt;
+use core::ops::Range;
+
+use crate::Hachi;
+
+// A memory-mapped device. Reads and writes the program makes through
+// FX33/FX55/FX65 inside the device's range are routed here, with `offset`
+// relative to the start of the range. Writes also land in the RAM beneath
+// the device so the rest of the machine sees a consistent memory image.
+pub trait Peripheral: Any {
+    fn read(&mut self, offset: u16) -> u8;
+
+    fn write(&mut self, offset: u16, value: u8);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PeripheralId(usize);
+
+pub(crate) struct Mapping {
+    id: PeripheralId,
+    range: Range<usize>,
+    device: Box<dyn Peripheral>,
+}
+
+impl fmt::Debug for Mapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mapping")
+            .field("id", &self.id)
+            .field("range", &self.range)
+            .finish()
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Bus {
+    mappings: Vec<Mapping>,
+    next_id: usize,
+}
+
+impl Hachi {
+    // Maps `device` over `range`. Later mappings shadow earlier ones where
+    // they overlap.
+    pub fn attach_peripheral(
+        &mut self,
+        range: Range<u16>,
+        device: Box<dyn Peripheral>,
+    ) -> PeripheralId {
+        let id = PeripheralId(self.bus.next_id);
+        self.bus.next_id += 1;
+
+        let start = (range.start as usize).min(self.ram.len());
+        let end = (range.end as usize).clamp(start, self.ram.len());
+        self.bus.mappings.push(Mapping {
+            id,
+            range: start..end,
+            device,
+        });
+        id
+    }
+
+    pub fn detach_peripheral(&mut self, id: PeripheralId) -> Option<Box<dyn Peripheral>> {
+        let idx = self.bus.mappings.iter().position(|m| m.id == id)?;
+        Some(self.bus.mappings.remove(idx).device)
+    }
+
+    // Borrows an attached device as its concrete type, e.g. to exchange
+    // data with a `SerialPort` from the host side.
+    pub fn peripheral_mut<T: Peripheral>(&mut self, id: PeripheralId) -> Option<&mut T> {
+        let mapping = self.bus.mappings.iter_mut().find(|m| m.id == id)?;
+        let device: &mut dyn Any = mapping.device.as_mut();
+        device.downcast_mut::<T>()
+    }
+
+    fn mapping_at(&mut self, addr: usize) -> Option<&mut Mapping> {
+        self.bus
+            .mappings
+            .iter_mut()
+            .rev()
+            .find(|m| m.range.contains(&addr))
+    }
+
+    pub(crate) fn bus_read(&mut self, addr: usize) -> u8 {
+        if !self.bus.mappings.is_empty() {
+            if let Some(mapping) = self.mapping_at(addr) {
+                let offset = (addr - mapping.range.start) as u16;
+                return mapping.device.read(offset);
+            }
+        }
+        self.ram[addr]
+    }
+
+    pub(crate) fn bus_write(&mut self, addr: usize, value: u8) {
+        self.ram[addr] = value;
+        if !self.bus.mappings.is_empty() {
+            if let Some(mapping) = self.mapping_at(addr) {
+                let offset = (addr - mapping.range.start) as u16;
+                mapping.device.write(offset, value);
+            }
+        }
+    }
+}
+
+// A toy serial port occupying two bytes:
+//
+//     base + 0  status (read): bit 0 set when a received byte is waiting,
+//               bit 1 set when the transmitter can accept a byte
+//     base + 1  data: reading takes the next received byte (0 if none),
+//               writing transmits a byte
+//
+// From a ROM, `i := base  load v1` reads status into v0 and data into v1;
+// writing is `i := base + 1  save v0`.
+#[derive(Debug, Default)]
+pub struct SerialPort {
+    rx: VecDeque<u8>,
+    tx: VecDeque<u8>,
+    tx_capacity: Option<usize>,
+}
+
+pub const SERIAL_STATUS_RX_READY: u8 = 0b01;
+pub const SERIAL_STATUS_TX_READY: u8 = 0b10;
+
+impl SerialPort {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Limits how many transmitted bytes may pile up before the host drains
+    // them; while full, the status register reports the transmitter busy
+    // and further writes are dropped.
+    pub fn with_tx_capacity(capacity: usize) -> Self {
+        Self {
+            tx_capacity: Some(capacity),
+            ..Self::default()
+        }
+    }
+
+    // Queues a byte for the program to read.
+    pub fn send_to_rom(&mut self, byte: u8) {
+        self.rx.push_back(byte);
+    }
+
+    // Takes the next byte the program transmitted.
+    pub fn receive_from_rom(&mut self) -> Option<u8> {
+        self.tx.pop_front()
+    }
+
+    pub fn drain_transmitted(&mut self) -> Vec<u8> {
+        self.tx.drain(..).collect()
+    }
+
+    fn tx_ready(&self) -> bool {
+        self.tx_capacity.is_none_or(|cap| self.tx.len() < cap)
+    }
+}
+
+impl Peripheral for SerialPort {
+    fn read(&mut self, offset: u16) -> u8 {
+        match offset {
+            0 => {
+                let mut status = 0;
+                if !self.rx.is_empty() {
+                    status |= SERIAL_STATUS_RX_READY;
+                }
+                if self.tx_ready() {
+                    status |= SERIAL_STATUS_TX_READY;
+                }
+                status
+            }
+            1 => self.rx.pop_front().unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, offset: u16, value: u8) {
+        if offset == 1 && self.tx_ready() {
+            self.tx.push_back(value);
+        }
+    }
+}
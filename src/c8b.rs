@@ -0,0 +1,400 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{Hachi, LoadError, Orientation, Quirks, Rotation, Variant};
+
+const MAGIC: [u8; 3] = *b"CBF";
+const VERSION: u8 = 0;
+const HEADER_LEN: usize = 8;
+const BYTECODE_ENTRY_LEN: usize = 5;
+
+// Platform IDs of the builds this core can run.
+const PLATFORM_CHIP8: u8 = 0x00;
+const PLATFORM_CHIP8X: u8 = 0x09;
+const PLATFORM_SCHIP_10: u8 = 0x10;
+const PLATFORM_SCHIP_11: u8 = 0x11;
+#[cfg(feature = "megachip")]
+const PLATFORM_MEGACHIP: u8 = 0x12;
+const PLATFORM_XOCHIP: u8 = 0x13;
+
+// Property keys this core uses.
+const PROP_DESIGNER: u8 = 0x00;
+const PROP_DESCRIPTION: u8 = 0x01;
+const PROP_TICKRATE: u8 = 0x04;
+const PROP_PALETTE: u8 = 0x05;
+const PROP_ROTATION: u8 = 0x07;
+
+// A CHIP-8 binary (.c8b): one or more builds of a program for different
+// platforms, plus the settings and credits to run it with. All numbers
+// are big-endian.
+//
+//     0  "CBF"
+//     3  version, 0
+//     4  bytecode table offset (2)
+//     6  property table offset (2), 0 if there is none
+//
+// The bytecode table lists each build as a platform ID (1), bytecode
+// offset (2) and length (2), up to the property table or the first
+// bytecode. Properties are a key (1), a length (1) and that many bytes of
+// data, up to the next bytecode or the end of the file. The ones used here
+// are
+//
+//     0x00  designer, UTF-8
+//     0x01  description, UTF-8
+//     0x04  instructions per frame, 1 to 4 bytes
+//     0x05  palette, RGB triples in `set_palette` order
+//     0x07  rotation, quarter turns clockwise (1)
+//
+// and the rest are skipped. The program runs with its variant's quirks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct C8b {
+    pub programs: Vec<C8bProgram>,
+    pub author: Option<String>,
+    pub description: Option<String>,
+    pub tickrate: Option<u32>,
+    pub palette: Vec<[u8; 3]>,
+    pub rotation: Option<Rotation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct C8bProgram {
+    // The file's platform ID, not `Variant`'s numbering.
+    pub platform: u8,
+    pub bytecode: Vec<u8>,
+}
+
+impl C8bProgram {
+    // The variant that runs this build, if this core supports the platform.
+    pub fn variant(&self) -> Option<Variant> {
+        match self.platform {
+            PLATFORM_CHIP8 => Some(Variant::Chip8),
+            PLATFORM_CHIP8X => Some(Variant::Chip8X),
+            PLATFORM_SCHIP_10 | PLATFORM_SCHIP_11 => Some(Variant::SuperChip),
+            #[cfg(feature = "megachip")]
+            PLATFORM_MEGACHIP => Some(Variant::MegaChip),
+            PLATFORM_XOCHIP => Some(Variant::XoChip),
+            _ => None,
+        }
+    }
+}
+
+impl C8b {
+    // The first build this core can run.
+    pub fn supported_program(&self) -> Option<&C8bProgram> {
+        self.programs
+            .iter()
+            .find(|program| program.variant().is_some())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum C8bError {
+    BadMagic,
+    UnsupportedVersion(u8),
+    // A table or bytecode runs past the end of the file.
+    Truncated,
+    // None of the builds are for a platform this core supports.
+    NoSupportedPlatform,
+    Load(LoadError),
+}
+
+impl fmt::Display for C8bError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            C8bError::BadMagic => write!(f, "not a CHIP-8 binary"),
+            C8bError::UnsupportedVersion(version) => {
+                write!(f, "unsupported CHIP-8 binary version {}", version)
+            }
+            C8bError::Truncated => write!(f, "CHIP-8 binary ends unexpectedly"),
+            C8bError::NoSupportedPlatform => {
+                write!(f, "CHIP-8 binary has no build for a supported platform")
+            }
+            C8bError::Load(err) => write!(f, "CHIP-8 binary program: {}", err),
+        }
+    }
+}
+
+impl core::error::Error for C8bError {}
+
+pub fn parse_c8b(data: &[u8]) -> Result<C8b, C8bError> {
+    let mut reader = Reader { data, pos: 0 };
+
+    if reader.bytes(MAGIC.len()).map_err(|_| C8bError::BadMagic)? != MAGIC {
+        return Err(C8bError::BadMagic);
+    }
+    let version = reader.u8()?;
+    if version != VERSION {
+        return Err(C8bError::UnsupportedVersion(version));
+    }
+    let bytecode_table = reader.u16()? as usize;
+    let property_table = reader.u16()? as usize;
+    if bytecode_table < HEADER_LEN || (property_table != 0 && property_table < HEADER_LEN) {
+        return Err(C8bError::Truncated);
+    }
+
+    // The bytecode table has no count. It ends where the next thing in the
+    // file starts, which is the property table or the first bytecode.
+    let mut c8b = C8b::default();
+    let mut bytecode_offsets = Vec::new();
+    let mut table_end = data.len();
+    if property_table > bytecode_table {
+        table_end = table_end.min(property_table);
+    }
+    reader.pos = bytecode_table;
+    while reader.pos + BYTECODE_ENTRY_LEN <= table_end {
+        let platform = reader.u8()?;
+        let offset = reader.u16()? as usize;
+        let len = reader.u16()? as usize;
+        let bytecode = data.get(offset..offset + len).ok_or(C8bError::Truncated)?;
+        if offset >= bytecode_table {
+            table_end = table_end.min(offset);
+        }
+        bytecode_offsets.push(offset);
+        c8b.programs.push(C8bProgram {
+            platform,
+            bytecode: bytecode.to_vec(),
+        });
+    }
+    if c8b.programs.is_empty() {
+        return Err(C8bError::Truncated);
+    }
+
+    if property_table != 0 {
+        let properties_end = bytecode_offsets
+            .iter()
+            .copied()
+            .chain(Some(bytecode_table))
+            .filter(|&offset| offset > property_table)
+            .min()
+            .unwrap_or(data.len());
+        reader.pos = property_table;
+        while reader.pos < properties_end {
+            let key = reader.u8()?;
+            let len = reader.u8()? as usize;
+            let value = reader.bytes(len)?;
+            read_property(&mut c8b, key, value);
+        }
+    }
+
+    Ok(c8b)
+}
+
+fn read_property(c8b: &mut C8b, key: u8, value: &[u8]) {
+    match key {
+        PROP_DESIGNER => c8b.author = Some(String::from_utf8_lossy(value).into_owned()),
+        PROP_DESCRIPTION => c8b.description = Some(String::from_utf8_lossy(value).into_owned()),
+        PROP_TICKRATE if (1..=4).contains(&value.len()) => {
+            c8b.tickrate = Some(
+                value
+                    .iter()
+                    .fold(0, |rate, &byte| (rate << 8) | byte as u32),
+            )
+        }
+        PROP_PALETTE => {
+            c8b.palette = value
+                .chunks_exact(3)
+                .map(|rgb| [rgb[0], rgb[1], rgb[2]])
+                .collect()
+        }
+        PROP_ROTATION if value.len() == 1 => {
+            c8b.rotation = Some(match value[0] % 4 {
+                0 => Rotation::None,
+                1 => Rotation::Cw90,
+                2 => Rotation::Cw180,
+                _ => Rotation::Cw270,
+            })
+        }
+        _ => (),
+    }
+}
+
+impl Hachi {
+    // Loads the first build this core can run, switching to its variant
+    // and that variant's quirks and applying the file's speed, palette and
+    // rotation. Nothing changes unless the build fits in the variant's RAM.
+    // The credits are left to the frontend in the returned container.
+    pub fn load_c8b(&mut self, data: &[u8]) -> Result<C8b, C8bError> {
+        let c8b = parse_c8b(data)?;
+        let program = c8b
+            .supported_program()
+            .ok_or(C8bError::NoSupportedPlatform)?;
+        let variant = program.variant().ok_or(C8bError::NoSupportedPlatform)?;
+        self.check_rom_fits(variant, program.bytecode.len())
+            .map_err(C8bError::Load)?;
+
+        self.set_variant(variant);
+        self.set_quirks(Quirks::for_variant(variant));
+        if let Some(tickrate) = c8b.tickrate {
+            self.set_instructions_per_frame(tickrate);
+        }
+        if !c8b.palette.is_empty() {
+            let mut palette = self.palette();
+            for (entry, &[r, g, b]) in palette.iter_mut().zip(&c8b.palette) {
+                *entry = [r, g, b, 0xFF];
+            }
+            self.set_palette(palette);
+        }
+        if let Some(rotation) = c8b.rotation {
+            self.set_orientation(Orientation {
+                rotation,
+                ..self.orientation()
+            });
+        }
+        self.reset();
+        self.load(&program.bytecode).map_err(C8bError::Load)?;
+
+        Ok(c8b)
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], C8bError> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or(C8bError::Truncated)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, C8bError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, C8bError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Header, bytecode table, property table, then the bytecode.
+    fn fixture(builds: &[(u8, &[u8])], properties: &[(u8, &[u8])]) -> Vec<u8> {
+        let table_len = builds.len() * BYTECODE_ENTRY_LEN;
+        let properties_len: usize = properties.iter().map(|(_, value)| 2 + value.len()).sum();
+        let property_table = match properties {
+            [] => 0,
+            _ => HEADER_LEN + table_len,
+        };
+
+        let mut out = MAGIC.to_vec();
+        out.push(VERSION);
+        out.extend_from_slice(&(HEADER_LEN as u16).to_be_bytes());
+        out.extend_from_slice(&(property_table as u16).to_be_bytes());
+        let mut offset = HEADER_LEN + table_len + properties_len;
+        for (platform, bytecode) in builds {
+            out.push(*platform);
+            out.extend_from_slice(&(offset as u16).to_be_bytes());
+            out.extend_from_slice(&(bytecode.len() as u16).to_be_bytes());
+            offset += bytecode.len();
+        }
+        for (key, value) in properties {
+            out.push(*key);
+            out.push(value.len() as u8);
+            out.extend_from_slice(value);
+        }
+        for (_, bytecode) in builds {
+            out.extend_from_slice(bytecode);
+        }
+        out
+    }
+
+    #[test]
+    fn parses_builds_and_properties() {
+        let data = fixture(
+            &[(0x7F, &[0xAA]), (PLATFORM_XOCHIP, &[0x00, 0xE0])],
+            &[
+                (PROP_DESIGNER, b"someone"),
+                (0x02, b"2024"),
+                (PROP_TICKRATE, &[0x01, 0xF4]),
+                (PROP_PALETTE, &[1, 2, 3, 4, 5, 6]),
+                (PROP_ROTATION, &[1]),
+            ],
+        );
+        let c8b = parse_c8b(&data).unwrap();
+
+        assert_eq!(c8b.programs.len(), 2);
+        assert_eq!(c8b.programs[0].variant(), None);
+        let program = c8b.supported_program().unwrap();
+        assert_eq!(program.variant(), Some(Variant::XoChip));
+        assert_eq!(program.bytecode, [0x00, 0xE0]);
+        assert_eq!(c8b.author.as_deref(), Some("someone"));
+        assert_eq!(c8b.description, None);
+        assert_eq!(c8b.tickrate, Some(500));
+        assert_eq!(c8b.palette, [[1, 2, 3], [4, 5, 6]]);
+        assert_eq!(c8b.rotation, Some(Rotation::Cw90));
+    }
+
+    #[test]
+    fn maps_platform_ids() {
+        let variant = |platform| {
+            C8bProgram {
+                platform,
+                bytecode: Vec::new(),
+            }
+            .variant()
+        };
+        assert_eq!(variant(PLATFORM_CHIP8), Some(Variant::Chip8));
+        assert_eq!(variant(PLATFORM_CHIP8X), Some(Variant::Chip8X));
+        assert_eq!(variant(PLATFORM_SCHIP_10), Some(Variant::SuperChip));
+        assert_eq!(variant(PLATFORM_SCHIP_11), Some(Variant::SuperChip));
+        assert_eq!(variant(PLATFORM_XOCHIP), Some(Variant::XoChip));
+        assert_eq!(variant(0x01), None);
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        let data = fixture(&[(PLATFORM_CHIP8, &[0x12, 0x00])], &[]);
+        assert_eq!(parse_c8b(b"CB"), Err(C8bError::BadMagic));
+        assert_eq!(parse_c8b(b"CBX\0\0\x08\0\0"), Err(C8bError::BadMagic));
+
+        let mut newer = data.clone();
+        newer[3] = 1;
+        assert_eq!(parse_c8b(&newer), Err(C8bError::UnsupportedVersion(1)));
+        assert_eq!(parse_c8b(&data[..data.len() - 1]), Err(C8bError::Truncated));
+
+        let data = fixture(&[(0x7F, &[0x12, 0x00])], &[]);
+        assert_eq!(
+            Hachi::new().load_c8b(&data),
+            Err(C8bError::NoSupportedPlatform)
+        );
+    }
+
+    #[test]
+    fn loads_the_supported_build() {
+        let data = fixture(
+            &[(PLATFORM_SCHIP_11, &[0x00, 0xFF])],
+            &[(PROP_TICKRATE, &[30])],
+        );
+        let mut hachi = Hachi::new();
+        hachi.load_c8b(&data).unwrap();
+        assert_eq!(hachi.variant(), Variant::SuperChip);
+        assert_eq!(hachi.quirks(), Quirks::for_variant(Variant::SuperChip));
+        assert_eq!(hachi.instructions_per_frame(), 30);
+        assert_eq!(hachi.ram[0x200..0x202], [0x00, 0xFF]);
+    }
+
+    #[test]
+    fn changes_nothing_when_the_build_does_not_fit() {
+        let too_big = alloc::vec![0; 0x1000];
+        let data = fixture(&[(PLATFORM_CHIP8, &too_big)], &[(PROP_TICKRATE, &[99])]);
+        let mut hachi = Hachi::new();
+        hachi.set_variant(Variant::XoChip);
+
+        assert!(matches!(
+            hachi.load_c8b(&data),
+            Err(C8bError::Load(LoadError::RomTooLarge { .. }))
+        ));
+        assert_eq!(hachi.variant(), Variant::XoChip);
+        assert_ne!(hachi.instructions_per_frame(), 99);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::{Hachi, Key, DISPLAY_HEIGHT, DISPLAY_WIDTH, NUM_KEYS};
+
+// The VP-590 color board divides the screen into zones 8 pixels wide and
+// one pixel high. BXY0 colors them in blocks of four rows.
+const ZONE_WIDTH: usize = 8;
+const ZONE_COLUMNS: usize = DISPLAY_WIDTH / ZONE_WIDTH;
+const ZONE_ROWS: usize = DISPLAY_HEIGHT;
+pub(crate) const NUM_ZONES: usize = ZONE_COLUMNS * ZONE_ROWS;
+
+// The eight foreground colors, in the order the color board numbers them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Chip8xColor {
+    Black,
+    #[default]
+    Red,
+    Blue,
+    Violet,
+    Green,
+    Yellow,
+    Aqua,
+    White,
+}
+
+impl Chip8xColor {
+    pub const ALL: [Chip8xColor; 8] = [
+        Chip8xColor::Black,
+        Chip8xColor::Red,
+        Chip8xColor::Blue,
+        Chip8xColor::Violet,
+        Chip8xColor::Green,
+        Chip8xColor::Yellow,
+        Chip8xColor::Aqua,
+        Chip8xColor::White,
+    ];
+
+    pub fn rgb(self) -> [u8; 3] {
+        match self {
+            Chip8xColor::Black => [0x00, 0x00, 0x00],
+            Chip8xColor::Red => [0xFF, 0x00, 0x00],
+            Chip8xColor::Blue => [0x00, 0x00, 0xFF],
+            Chip8xColor::Violet => [0xFF, 0x00, 0xFF],
+            Chip8xColor::Green => [0x00, 0xFF, 0x00],
+            Chip8xColor::Yellow => [0xFF, 0xFF, 0x00],
+            Chip8xColor::Aqua => [0x00, 0xFF, 0xFF],
+            Chip8xColor::White => [0xFF, 0xFF, 0xFF],
+        }
+    }
+
+    pub(crate) fn from_bits(bits: u8) -> Self {
+        Self::ALL[(bits & 7) as usize]
+    }
+
+    pub(crate) fn bits(self) -> u8 {
+        self as u8
+    }
+}
+
+// 02A0 steps the background through these.
+pub(crate) const BACKGROUNDS: [Chip8xColor; 4] = [
+    Chip8xColor::Blue,
+    Chip8xColor::Black,
+    Chip8xColor::Green,
+    Chip8xColor::Red,
+];
+
+#[derive(Debug, Clone)]
+pub(crate) struct Chip8x {
+    pub(crate) background: usize,
+    pub(crate) zones: [Chip8xColor; NUM_ZONES],
+    pub(crate) tone: u8,
+    pub(crate) keys2: [bool; NUM_KEYS],
+}
+
+impl Default for Chip8x {
+    fn default() -> Self {
+        Self {
+            background: 0,
+            zones: [Chip8xColor::default(); NUM_ZONES],
+            tone: 0,
+            keys2: [false; NUM_KEYS],
+        }
+    }
+}
+
+impl Hachi {
+    pub fn chip8x_background(&self) -> Chip8xColor {
+        BACKGROUNDS[self.chip8x.background]
+    }
+
+    // The color pixel (x, y) shows: its zone's foreground color if lit,
+    // otherwise the background.
+    pub fn chip8x_color_at(&self, x: usize, y: usize) -> Chip8xColor {
+        let lit = x < self.display_width
+            && y < self.display_height
+            && self.display[x + y * self.display_width];
+        if !lit {
+            return self.chip8x_background();
+        }
+        self.chip8x.zones
+            [(x / ZONE_WIDTH).min(ZONE_COLUMNS - 1) + y.min(ZONE_ROWS - 1) * ZONE_COLUMNS]
+    }
+
+    // The last value FXF8 sent to the VP-595 sound board; higher values
+    // give lower tones.
+    pub fn chip8x_tone(&self) -> u8 {
+        self.chip8x.tone
+    }
+
+    // The second hex keypad, tested by EXF2 and EXF5.
+    pub fn keypress2(&mut self, key: Key, pressed: bool) {
+        self.chip8x.keys2[key.index()] = pressed;
+        self.idle_instructions = 0;
+    }
+
+    // Runs `op` if it's one CHIP-8X adds or changes, returning false to
+    // leave it to the CHIP-8 decoder.
+    pub(crate) fn execute_chip8x(&mut self, op: u16) -> bool {
+        let x = ((op & 0x0F00) >> 8) as usize;
+        let y = ((op & 0x00F0) >> 4) as usize;
+        let n = (op & 0x000F) as usize;
+
+        match op & 0xF000 {
+            0x0000 if op == 0x02A0 => {
+                self.chip8x.background = (self.chip8x.background + 1) % BACKGROUNDS.len();
+            }
+            0x5000 if n == 1 => {
+                // Each nibble is added on its own, wrapping at 8
+                let (vx, vy) = (self.v_registers[x], self.v_registers[y]);
+                let high = ((vx >> 4) + (vy >> 4)) & 7;
+                let low = ((vx & 0xF) + (vy & 0xF)) & 7;
+                self.v_registers[x] = high << 4 | low;
+            }
+            0xB000 => {
+                let color = Chip8xColor::from_bits(self.v_registers[y]);
+                let vx = self.v_registers[x] as usize;
+                let vx1 = self.v_registers[(x + 1) & 0xF] as usize;
+                if n == 0 {
+                    // VX and VX+1 each hold a start in the low nibble and
+                    // an extent in the high one: columns of 8 pixels and
+                    // rows of 4
+                    let columns = (vx & 0xF)..=(vx & 0xF) + (vx >> 4);
+                    let rows = (vx1 & 0xF) * 4..((vx1 & 0xF) + (vx1 >> 4) + 1) * 4;
+                    self.color_zones(columns, rows, color);
+                } else {
+                    // N single-pixel rows from (VX, VX+1), one zone wide
+                    let column = vx / ZONE_WIDTH;
+                    self.color_zones(column..=column, vx1..vx1 + n, color);
+                }
+            }
+            0xE000 if op & 0xFF == 0xF2 || op & 0xFF == 0xF5 => {
+                let held = self.chip8x.keys2[(self.v_registers[x] & 0xF) as usize];
+                if held == (op & 0xFF == 0xF2) {
+                    self.skip_next_instruction();
+                }
+            }
+            0xF000 if op & 0xFF == 0xF8 => self.chip8x.tone = self.v_registers[x],
+            _ => return false,
+        }
+        true
+    }
+
+    fn color_zones(
+        &mut self,
+        columns: core::ops::RangeInclusive<usize>,
+        rows: core::ops::Range<usize>,
+        color: Chip8xColor,
+    ) {
+        for row in rows.filter(|&row| row < ZONE_ROWS) {
+            for column in columns.clone().filter(|&column| column < ZONE_COLUMNS) {
+                self.chip8x.zones[column + row * ZONE_COLUMNS] = color;
+            }
+        }
+        self.mark_all_rows_dirty();
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{Hachi, HachiError, Instruction, TickStatus, NUM_REGISTERS};
+
+// Instructions `step_over` runs at most while waiting for a subroutine to
+// return, so a call that never comes back can't hang the debugger.
+const STEP_OVER_LIMIT: u32 = 1_000_000;
+
+// Stepping and read-only inspection for debugger frontends, borrowed from
+// a machine with `Hachi::debug`.
+pub struct Debugger<'a> {
+    hachi: &'a mut Hachi,
+}
+
+impl Hachi {
+    pub fn debug(&mut self) -> Debugger<'_> {
+        Debugger { hachi: self }
+    }
+}
+
+impl Debugger<'_> {
+    // Executes exactly one instruction, even one sitting on a breakpoint.
+    pub fn step(&mut self) -> Result<TickStatus, HachiError> {
+        self.hachi.stopped_at = Some(self.hachi.program_counter);
+        self.hachi.tick()
+    }
+
+    // Like `step`, but runs a CALL through to its return. Stops early on
+    // anything a tick reports (breakpoints inside the subroutine, errors)
+    // or if the machine halts.
+    pub fn step_over(&mut self) -> Result<TickStatus, HachiError> {
+        let is_call = matches!(self.current_instruction(), Instruction::Call { .. });
+        let return_to = self.hachi.program_counter.wrapping_add(2);
+        let depth = self.hachi.stack_pointer;
+
+        let mut status = self.step()?;
+        if !is_call {
+            return Ok(status);
+        }
+
+        for _ in 0..STEP_OVER_LIMIT {
+            let returned =
+                self.hachi.program_counter == return_to && self.hachi.stack_pointer == depth;
+            if returned || status != TickStatus::Executed || self.hachi.is_halted() {
+                break;
+            }
+            status = self.hachi.tick()?;
+        }
+        Ok(status)
+    }
+
+    pub fn current_instruction(&self) -> Instruction {
+        Instruction::decode_for(self.hachi.peek_opcode().unwrap_or(0), self.hachi.variant)
+    }
+
+    pub fn v_registers(&self) -> &[u8; NUM_REGISTERS] {
+        &self.hachi.v_registers
+    }
+
+    pub fn i(&self) -> u16 {
+        self.hachi.i_register
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.hachi.program_counter
+    }
+
+    pub fn sp(&self) -> u16 {
+        self.hachi.stack_pointer
+    }
+
+    // Return addresses currently on the stack, outermost call first.
+    pub fn stack(&self) -> &[u16] {
+        &self.hachi.stack[..self.hachi.stack_pointer as usize]
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.hachi.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.hachi.sound_timer
+    }
+}
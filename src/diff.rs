@@ -0,0 +1,108 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::instruction::Register;
+use crate::Hachi;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterChange {
+    pub register: Register,
+    pub old: u32,
+    pub new: u32,
+}
+
+// How one machine differs from another, `self` being the old side. Memory
+// is reported as runs of consecutive differing bytes and pixels as (x, y)
+// in either plane. When the display sizes differ `dimensions` holds both
+// and no pixels are compared.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    pub registers: Vec<RegisterChange>,
+    pub stack: Vec<usize>,
+    pub memory: Vec<Range<usize>>,
+    pub pixels: Vec<(usize, usize)>,
+    pub dimensions: Option<((usize, usize), (usize, usize))>,
+}
+
+impl StateDiff {
+    pub fn is_empty(&self) -> bool {
+        self.registers.is_empty()
+            && self.stack.is_empty()
+            && self.memory.is_empty()
+            && self.pixels.is_empty()
+            && self.dimensions.is_none()
+    }
+}
+
+impl Hachi {
+    // What changed going from `self` to `other`, e.g. before and after a
+    // single `tick`, or between this core and another emulator's state
+    // loaded through the savestate format.
+    pub fn diff(&self, other: &Hachi) -> StateDiff {
+        StateDiff {
+            registers: self.register_changes(other),
+            stack: (0..self.stack.len().max(other.stack.len()))
+                .filter(|&slot| self.stack.get(slot) != other.stack.get(slot))
+                .collect(),
+            memory: changed_runs(&self.ram, &other.ram),
+            pixels: self.pixel_changes(other),
+            dimensions: (self.display_width != other.display_width
+                || self.display_height != other.display_height)
+                .then_some((
+                    (self.display_width, self.display_height),
+                    (other.display_width, other.display_height),
+                )),
+        }
+    }
+
+    fn register_values(&self) -> impl Iterator<Item = (Register, u32)> + '_ {
+        let v = (0..self.v_registers.len() as u8)
+            .map(|x| (Register::V(x), self.v_registers[x as usize] as u32));
+        v.chain([
+            (Register::I, self.i_address() as u32),
+            (Register::Pc, self.program_counter as u32),
+            (Register::Sp, self.stack_pointer as u32),
+            (Register::DelayTimer, self.delay_timer as u32),
+            (Register::SoundTimer, self.sound_timer as u32),
+        ])
+    }
+
+    fn register_changes(&self, other: &Hachi) -> Vec<RegisterChange> {
+        self.register_values()
+            .zip(other.register_values())
+            .filter(|((_, old), (_, new))| old != new)
+            .map(|((register, old), (_, new))| RegisterChange { register, old, new })
+            .collect()
+    }
+
+    fn pixel_changes(&self, other: &Hachi) -> Vec<(usize, usize)> {
+        if self.display_width != other.display_width || self.display_height != other.display_height
+        {
+            return Vec::new();
+        }
+
+        let width = self.display_width;
+        (0..self.display.len())
+            .filter(|&idx| {
+                self.display[idx] != other.display[idx]
+                    || self.second_plane.get(idx) != other.second_plane.get(idx)
+            })
+            .map(|idx| (idx % width, idx / width))
+            .collect()
+    }
+}
+
+// Runs of differing bytes; anything past the end of the shorter slice
+// counts as changed.
+fn changed_runs(old: &[u8], new: &[u8]) -> Vec<Range<usize>> {
+    let len = old.len().max(new.len());
+    let mut runs: Vec<Range<usize>> = Vec::new();
+
+    for addr in (0..len).filter(|&addr| old.get(addr) != new.get(addr)) {
+        match runs.last_mut() {
+            Some(run) if run.end == addr => run.end += 1,
+            _ => runs.push(addr..addr + 1),
+        }
+    }
+    runs
+}
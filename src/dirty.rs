@@ -0,0 +1,37 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::Hachi;
+
+impl Hachi {
+    // Rows that changed since the last call to `take_dirty_rows` or
+    // `take_dirty_span`, top to bottom. Everything counts as changed at
+    // start-up and after a resolution switch or state load, so a frontend
+    // that only ever redraws dirty rows still paints the whole screen first.
+    pub fn take_dirty_rows(&mut self) -> Vec<usize> {
+        let rows = self
+            .dirty_rows
+            .iter()
+            .enumerate()
+            .filter(|(_, &dirty)| dirty)
+            .map(|(y, _)| y)
+            .collect();
+        self.dirty_rows.fill(false);
+        rows
+    }
+
+    // The smallest band of rows covering every change, for displays that
+    // redraw a window at a time (SPI LCDs), without allocating.
+    pub fn take_dirty_span(&mut self) -> Option<Range<usize>> {
+        let first = self.dirty_rows.iter().position(|&dirty| dirty)?;
+        let last = self.dirty_rows.iter().rposition(|&dirty| dirty)?;
+        self.dirty_rows.fill(false);
+        Some(first..last + 1)
+    }
+
+    pub(crate) fn mark_all_rows_dirty(&mut self) {
+        self.display_dirty = true;
+        self.dirty_rows.clear();
+        self.dirty_rows.resize(self.display_height, true);
+    }
+}
@@ -0,0 +1,180 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{Instruction, Variant};
+
+// One decoded instruction of a ROM listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisasmLine {
+    pub addr: u16,
+    // Two bytes, or four for XO-CHIP's F000 NNNN and MEGA-CHIP's 01NN NNNN. A trailing odd byte is
+    // listed on its own.
+    pub bytes: Vec<u8>,
+    pub text: String,
+    // The label starting at `addr`, when listed by `Hachi::disassemble_ram`.
+    pub label: Option<String>,
+}
+
+// A labelled line is preceded by the label on a line of its own.
+impl fmt::Display for DisasmLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(label) = &self.label {
+            writeln!(f, "{}:", label)?;
+        }
+        write!(f, "{:03X}: ", self.addr)?;
+        for byte in &self.bytes {
+            write!(f, "{:02X}", byte)?;
+        }
+        write!(
+            f,
+            "{:width$}  {}",
+            "",
+            self.text,
+            width = 8 - self.bytes.len() * 2
+        )
+    }
+}
+
+// Mnemonics follow the widely used Cowgod naming, e.g. `LD V3, 0x1F` and
+// `DRW V0, V1, 5`, extended with the SUPER-CHIP and XO-CHIP instructions.
+// MEGA-CHIP's are the ones from its documentation; CHIP-8X never had
+// standard ones.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Instruction::*;
+
+        match *self {
+            Zero => write!(f, "DW 0x0000"),
+            ClearScreen => write!(f, "CLS"),
+            Return => write!(f, "RET"),
+            ScrollDown { n } => write!(f, "SCD {}", n),
+            ScrollRight => write!(f, "SCR"),
+            ScrollLeft => write!(f, "SCL"),
+            Exit => write!(f, "EXIT"),
+            LowRes => write!(f, "LOW"),
+            HighRes => write!(f, "HIGH"),
+            ScrollUp { n } => write!(f, "SCU {}", n),
+            Jump { nnn } => write!(f, "JP 0x{:03X}", nnn),
+            Call { nnn } => write!(f, "CALL 0x{:03X}", nnn),
+            SkipEqImm { x, nn } => write!(f, "SE V{:X}, 0x{:02X}", x, nn),
+            SkipNeImm { x, nn } => write!(f, "SNE V{:X}, 0x{:02X}", x, nn),
+            SkipEqReg { x, y } => write!(f, "SE V{:X}, V{:X}", x, y),
+            SaveRange { x, y } => write!(f, "SAVE V{:X}-V{:X}", x, y),
+            LoadRange { x, y } => write!(f, "LOAD V{:X}-V{:X}", x, y),
+            SetImm { x, nn } => write!(f, "LD V{:X}, 0x{:02X}", x, nn),
+            AddImm { x, nn } => write!(f, "ADD V{:X}, 0x{:02X}", x, nn),
+            Set { x, y } => write!(f, "LD V{:X}, V{:X}", x, y),
+            Or { x, y } => write!(f, "OR V{:X}, V{:X}", x, y),
+            And { x, y } => write!(f, "AND V{:X}, V{:X}", x, y),
+            Xor { x, y } => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Add { x, y } => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Sub { x, y } => write!(f, "SUB V{:X}, V{:X}", x, y),
+            ShiftRight { x, y } => write!(f, "SHR V{:X}, V{:X}", x, y),
+            SubReversed { x, y } => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            ShiftLeft { x, y } => write!(f, "SHL V{:X}, V{:X}", x, y),
+            SkipNeReg { x, y } => write!(f, "SNE V{:X}, V{:X}", x, y),
+            SetI { nnn } => write!(f, "LD I, 0x{:03X}", nnn),
+            SetILong => write!(f, "LD I, long"),
+            JumpOffset { nnn } => write!(f, "JP V0, 0x{:03X}", nnn),
+            Random { x, nn } => write!(f, "RND V{:X}, 0x{:02X}", x, nn),
+            Draw { x, y, n } => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            SkipKey { x } => write!(f, "SKP V{:X}", x),
+            SkipNotKey { x } => write!(f, "SKNP V{:X}", x),
+            SelectPlanes { n } => write!(f, "PLANE {}", n),
+            LoadAudio => write!(f, "AUDIO"),
+            GetDelay { x } => write!(f, "LD V{:X}, DT", x),
+            WaitKey { x } => write!(f, "LD V{:X}, K", x),
+            SetDelay { x } => write!(f, "LD DT, V{:X}", x),
+            SetSound { x } => write!(f, "LD ST, V{:X}", x),
+            AddI { x } => write!(f, "ADD I, V{:X}", x),
+            FontChar { x } => write!(f, "LD F, V{:X}", x),
+            BigFontChar { x } => write!(f, "LD HF, V{:X}", x),
+            SetPitch { x } => write!(f, "PITCH V{:X}", x),
+            Bcd { x } => write!(f, "LD B, V{:X}", x),
+            Store { x } => write!(f, "LD [I], V{:X}", x),
+            Load { x } => write!(f, "LD V{:X}, [I]", x),
+            SaveFlags { x } => write!(f, "LD R, V{:X}", x),
+            LoadFlags { x } => write!(f, "LD V{:X}, R", x),
+            CycleBackground => write!(f, "BGCOL"),
+            AddNibbles { x, y } => write!(f, "ADDN V{:X}, V{:X}", x, y),
+            ColorZones { x, y, n } => write!(f, "COL V{:X}, V{:X}, {}", x, y, n),
+            SkipKey2 { x } => write!(f, "SKP2 V{:X}", x),
+            SkipNotKey2 { x } => write!(f, "SKNP2 V{:X}", x),
+            SetTone { x } => write!(f, "TONE V{:X}", x),
+            MegaOff => write!(f, "MEGAOFF"),
+            MegaOn => write!(f, "MEGAON"),
+            ScrollUpMega { n } => write!(f, "SCRU {}", n),
+            LoadIHigh { nn } => write!(f, "LDHI I, 0x{:02X}....", nn),
+            LoadPalette { nn } => write!(f, "LDPAL {}", nn),
+            SpriteWidth { nn } => write!(f, "SPRW {}", nn),
+            SpriteHeight { nn } => write!(f, "SPRH {}", nn),
+            SetAlpha { nn } => write!(f, "ALPHA 0x{:02X}", nn),
+            PlaySample { n } => write!(f, "DIGISND {}", n),
+            StopSample => write!(f, "STOPSND"),
+            SetBlend { n } => write!(f, "BMODE {}", n),
+            CollisionColor { nn } => write!(f, "CCOL {}", nn),
+            Unknown { opcode } => write!(f, "DW 0x{:04X}", opcode),
+        }
+    }
+}
+
+pub fn disassemble(op: u16) -> String {
+    Instruction::decode(op).to_string()
+}
+
+pub fn disassemble_for(op: u16, variant: Variant) -> String {
+    Instruction::decode_for(op, variant).to_string()
+}
+
+// Lists `rom` as if loaded at `origin`. Data mixed in with code is decoded
+// as instructions too; there is no way to tell the two apart statically.
+pub fn disassemble_rom(rom: &[u8], origin: u16) -> Vec<DisasmLine> {
+    disassemble_rom_for(rom, origin, Variant::XoChip)
+}
+
+// `disassemble_rom` for the instruction set of `variant`.
+pub fn disassemble_rom_for(rom: &[u8], origin: u16, variant: Variant) -> Vec<DisasmLine> {
+    let mut lines = Vec::with_capacity(rom.len() / 2 + 1);
+    let mut offset = 0;
+
+    while offset < rom.len() {
+        let addr = origin.wrapping_add(offset as u16);
+        let rest = &rom[offset..];
+
+        if rest.len() < 2 {
+            lines.push(DisasmLine {
+                addr,
+                bytes: rest.to_vec(),
+                text: format!("DB 0x{:02X}", rest[0]),
+                label: None,
+            });
+            break;
+        }
+
+        let op = u16::from_be_bytes([rest[0], rest[1]]);
+        let instruction = Instruction::decode_for(op, variant);
+        let (len, text) = match instruction {
+            Instruction::SetILong if rest.len() >= 4 => {
+                let nnnn = u16::from_be_bytes([rest[2], rest[3]]);
+                (4, format!("LD I, long 0x{:04X}", nnnn))
+            }
+            Instruction::LoadIHigh { nn } if rest.len() >= 4 => {
+                let nnnn = u16::from_be_bytes([rest[2], rest[3]]);
+                (4, format!("LDHI I, 0x{:02X}{:04X}", nn, nnnn))
+            }
+            _ => (2, instruction.to_string()),
+        };
+
+        lines.push(DisasmLine {
+            addr,
+            bytes: rest[..len].to_vec(),
+            text,
+            label: None,
+        });
+        offset += len;
+    }
+
+    lines
+}
@@ -0,0 +1,218 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::Hachi;
+
+const MAX_DIAGNOSTICS: usize = 256;
+
+pub(crate) type Diagnostics = VecDeque<HachiError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HachiError {
+    UnknownOpcode { opcode: u16, pc: u16 },
+    MemoryFault { addr: usize, pc: u16 },
+    StackOverflow { pc: u16, depth: usize },
+    // 00EE with nothing on the stack.
+    StackUnderflow { pc: u16 },
+    FellOffProgram { pc: u16 },
+}
+
+impl HachiError {
+    // Address of the instruction that raised the error.
+    pub fn pc(&self) -> u16 {
+        match *self {
+            HachiError::UnknownOpcode { pc, .. } => pc,
+            HachiError::MemoryFault { pc, .. } => pc,
+            HachiError::StackOverflow { pc, .. } => pc,
+            HachiError::StackUnderflow { pc } => pc,
+            HachiError::FellOffProgram { pc } => pc,
+        }
+    }
+}
+
+impl fmt::Display for HachiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            HachiError::UnknownOpcode { opcode, pc } => {
+                write!(f, "unknown opcode {:04X} at {:03X}", opcode, pc)
+            }
+            HachiError::MemoryFault { addr, pc } => {
+                write!(
+                    f,
+                    "memory access out of range at {:04X} by {:03X}",
+                    addr, pc
+                )
+            }
+            HachiError::StackOverflow { pc, depth } => {
+                write!(
+                    f,
+                    "stack overflow at {:03X}: more than {} nested calls",
+                    pc, depth
+                )
+            }
+            HachiError::StackUnderflow { pc } => {
+                write!(f, "stack underflow at {:03X}: return with no call", pc)
+            }
+            HachiError::FellOffProgram { pc } => {
+                write!(
+                    f,
+                    "executed 0000 at {:03X}, past the end of the program",
+                    pc
+                )
+            }
+        }
+    }
+}
+
+impl core::error::Error for HachiError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadError {
+    Empty,
+    // The ROM doesn't fit between the start address and the end of RAM.
+    RomTooLarge { size: usize, max: usize },
+    // `load_at` was given an address past the end of RAM.
+    AddressOutOfRange { addr: usize },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            LoadError::Empty => write!(f, "ROM is empty"),
+            LoadError::RomTooLarge { size, max } => {
+                write!(f, "ROM is {} bytes but at most {} fit in RAM", size, max)
+            }
+            LoadError::AddressOutOfRange { addr } => {
+                write!(f, "address {:04X} is outside RAM", addr)
+            }
+        }
+    }
+}
+
+impl core::error::Error for LoadError {}
+
+// What `tick` does when an instruction can't be executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorPolicy {
+    // Stop on the faulting instruction and return the error. Ticking again
+    // retries the same instruction.
+    #[default]
+    Halt,
+    // Step over the faulting instruction and carry on silently.
+    Skip,
+    // Treat the faulting instruction as a no-op, recording the error so it
+    // can be collected with `take_diagnostics` and passing it to the
+    // observer's `on_error`.
+    Log,
+}
+
+impl Hachi {
+    pub fn error_policy(&self) -> ErrorPolicy {
+        self.error_policy
+    }
+
+    pub fn set_error_policy(&mut self, policy: ErrorPolicy) {
+        self.error_policy = policy;
+    }
+
+    // Errors swallowed under `ErrorPolicy::Log`, oldest first. Only the most
+    // recent few hundred are kept.
+    pub fn take_diagnostics(&mut self) -> Vec<HachiError> {
+        self.diagnostics.drain(..).collect()
+    }
+
+    pub(crate) fn recover(&mut self, err: HachiError) -> Result<(), HachiError> {
+        match self.error_policy {
+            ErrorPolicy::Halt => {
+                self.program_counter = err.pc();
+                Err(err)
+            }
+            ErrorPolicy::Skip => {
+                self.skip_faulting_instruction(err.pc());
+                Ok(())
+            }
+            ErrorPolicy::Log => {
+                self.skip_faulting_instruction(err.pc());
+                self.notify(|observer| observer.on_error(&err));
+                if self.diagnostics.len() == MAX_DIAGNOSTICS {
+                    self.diagnostics.pop_front();
+                }
+                self.diagnostics.push_back(err);
+                Ok(())
+            }
+        }
+    }
+
+    // Steps over the instruction at `pc`, all four bytes of an XO-CHIP or
+    // MEGA-CHIP long I load included.
+    fn skip_faulting_instruction(&mut self, pc: u16) {
+        self.program_counter = pc;
+        let len = self.next_instruction_len() as usize;
+        self.program_counter = ((pc as usize + len) % self.ram.len()) as u16;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::rc::Rc;
+    use core::cell::RefCell;
+
+    use super::*;
+    use crate::{HachiObserver, Variant};
+
+    struct Errors(Rc<RefCell<Vec<HachiError>>>);
+
+    impl HachiObserver for Errors {
+        fn on_error(&mut self, err: &HachiError) {
+            self.0.borrow_mut().push(*err);
+        }
+    }
+
+    #[test]
+    fn returns_unknown_opcodes_instead_of_panicking() {
+        let mut hachi = Hachi::new();
+        hachi.load(&[0xFF, 0xFF]).unwrap();
+        let err = HachiError::UnknownOpcode {
+            opcode: 0xFFFF,
+            pc: 0x200,
+        };
+
+        assert_eq!(hachi.tick(), Err(err));
+        assert_eq!(hachi.pc(), 0x200);
+        assert_eq!(hachi.tick(), Err(err));
+    }
+
+    #[test]
+    fn skips_all_of_a_faulting_long_i_load() {
+        // RAM ends halfway through the F000 NNNN
+        let mut hachi = Hachi::new();
+        hachi.set_variant(Variant::XoChip);
+        assert!(hachi.set_ram_size(Some(0x1000)));
+        hachi.load_at(0xFFE, &[0xF0, 0x00]).unwrap();
+        assert!(hachi.set_pc(0xFFE));
+        hachi.set_error_policy(ErrorPolicy::Skip);
+
+        hachi.tick().unwrap();
+        assert_eq!(hachi.pc(), 0x002);
+    }
+
+    #[test]
+    fn logs_to_the_diagnostics_and_the_observer() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut hachi = Hachi::new();
+        hachi.set_observer(Box::new(Errors(seen.clone())));
+        hachi.set_error_policy(ErrorPolicy::Log);
+        hachi.load(&[0xFF, 0xFF, 0x60, 0x01]).unwrap();
+
+        hachi.tick().unwrap();
+        assert_eq!(hachi.pc(), 0x202);
+        let err = HachiError::UnknownOpcode {
+            opcode: 0xFFFF,
+            pc: 0x200,
+        };
+        assert_eq!(*seen.borrow(), [err]);
+        assert_eq!(hachi.take_diagnostics(), [err]);
+    }
+}
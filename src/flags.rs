@@ -0,0 +1,58 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::Hachi;
+
+// Where the RPL user flags are kept between sessions. Games save high
+// scores in them with FX75, so frontends persist them the way they would a
+// battery save.
+pub trait FlagStorage {
+    // Fills `flags` with the saved values. Returns false if nothing has
+    // been saved yet, in which case the flags are left as they are.
+    fn load(&mut self, flags: &mut [u8]) -> bool;
+
+    fn save(&mut self, flags: &[u8]);
+}
+
+// Keeps the flags in memory only; useful for tests and as a template.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFlags {
+    pub data: Option<Vec<u8>>,
+}
+
+impl FlagStorage for MemoryFlags {
+    fn load(&mut self, flags: &mut [u8]) -> bool {
+        match &self.data {
+            Some(data) => {
+                let len = data.len().min(flags.len());
+                flags[..len].copy_from_slice(&data[..len]);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn save(&mut self, flags: &[u8]) {
+        self.data = Some(flags.to_vec());
+    }
+}
+
+impl Hachi {
+    // Loads the flags from `storage` now and saves them back on every FX75.
+    // Without storage the flags only live as long as the machine.
+    pub fn set_flag_storage(&mut self, mut storage: Box<dyn FlagStorage>) {
+        storage.load(&mut self.rpl_flags);
+        self.flag_storage = Some(storage);
+    }
+
+    pub fn take_flag_storage(&mut self) -> Option<Box<dyn FlagStorage>> {
+        self.flag_storage.take()
+    }
+
+    // Called after FX75 changes the flags.
+    pub(crate) fn save_rpl_flags(&mut self) {
+        if let Some(storage) = &mut self.flag_storage {
+            storage.save(&self.rpl_flags);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use crate::schip::BIG_FONTSET;
+use crate::{Hachi, FONTSET};
+
+impl Hachi {
+    // Replaces the 4x5 hex digits FX29 points at, sixteen glyphs of five
+    // bytes each. The font is written into RAM straight away and again on
+    // every reset.
+    pub fn set_font(&mut self, font: &[u8; 80]) {
+        self.font = *font;
+        self.load_fonts();
+    }
+
+    // Replaces the SUPER-CHIP 8x10 digits FX30 points at, ten bytes per
+    // glyph.
+    pub fn set_big_font(&mut self, font: &[u8; 160]) {
+        self.big_font = *font;
+        self.load_fonts();
+    }
+
+    pub fn font(&self) -> &[u8; 80] {
+        &self.font
+    }
+
+    pub fn big_font(&self) -> &[u8; 160] {
+        &self.big_font
+    }
+
+    // Goes back to the built-in fonts.
+    pub fn reset_fonts(&mut self) {
+        self.font = FONTSET;
+        self.big_font = BIG_FONTSET;
+        self.load_fonts();
+    }
+}
@@ -0,0 +1,196 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::hash::fnv1a64;
+use crate::{Hachi, HachiError, RunOutcome, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+
+// A snapshot of the display with its pixels packed one bit per pixel. Rows
+// are padded to a whole number of bytes and the most significant bit of each
+// byte is the leftmost pixel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+    frame_count: u64,
+    dirty: bool,
+}
+
+impl Frame {
+    pub(crate) fn pack(
+        display: &[bool],
+        width: usize,
+        height: usize,
+        frame_count: u64,
+        dirty: bool,
+    ) -> Self {
+        let stride = width.div_ceil(8);
+        let mut pixels = vec![0; stride * height];
+
+        for (y, row) in display.chunks(width).take(height).enumerate() {
+            for (x, &on) in row.iter().enumerate() {
+                if on {
+                    pixels[y * stride + x / 8] |= 0b1000_0000 >> (x % 8);
+                }
+            }
+        }
+
+        Self {
+            width,
+            height,
+            pixels,
+            frame_count,
+            dirty,
+        }
+    }
+
+    pub(crate) fn from_packed(width: usize, height: usize, pixels: Vec<u8>) -> Self {
+        Self {
+            width,
+            height,
+            pixels,
+            frame_count: 0,
+            dirty: false,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // Bytes per packed row.
+    pub fn stride(&self) -> usize {
+        self.width.div_ceil(8)
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        if x >= self.width || y >= self.height {
+            return false;
+        }
+        self.pixels[y * self.stride() + x / 8] & (0b1000_0000 >> (x % 8)) != 0
+    }
+
+    // Number of timer ticks elapsed when the frame was taken.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    // Whether the display changed since the previous call to `Hachi::frame`.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+}
+
+// What happened during one call to `Hachi::run_frame`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameSummary {
+    // Why execution stopped. Anything but `Ok(Completed)` means the frame
+    // was cut short; errors only stop it under `ErrorPolicy::Halt`.
+    pub outcome: Result<RunOutcome, HachiError>,
+    // Whether the display changed during the frame.
+    pub display_dirty: bool,
+    // Whether the buzzer was sounding at the end of the frame.
+    pub sound_active: bool,
+}
+
+impl Hachi {
+    // Runs `cycles_per_frame` instructions and then ticks the timers once,
+    // the loop every 60 Hz frontend would otherwise write itself. A frame
+    // cut short by a breakpoint, watchpoint, the watchdog or an error leaves
+    // the timers alone, so resuming doesn't count the frame twice; a halted
+    // machine's timers keep running so a final beep still ends.
+    pub fn run_frame(&mut self, cycles_per_frame: u32) -> FrameSummary {
+        self.run_frame_with(|hachi| hachi.tick_n(cycles_per_frame as u64))
+    }
+
+    // The frame bookkeeping around `run`, which executes the frame's
+    // instructions.
+    pub(crate) fn run_frame_with(
+        &mut self,
+        run: impl FnOnce(&mut Self) -> Result<RunOutcome, HachiError>,
+    ) -> FrameSummary {
+        let was_dirty = self.display_dirty;
+        self.display_dirty = false;
+
+        let outcome = run(self);
+        if matches!(
+            outcome,
+            Ok(RunOutcome::Completed | RunOutcome::Halted { .. })
+        ) {
+            self.tick_timers();
+        }
+        let sound_active = self.sound_timer > 0;
+
+        let display_dirty = self.display_dirty;
+        // `frame` still reports changes since it was last called
+        self.display_dirty |= was_dirty;
+
+        FrameSummary {
+            outcome,
+            display_dirty,
+            sound_active,
+        }
+    }
+}
+
+impl Hachi {
+    // A fingerprint of what's on screen, for asserting that a test ROM
+    // reached a known-good frame. It covers the resolution and both
+    // XO-CHIP planes, and stays the same across platforms and releases.
+    pub fn display_hash(&self) -> u64 {
+        let dims = [self.display_width as u16, self.display_height as u16];
+        let pixels = self.display.iter().chain(&self.second_plane);
+        fnv1a64(
+            dims.iter()
+                .flat_map(|dim| dim.to_le_bytes())
+                .chain(pixels.map(|&lit| lit as u8)),
+        )
+    }
+
+    // The display as one `u64` per row with the leftmost pixel in the most
+    // significant bit, for monochrome OLEDs and LED matrices that take
+    // packed rows. The packing is always 64x32: on a larger display each bit
+    // covers a block of pixels and is lit if any of them is.
+    pub fn display_packed(&self) -> [u64; DISPLAY_HEIGHT] {
+        let x_scale = self.display_width / DISPLAY_WIDTH;
+        let y_scale = self.display_height / DISPLAY_HEIGHT;
+        let mut rows = [0; DISPLAY_HEIGHT];
+
+        for (y, row) in self.display_rows().enumerate() {
+            let packed = &mut rows[y / y_scale];
+            for (x, &lit) in row.iter().enumerate() {
+                if lit {
+                    *packed |= 1 << (DISPLAY_WIDTH - 1 - x / x_scale);
+                }
+            }
+        }
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_the_buzzer_after_the_timers_tick() {
+        // buzzer := 1, then spin
+        let mut hachi = Hachi::new();
+        hachi.load(&[0x60, 0x01, 0xF0, 0x18, 0x12, 0x04]).unwrap();
+        assert!(!hachi.run_frame(10).sound_active);
+
+        // buzzer := 2 lasts past the first frame
+        let mut hachi = Hachi::new();
+        hachi.load(&[0x60, 0x02, 0xF0, 0x18, 0x12, 0x04]).unwrap();
+        assert!(hachi.run_frame(10).sound_active);
+        assert!(!hachi.run_frame(10).sound_active);
+    }
+}
@@ -0,0 +1,71 @@
+use crate::Hachi;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HaltReason {
+    // Executed 0000 under `ZeroOpcodePolicy::Halt`, which almost always
+    // means the PC ran past the end of the program into empty RAM.
+    FellOffProgram { pc: u16 },
+    // The program ended itself with the SUPER-CHIP 00FD opcode.
+    Exited { pc: u16 },
+    // A 1NNN jumping to itself, the usual way a CHIP-8 program ends.
+    // Nothing but the timers could change after it.
+    SelfJump { pc: u16 },
+}
+
+// What executing opcode 0000 does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ZeroOpcodePolicy {
+    #[default]
+    NoOp,
+    // Stop the machine with `HaltReason::FellOffProgram`.
+    Halt,
+    // Raise `HachiError::FellOffProgram`, handled like any other error.
+    Error,
+}
+
+impl Hachi {
+    pub fn is_halted(&self) -> bool {
+        self.halted.is_some()
+    }
+
+    pub fn halt_reason(&self) -> Option<HaltReason> {
+        self.halted
+    }
+
+    // Lets a halted machine run again from where it stopped.
+    pub fn resume(&mut self) {
+        self.halted = None;
+    }
+
+    pub fn zero_opcode_policy(&self) -> ZeroOpcodePolicy {
+        self.zero_opcode_policy
+    }
+
+    pub fn set_zero_opcode_policy(&mut self, policy: ZeroOpcodePolicy) {
+        self.zero_opcode_policy = policy;
+    }
+
+    pub(crate) fn halt(&mut self, reason: HaltReason) {
+        self.halted = Some(reason);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TickStatus, Variant};
+
+    #[test]
+    fn stays_halted_after_exit() {
+        let mut hachi = Hachi::new();
+        hachi.set_variant(Variant::SuperChip);
+        hachi.load(&[0x00, 0xFD]).unwrap();
+        let reason = HaltReason::Exited { pc: 0x200 };
+
+        assert_eq!(hachi.tick(), Ok(TickStatus::Halted { reason }));
+        assert!(hachi.is_halted());
+        assert_eq!(hachi.halt_reason(), Some(reason));
+        assert_eq!(hachi.tick(), Ok(TickStatus::Halted { reason }));
+        assert_eq!(hachi.pc(), 0x200);
+    }
+}
@@ -0,0 +1,24 @@
+// CRC-32 (IEEE 802.3), the checksum ROM databases and bug reports commonly
+// identify ROM images by.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+// 64-bit FNV-1a: quick, and stable across platforms and releases, which is
+// all a screenshot comparison needs.
+pub(crate) fn fnv1a64(data: impl IntoIterator<Item = u8>) -> u64 {
+    let mut hash = 0xCBF2_9CE4_8422_2325u64;
+    for byte in data {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01B3);
+    }
+    hash
+}
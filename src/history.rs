@@ -0,0 +1,79 @@
+use alloc::collections::VecDeque;
+
+use crate::Hachi;
+
+pub const DEFAULT_PC_HISTORY_LEN: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub pc: u16,
+    pub opcode: u16,
+}
+
+// The most recently executed instructions, oldest first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PcHistory {
+    entries: VecDeque<HistoryEntry>,
+    capacity: usize,
+}
+
+impl PcHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &HistoryEntry> + '_ {
+        self.entries.iter()
+    }
+
+    pub fn last(&self) -> Option<&HistoryEntry> {
+        self.entries.back()
+    }
+
+    pub(crate) fn record(&mut self, pc: u16, opcode: u16) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(HistoryEntry { pc, opcode });
+    }
+
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        while self.entries.len() > capacity {
+            self.entries.pop_front();
+        }
+        self.capacity = capacity;
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Hachi {
+    pub fn pc_history(&self) -> &PcHistory {
+        &self.pc_history
+    }
+
+    // Number of instructions remembered; zero turns the history off.
+    pub fn set_pc_history_len(&mut self, len: usize) {
+        self.pc_history.set_capacity(len);
+    }
+}
@@ -0,0 +1,123 @@
+use alloc::boxed::Box;
+
+use crate::Hachi;
+
+// Source of the random bytes CXNN masks. The default draws from `rand`'s
+// thread-local generator, which on wasm32-wasi is seeded through the WASI
+// `random_get` call; sandboxed hosts that want to supply their own entropy
+// (or none at all), and tests or replays that need a fixed sequence, can
+// install their own. Any `FnMut() -> u8` closure is a source. The core has
+// no other dependency on the host: all timing is driven by the caller
+// through `tick`, `tick_timers` and `advance_timers`.
+pub trait RandomSource {
+    fn next_byte(&mut self) -> u8;
+
+    // The generator's position, for save states. Sources that can't report
+    // one are left out of states and keep running on after a restore.
+    fn saved_state(&self) -> Option<u64> {
+        None
+    }
+
+    // Moves the generator back to a position `saved_state` reported.
+    // Returns false if the source can't be moved.
+    fn restore_state(&mut self, _state: u64) -> bool {
+        false
+    }
+}
+
+impl<F: FnMut() -> u8> RandomSource for F {
+    fn next_byte(&mut self) -> u8 {
+        self()
+    }
+}
+
+pub type RandomFn = fn() -> u8;
+
+// The default source, backed by `rand::random`.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThreadRandom;
+
+#[cfg(feature = "std")]
+impl RandomSource for ThreadRandom {
+    fn next_byte(&mut self) -> u8 {
+        rand::random()
+    }
+}
+
+// A xorshift64* generator. Two machines seeded alike produce the same
+// CXNN results, which makes runs reproducible without pulling in `rand`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Xorshift {
+    state: u64,
+}
+
+impl Xorshift {
+    pub fn new(seed: u64) -> Self {
+        // Zero is the one state xorshift never leaves
+        let state = if seed == 0 {
+            0x9E37_79B9_7F4A_7C15
+        } else {
+            seed
+        };
+        Self { state }
+    }
+}
+
+impl RandomSource for Xorshift {
+    fn next_byte(&mut self) -> u8 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+    }
+
+    fn saved_state(&self) -> Option<u64> {
+        Some(self.state)
+    }
+
+    fn restore_state(&mut self, state: u64) -> bool {
+        *self = Xorshift::new(state);
+        true
+    }
+}
+
+// Without std there's no entropy to draw on, so every machine starts from
+// the same seed; hosts with a hardware RNG should install their own source.
+#[cfg(not(feature = "std"))]
+const DEFAULT_SEED: u64 = 0x4841_4348_4920_3821;
+
+#[cfg(feature = "std")]
+pub(crate) fn default_random_source() -> Box<dyn RandomSource> {
+    Box::new(ThreadRandom)
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn default_random_source() -> Box<dyn RandomSource> {
+    Box::new(Xorshift::new(DEFAULT_SEED))
+}
+
+impl Hachi {
+    pub fn new_with_seed(seed: u64) -> Self {
+        let mut hachi = Hachi::new();
+        hachi.random = Box::new(Xorshift::new(seed));
+        hachi
+    }
+
+    pub fn set_random_source(&mut self, source: Box<dyn RandomSource>) {
+        self.random = source;
+    }
+
+    pub fn set_random_fn(&mut self, random: RandomFn) {
+        self.random = Box::new(random);
+    }
+
+    // Moves the random source to a position it or another source reported
+    // through `saved_state`. A source that can't be moved is replaced with
+    // a `Xorshift` at that position, so CXNN continues as it would have.
+    pub(crate) fn restore_random(&mut self, state: u64) {
+        if !self.random.restore_state(state) {
+            self.random = Box::new(Xorshift::new(state));
+        }
+    }
+}
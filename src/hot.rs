@@ -0,0 +1,151 @@
+use alloc::boxed::Box;
+use core::sync::atomic::{AtomicU16, AtomicU32, AtomicU8, Ordering};
+
+use crate::{Hachi, Key, NUM_KEYS};
+
+// Room for the largest display any enabled variant can show.
+#[cfg(not(feature = "megachip"))]
+const HOT_DISPLAY_SIZE: usize = crate::HIRES_DISPLAY_WIDTH * crate::HIRES_DISPLAY_HEIGHT;
+#[cfg(feature = "megachip")]
+const HOT_DISPLAY_SIZE: usize = crate::MEGA_DISPLAY_WIDTH * crate::MEGA_DISPLAY_HEIGHT;
+
+// The state a UI thread needs every frame, laid out so it can live in
+// memory shared with another thread or a JavaScript `SharedArrayBuffer`.
+// The core writes the display, audio flag and frame counter through to it
+// as they change; the UI writes key states into it and the core picks them
+// up at the start of every instruction.
+//
+// Layout (`repr(C)`, one byte per pixel and key, 0 or 1), where D is
+// `DISPLAY_LEN`: 8192 bytes for 128x64, or 49152 for MEGA-CHIP's 256x192
+// with the `megachip` feature:
+//
+//     offset      0  display, D bytes, row-major at the current resolution
+//     offset      D  keys, 16 bytes indexed by key value
+//     offset   D+16  audio, 1 byte, non-zero while the buzzer sounds
+//     offset   D+17  hires, 1 byte, non-zero while the display is 128x64
+//     offset   D+20  frame count, little-endian u32
+//     offset   D+24  display width, little-endian u16
+//     offset   D+26  display height, little-endian u16
+//
+// Only the first width * height display bytes are in use.
+#[repr(C)]
+pub struct HotState {
+    pub display: [AtomicU8; HOT_DISPLAY_SIZE],
+    pub keys: [AtomicU8; NUM_KEYS],
+    pub audio: AtomicU8,
+    pub hires: AtomicU8,
+    _padding: [u8; 2],
+    pub frame_count: AtomicU32,
+    pub width: AtomicU16,
+    pub height: AtomicU16,
+}
+
+const _: () = assert!(core::mem::offset_of!(HotState, keys) == HotState::KEYS_OFFSET);
+const _: () = assert!(core::mem::offset_of!(HotState, audio) == HotState::AUDIO_OFFSET);
+const _: () = assert!(core::mem::offset_of!(HotState, hires) == HotState::HIRES_OFFSET);
+const _: () = assert!(core::mem::offset_of!(HotState, frame_count) == HotState::FRAME_COUNT_OFFSET);
+const _: () = assert!(core::mem::offset_of!(HotState, width) == HotState::WIDTH_OFFSET);
+const _: () = assert!(core::mem::offset_of!(HotState, height) == HotState::HEIGHT_OFFSET);
+
+impl HotState {
+    pub const DISPLAY_LEN: usize = HOT_DISPLAY_SIZE;
+    pub const DISPLAY_OFFSET: usize = 0;
+    pub const KEYS_OFFSET: usize = HOT_DISPLAY_SIZE;
+    pub const AUDIO_OFFSET: usize = Self::KEYS_OFFSET + NUM_KEYS;
+    pub const HIRES_OFFSET: usize = Self::AUDIO_OFFSET + 1;
+    pub const FRAME_COUNT_OFFSET: usize = Self::AUDIO_OFFSET + 4;
+    pub const WIDTH_OFFSET: usize = Self::FRAME_COUNT_OFFSET + 4;
+    pub const HEIGHT_OFFSET: usize = Self::WIDTH_OFFSET + 2;
+
+    #[allow(clippy::declare_interior_mutable_const)]
+    const ZERO: AtomicU8 = AtomicU8::new(0);
+
+    pub const fn new() -> Self {
+        Self {
+            display: [Self::ZERO; HOT_DISPLAY_SIZE],
+            keys: [Self::ZERO; NUM_KEYS],
+            audio: AtomicU8::new(0),
+            hires: AtomicU8::new(0),
+            _padding: [0; 2],
+            frame_count: AtomicU32::new(0),
+            width: AtomicU16::new(0),
+            height: AtomicU16::new(0),
+        }
+    }
+
+    // Allocates a state block that lives for the rest of the program, for
+    // hosts that don't place one in static memory themselves.
+    pub fn leak() -> &'static HotState {
+        Box::leak(Box::new(HotState::new()))
+    }
+
+    // Address of the block, e.g. to build typed-array views over wasm memory.
+    pub fn as_ptr(&self) -> *const u8 {
+        self as *const HotState as *const u8
+    }
+}
+
+impl Default for HotState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hachi {
+    // Builds a machine whose display, keys and audio flag are mirrored into
+    // `state`, so another thread can read frames without copying them out.
+    pub fn with_hot_state(state: &'static HotState) -> Self {
+        let mut hachi = Hachi::new();
+        hachi.hot_state = Some(state);
+        hachi.publish_display();
+        hachi.publish_timers();
+        hachi
+    }
+
+    pub fn hot_state(&self) -> Option<&'static HotState> {
+        self.hot_state
+    }
+
+    pub(crate) fn publish_display(&self) {
+        if let Some(hot) = self.hot_state {
+            for (cell, &on) in hot.display.iter().zip(self.display.iter()) {
+                cell.store(on as u8, Ordering::Relaxed);
+            }
+            hot.hires.store(self.is_hires() as u8, Ordering::Relaxed);
+            hot.width
+                .store(self.display_width as u16, Ordering::Relaxed);
+            hot.height
+                .store(self.display_height as u16, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn publish_timers(&self) {
+        if let Some(hot) = self.hot_state {
+            hot.audio
+                .store((self.sound_timer > 0) as u8, Ordering::Relaxed);
+            hot.frame_count
+                .store(self.frame_count as u32, Ordering::Release);
+        }
+    }
+
+    pub(crate) fn publish_key(&self, key: Key, pressed: bool) {
+        if let Some(hot) = self.hot_state {
+            hot.keys[key.index()].store(pressed as u8, Ordering::Relaxed);
+        }
+    }
+
+    // Applies key changes the UI made to the shared block since last time.
+    pub(crate) fn pull_hot_keys(&mut self) {
+        let hot = match self.hot_state {
+            Some(hot) => hot,
+            None => return,
+        };
+
+        for key in Key::ALL {
+            let pressed = hot.keys[key.index()].load(Ordering::Relaxed) != 0;
+            if pressed != self.keys[key.index()] {
+                self.keypress(key, pressed);
+            }
+        }
+    }
+}
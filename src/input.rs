@@ -0,0 +1,107 @@
+use crate::{Hachi, Key, NUM_KEYS};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputMode {
+    // EX9E and EXA1 see exactly the keys held at the moment they execute.
+    #[default]
+    Immediate,
+    // Models the COSMAC VIP keypad latch: a press latches that key's value
+    // and EX9E/EXA1 test against the latch. A released key stays latched
+    // until a skip instruction has seen it, so taps shorter than the ROM's
+    // polling interval still register, and only the most recently pressed
+    // key is visible.
+    VipLatch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct KeyLatch {
+    pub(crate) key: Option<Key>,
+    pub(crate) observed: bool,
+    pub(crate) released: bool,
+}
+
+impl KeyLatch {
+    fn press(&mut self, key: Key) {
+        *self = KeyLatch {
+            key: Some(key),
+            observed: false,
+            released: false,
+        };
+    }
+
+    fn release(&mut self, key: Key) {
+        if self.key == Some(key) {
+            self.released = true;
+            if self.observed {
+                self.key = None;
+            }
+        }
+    }
+
+    fn test(&mut self, key: Key) -> bool {
+        if self.key != Some(key) {
+            return false;
+        }
+
+        self.observed = true;
+        if self.released {
+            self.key = None;
+        }
+        true
+    }
+}
+
+impl Hachi {
+    // Whether the host has `key` held, as last reported through `keypress`.
+    pub fn is_key_pressed(&self, key: Key) -> bool {
+        self.keys[key.index()]
+    }
+
+    // Held state of every key, indexed by `Key::index`.
+    pub fn keys(&self) -> [bool; NUM_KEYS] {
+        self.keys
+    }
+
+    pub fn input_mode(&self) -> InputMode {
+        self.input_mode
+    }
+
+    pub fn set_input_mode(&mut self, mode: InputMode) {
+        self.input_mode = mode;
+        self.key_latch = KeyLatch::default();
+    }
+
+    pub(crate) fn latch_key(&mut self, key: Key, pressed: bool) {
+        if pressed {
+            self.key_latch.press(key);
+        } else {
+            self.key_latch.release(key);
+        }
+    }
+
+    // One attempt at completing FX0A. As on the COSMAC VIP, the first key
+    // pressed is remembered and the instruction completes once it's
+    // released, so a key held across frames isn't read twice.
+    pub(crate) fn wait_for_key(&mut self) -> Option<Key> {
+        if self.quirks.key_wait_on_press {
+            return Key::ALL.iter().copied().find(|key| self.keys[key.index()]);
+        }
+
+        match self.key_wait {
+            Some(key) if !self.keys[key.index()] => self.key_wait.take(),
+            Some(_) => None,
+            None => {
+                self.key_wait = Key::ALL.iter().copied().find(|key| self.keys[key.index()]);
+                None
+            }
+        }
+    }
+
+    // Whether EX9E/EXA1 should consider `key` held.
+    pub(crate) fn is_key_down(&mut self, key: Key) -> bool {
+        match self.input_mode {
+            InputMode::Immediate => self.keys[key.index()],
+            InputMode::VipLatch => self.key_latch.test(key),
+        }
+    }
+}
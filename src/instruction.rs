@@ -0,0 +1,387 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::Variant;
+
+// A decoded CHIP-8 instruction. `x` and `y` are register indices, `nn` and
+// `nnn` immediates, matching the usual opcode notation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    // 0000, whose meaning depends on `ZeroOpcodePolicy`.
+    Zero,
+    ClearScreen,
+    Return,
+    // SUPER-CHIP
+    ScrollDown { n: u8 },
+    ScrollRight,
+    ScrollLeft,
+    Exit,
+    LowRes,
+    HighRes,
+    // XO-CHIP
+    ScrollUp { n: u8 },
+    Jump { nnn: u16 },
+    Call { nnn: u16 },
+    SkipEqImm { x: u8, nn: u8 },
+    SkipNeImm { x: u8, nn: u8 },
+    SkipEqReg { x: u8, y: u8 },
+    SaveRange { x: u8, y: u8 },
+    LoadRange { x: u8, y: u8 },
+    SetImm { x: u8, nn: u8 },
+    AddImm { x: u8, nn: u8 },
+    Set { x: u8, y: u8 },
+    Or { x: u8, y: u8 },
+    And { x: u8, y: u8 },
+    Xor { x: u8, y: u8 },
+    Add { x: u8, y: u8 },
+    Sub { x: u8, y: u8 },
+    ShiftRight { x: u8, y: u8 },
+    SubReversed { x: u8, y: u8 },
+    ShiftLeft { x: u8, y: u8 },
+    SkipNeReg { x: u8, y: u8 },
+    SetI { nnn: u16 },
+    // F000 NNNN; the address is the word after the opcode.
+    SetILong,
+    JumpOffset { nnn: u16 },
+    Random { x: u8, nn: u8 },
+    Draw { x: u8, y: u8, n: u8 },
+    SkipKey { x: u8 },
+    SkipNotKey { x: u8 },
+    SelectPlanes { n: u8 },
+    LoadAudio,
+    GetDelay { x: u8 },
+    WaitKey { x: u8 },
+    SetDelay { x: u8 },
+    SetSound { x: u8 },
+    AddI { x: u8 },
+    FontChar { x: u8 },
+    BigFontChar { x: u8 },
+    SetPitch { x: u8 },
+    Bcd { x: u8 },
+    Store { x: u8 },
+    Load { x: u8 },
+    SaveFlags { x: u8 },
+    LoadFlags { x: u8 },
+    // CHIP-8X
+    CycleBackground,
+    AddNibbles { x: u8, y: u8 },
+    ColorZones { x: u8, y: u8, n: u8 },
+    SkipKey2 { x: u8 },
+    SkipNotKey2 { x: u8 },
+    SetTone { x: u8 },
+    // MEGA-CHIP
+    MegaOff,
+    MegaOn,
+    ScrollUpMega { n: u8 },
+    // 01NN NNNN; the low 16 bits of the address are the word after the
+    // opcode.
+    LoadIHigh { nn: u8 },
+    LoadPalette { nn: u8 },
+    SpriteWidth { nn: u8 },
+    SpriteHeight { nn: u8 },
+    SetAlpha { nn: u8 },
+    PlaySample { n: u8 },
+    StopSample,
+    SetBlend { n: u8 },
+    CollisionColor { nn: u8 },
+    Unknown { opcode: u16 },
+}
+
+// A piece of machine state an instruction can read or write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Register {
+    V(u8),
+    I,
+    Pc,
+    Sp,
+    DelayTimer,
+    SoundTimer,
+}
+
+impl Instruction {
+    pub fn decode(op: u16) -> Instruction {
+        let x = ((op & 0x0F00) >> 8) as u8;
+        let y = ((op & 0x00F0) >> 4) as u8;
+        let n = (op & 0x000F) as u8;
+        let nn = (op & 0x00FF) as u8;
+        let nnn = op & 0x0FFF;
+
+        match (op >> 12, x, y, n) {
+            (0, 0, 0, 0) => Instruction::Zero,
+            (0, 0, 0xE, 0) => Instruction::ClearScreen,
+            (0, 0, 0xE, 0xE) => Instruction::Return,
+            (0, 0, 0xC, _) => Instruction::ScrollDown { n },
+            (0, 0, 0xD, _) => Instruction::ScrollUp { n },
+            (0, 0, 0xF, 0xB) => Instruction::ScrollRight,
+            (0, 0, 0xF, 0xC) => Instruction::ScrollLeft,
+            (0, 0, 0xF, 0xD) => Instruction::Exit,
+            (0, 0, 0xF, 0xE) => Instruction::LowRes,
+            (0, 0, 0xF, 0xF) => Instruction::HighRes,
+            (1, _, _, _) => Instruction::Jump { nnn },
+            (2, _, _, _) => Instruction::Call { nnn },
+            (3, _, _, _) => Instruction::SkipEqImm { x, nn },
+            (4, _, _, _) => Instruction::SkipNeImm { x, nn },
+            (5, _, _, 0) => Instruction::SkipEqReg { x, y },
+            (5, _, _, 2) => Instruction::SaveRange { x, y },
+            (5, _, _, 3) => Instruction::LoadRange { x, y },
+            (6, _, _, _) => Instruction::SetImm { x, nn },
+            (7, _, _, _) => Instruction::AddImm { x, nn },
+            (8, _, _, 0) => Instruction::Set { x, y },
+            (8, _, _, 1) => Instruction::Or { x, y },
+            (8, _, _, 2) => Instruction::And { x, y },
+            (8, _, _, 3) => Instruction::Xor { x, y },
+            (8, _, _, 4) => Instruction::Add { x, y },
+            (8, _, _, 5) => Instruction::Sub { x, y },
+            (8, _, _, 6) => Instruction::ShiftRight { x, y },
+            (8, _, _, 7) => Instruction::SubReversed { x, y },
+            (8, _, _, 0xE) => Instruction::ShiftLeft { x, y },
+            (9, _, _, 0) => Instruction::SkipNeReg { x, y },
+            (0xA, _, _, _) => Instruction::SetI { nnn },
+            (0xB, _, _, _) => Instruction::JumpOffset { nnn },
+            (0xC, _, _, _) => Instruction::Random { x, nn },
+            (0xD, _, _, _) => Instruction::Draw { x, y, n },
+            (0xE, _, 9, 0xE) => Instruction::SkipKey { x },
+            (0xE, _, 0xA, 1) => Instruction::SkipNotKey { x },
+            (0xF, 0, 0, 0) => Instruction::SetILong,
+            (0xF, _, 0, 1) => Instruction::SelectPlanes { n: x },
+            (0xF, 0, 0, 2) => Instruction::LoadAudio,
+            (0xF, _, 0, 7) => Instruction::GetDelay { x },
+            (0xF, _, 0, 0xA) => Instruction::WaitKey { x },
+            (0xF, _, 1, 5) => Instruction::SetDelay { x },
+            (0xF, _, 1, 8) => Instruction::SetSound { x },
+            (0xF, _, 1, 0xE) => Instruction::AddI { x },
+            (0xF, _, 2, 9) => Instruction::FontChar { x },
+            (0xF, _, 3, 0) => Instruction::BigFontChar { x },
+            (0xF, _, 3, 0xA) => Instruction::SetPitch { x },
+            (0xF, _, 3, 3) => Instruction::Bcd { x },
+            (0xF, _, 5, 5) => Instruction::Store { x },
+            (0xF, _, 6, 5) => Instruction::Load { x },
+            (0xF, _, 7, 5) => Instruction::SaveFlags { x },
+            (0xF, _, 8, 5) => Instruction::LoadFlags { x },
+            _ => Instruction::Unknown { opcode: op },
+        }
+    }
+
+    // Decodes `op` the way `variant` runs it: CHIP-8X and MEGA-CHIP give
+    // some opcodes new meanings, and instructions the variant doesn't have
+    // come back as `Unknown`. `decode` is the same as decoding for XO-CHIP.
+    pub fn decode_for(op: u16, variant: Variant) -> Instruction {
+        let x = ((op & 0x0F00) >> 8) as u8;
+        let y = ((op & 0x00F0) >> 4) as u8;
+        let n = (op & 0x000F) as u8;
+        let nn = (op & 0x00FF) as u8;
+
+        let extended = match variant {
+            Variant::Chip8X => match (op >> 12, nn) {
+                _ if op == 0x02A0 => Some(Instruction::CycleBackground),
+                (5, _) if n == 1 => Some(Instruction::AddNibbles { x, y }),
+                (0xB, _) => Some(Instruction::ColorZones { x, y, n }),
+                (0xE, 0xF2) => Some(Instruction::SkipKey2 { x }),
+                (0xE, 0xF5) => Some(Instruction::SkipNotKey2 { x }),
+                (0xF, 0xF8) => Some(Instruction::SetTone { x }),
+                _ => None,
+            },
+            #[cfg(feature = "megachip")]
+            Variant::MegaChip => match (op >> 8, nn) {
+                (0, 0x10) => Some(Instruction::MegaOff),
+                (0, 0x11) => Some(Instruction::MegaOn),
+                (0, _) if y == 0xB => Some(Instruction::ScrollUpMega { n }),
+                (1, _) => Some(Instruction::LoadIHigh { nn }),
+                (2, _) => Some(Instruction::LoadPalette { nn }),
+                (3, _) => Some(Instruction::SpriteWidth { nn }),
+                (4, _) => Some(Instruction::SpriteHeight { nn }),
+                (5, _) => Some(Instruction::SetAlpha { nn }),
+                (6, _) => Some(Instruction::PlaySample { n }),
+                (7, _) => Some(Instruction::StopSample),
+                (8, _) => Some(Instruction::SetBlend { n }),
+                (9, _) => Some(Instruction::CollisionColor { nn }),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(instruction) = extended {
+            return instruction;
+        }
+
+        use Instruction::*;
+        let instruction = Instruction::decode(op);
+        let supported = match instruction {
+            ScrollDown { .. } | ScrollRight | ScrollLeft | Exit | LowRes | HighRes => {
+                variant.has_schip_opcodes()
+            }
+            BigFontChar { .. } | SaveFlags { .. } | LoadFlags { .. } => variant.has_schip_opcodes(),
+            ScrollUp { .. } | SaveRange { .. } | LoadRange { .. } | SetILong => {
+                variant.has_xochip_opcodes()
+            }
+            SelectPlanes { .. } | LoadAudio | SetPitch { .. } => variant.has_xochip_opcodes(),
+            _ => true,
+        };
+        if supported {
+            instruction
+        } else {
+            Unknown { opcode: op }
+        }
+    }
+
+    // Registers whose values the instruction depends on.
+    pub fn reads(&self) -> Vec<Register> {
+        use Instruction::*;
+        use Register::*;
+
+        match *self {
+            Zero | ClearScreen | Jump { .. } | SetImm { .. } | SetI { .. } => vec![],
+            ScrollDown { .. } | ScrollRight | ScrollLeft | Exit | LowRes | HighRes => vec![],
+            ScrollUp { .. } | SelectPlanes { .. } | SetILong => vec![],
+            LoadAudio | LoadRange { .. } => vec![I],
+            SaveRange { x, y } => (x.min(y)..=x.max(y)).map(V).chain([I]).collect(),
+            SetPitch { x } => vec![V(x)],
+            Unknown { .. } | GetDelay { .. } | WaitKey { .. } => vec![],
+            Return => vec![Sp],
+            Call { .. } => vec![Pc, Sp],
+            SkipEqImm { x, .. } | SkipNeImm { x, .. } | AddImm { x, .. } => vec![V(x)],
+            SkipKey { x } | SkipNotKey { x } | SetDelay { x } | SetSound { x } => vec![V(x)],
+            FontChar { x } | BigFontChar { x } => vec![V(x)],
+            Set { y, .. } => vec![V(y)],
+            SkipEqReg { x, y } | SkipNeReg { x, y } => vec![V(x), V(y)],
+            Or { x, y } | And { x, y } | Xor { x, y } | Add { x, y } => vec![V(x), V(y)],
+            Sub { x, y } | SubReversed { x, y } => vec![V(x), V(y)],
+            ShiftRight { x, .. } | ShiftLeft { x, .. } => vec![V(x)],
+            JumpOffset { .. } => vec![V(0)],
+            Random { .. } => vec![],
+            Draw { x, y, .. } => vec![V(x), V(y), I],
+            AddI { x } => vec![V(x), I],
+            Bcd { x } => vec![V(x), I],
+            Store { x } => (0..=x).map(V).chain([I]).collect(),
+            Load { .. } => vec![I],
+            SaveFlags { x } => (0..=x.min(7)).map(V).collect(),
+            LoadFlags { .. } => vec![],
+            CycleBackground | MegaOff | MegaOn | ScrollUpMega { .. } | StopSample => vec![],
+            AddNibbles { x, y } => vec![V(x), V(y)],
+            ColorZones { x, y, .. } => vec![V(x), V((x + 1) & 0xF), V(y)],
+            SkipKey2 { x } | SkipNotKey2 { x } | SetTone { x } => vec![V(x)],
+            LoadIHigh { .. } | SpriteWidth { .. } | SpriteHeight { .. } => vec![],
+            SetAlpha { .. } | SetBlend { .. } | CollisionColor { .. } => vec![],
+            LoadPalette { .. } | PlaySample { .. } => vec![I],
+        }
+    }
+
+    // Registers the instruction may change, not counting the PC advancing
+    // to the next instruction.
+    pub fn writes(&self) -> Vec<Register> {
+        use Instruction::*;
+        use Register::*;
+
+        match *self {
+            Zero | ClearScreen | Unknown { .. } | Bcd { .. } | Store { .. } => vec![],
+            ScrollDown { .. } | ScrollRight | ScrollLeft | LowRes | HighRes => vec![],
+            ScrollUp { .. } | SelectPlanes { .. } | LoadAudio | SaveRange { .. } => vec![],
+            SetPitch { .. } => vec![],
+            SetILong => vec![I, Pc],
+            LoadRange { x, y } => (x.min(y)..=x.max(y)).map(V).collect(),
+            SaveFlags { .. } => vec![],
+            Exit => vec![Pc],
+            SetDelay { .. } => vec![DelayTimer],
+            SetSound { .. } => vec![SoundTimer],
+            Return | Call { .. } => vec![Pc, Sp],
+            Jump { .. } | JumpOffset { .. } => vec![Pc],
+            SkipEqImm { .. } | SkipNeImm { .. } | SkipEqReg { .. } | SkipNeReg { .. } => vec![Pc],
+            SkipKey { .. } | SkipNotKey { .. } => vec![Pc],
+            SetImm { x, .. } | AddImm { x, .. } | Set { x, .. } | Random { x, .. } => vec![V(x)],
+            GetDelay { x } => vec![V(x)],
+            WaitKey { x } => vec![V(x), Pc],
+            Or { x, .. } | And { x, .. } | Xor { x, .. } => vec![V(x)],
+            Add { x, .. } | Sub { x, .. } | SubReversed { x, .. } => vec![V(x), V(0xF)],
+            ShiftRight { x, .. } | ShiftLeft { x, .. } => vec![V(x), V(0xF)],
+            SetI { .. } | FontChar { .. } | BigFontChar { .. } => vec![I],
+            AddI { .. } => vec![I, V(0xF)],
+            Draw { .. } => vec![V(0xF)],
+            Load { x } => (0..=x).map(V).collect(),
+            LoadFlags { x } => (0..=x.min(7)).map(V).collect(),
+            CycleBackground | ColorZones { .. } | SetTone { .. } => vec![],
+            AddNibbles { x, .. } => vec![V(x)],
+            SkipKey2 { .. } | SkipNotKey2 { .. } => vec![Pc],
+            MegaOff | MegaOn | ScrollUpMega { .. } | LoadPalette { .. } => vec![],
+            SpriteWidth { .. } | SpriteHeight { .. } | SetAlpha { .. } => vec![],
+            PlaySample { .. } | StopSample | SetBlend { .. } | CollisionColor { .. } => vec![],
+            LoadIHigh { .. } => vec![I, Pc],
+        }
+    }
+
+    // The opcode pattern the instruction was decoded from, e.g. "8XY4".
+    pub fn pattern(&self) -> &'static str {
+        use Instruction::*;
+
+        match *self {
+            Zero => "0000",
+            ClearScreen => "00E0",
+            Return => "00EE",
+            ScrollDown { .. } => "00CN",
+            ScrollUp { .. } => "00DN",
+            ScrollRight => "00FB",
+            ScrollLeft => "00FC",
+            Exit => "00FD",
+            LowRes => "00FE",
+            HighRes => "00FF",
+            Jump { .. } => "1NNN",
+            Call { .. } => "2NNN",
+            SkipEqImm { .. } => "3XNN",
+            SkipNeImm { .. } => "4XNN",
+            SkipEqReg { .. } => "5XY0",
+            SaveRange { .. } => "5XY2",
+            LoadRange { .. } => "5XY3",
+            SetImm { .. } => "6XNN",
+            AddImm { .. } => "7XNN",
+            Set { .. } => "8XY0",
+            Or { .. } => "8XY1",
+            And { .. } => "8XY2",
+            Xor { .. } => "8XY3",
+            Add { .. } => "8XY4",
+            Sub { .. } => "8XY5",
+            ShiftRight { .. } => "8XY6",
+            SubReversed { .. } => "8XY7",
+            ShiftLeft { .. } => "8XYE",
+            SkipNeReg { .. } => "9XY0",
+            SetI { .. } => "ANNN",
+            JumpOffset { .. } => "BNNN",
+            Random { .. } => "CXNN",
+            Draw { .. } => "DXYN",
+            SkipKey { .. } => "EX9E",
+            SkipNotKey { .. } => "EXA1",
+            SetILong => "F000",
+            SelectPlanes { .. } => "FN01",
+            LoadAudio => "F002",
+            GetDelay { .. } => "FX07",
+            WaitKey { .. } => "FX0A",
+            SetDelay { .. } => "FX15",
+            SetSound { .. } => "FX18",
+            AddI { .. } => "FX1E",
+            FontChar { .. } => "FX29",
+            BigFontChar { .. } => "FX30",
+            SetPitch { .. } => "FX3A",
+            Bcd { .. } => "FX33",
+            Store { .. } => "FX55",
+            Load { .. } => "FX65",
+            SaveFlags { .. } => "FX75",
+            LoadFlags { .. } => "FX85",
+            CycleBackground => "02A0",
+            AddNibbles { .. } => "5XY1",
+            ColorZones { .. } => "BXYN",
+            SkipKey2 { .. } => "EXF2",
+            SkipNotKey2 { .. } => "EXF5",
+            SetTone { .. } => "FXF8",
+            MegaOff => "0010",
+            MegaOn => "0011",
+            ScrollUpMega { .. } => "00BN",
+            LoadIHigh { .. } => "01NN",
+            LoadPalette { .. } => "02NN",
+            SpriteWidth { .. } => "03NN",
+            SpriteHeight { .. } => "04NN",
+            SetAlpha { .. } => "05NN",
+            PlaySample { .. } => "060N",
+            StopSample => "0700",
+            SetBlend { .. } => "080N",
+            CollisionColor { .. } => "09NN",
+            Unknown { .. } => "unknown",
+        }
+    }
+}
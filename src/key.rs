@@ -0,0 +1,68 @@
+use crate::NUM_KEYS;
+
+// The sixteen keys of the hex keypad, laid out on the original COSMAC VIP as
+//
+//     1 2 3 C
+//     4 5 6 D
+//     7 8 9 E
+//     A 0 B F
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Key {
+    Key0,
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+    KeyA,
+    KeyB,
+    KeyC,
+    KeyD,
+    KeyE,
+    KeyF,
+}
+
+impl Key {
+    pub const ALL: [Key; NUM_KEYS] = [
+        Key::Key0,
+        Key::Key1,
+        Key::Key2,
+        Key::Key3,
+        Key::Key4,
+        Key::Key5,
+        Key::Key6,
+        Key::Key7,
+        Key::Key8,
+        Key::Key9,
+        Key::KeyA,
+        Key::KeyB,
+        Key::KeyC,
+        Key::KeyD,
+        Key::KeyE,
+        Key::KeyF,
+    ];
+
+    pub fn from_index(idx: usize) -> Option<Key> {
+        Self::ALL.get(idx).copied()
+    }
+
+    // Only the low nibble of a register selects a key, matching the way the
+    // VIP drove its keypad latch.
+    pub fn from_nibble(value: u8) -> Key {
+        Self::ALL[(value & 0xF) as usize]
+    }
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl From<Key> for u8 {
+    fn from(key: Key) -> u8 {
+        key as u8
+    }
+}
@@ -0,0 +1,150 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::ops::Range;
+
+use crate::disasm::{disassemble_rom_for, DisasmLine};
+use crate::{Hachi, Instruction};
+
+const HEXDUMP_WIDTH: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryLabel {
+    pub range: Range<u16>,
+    pub name: String,
+    pub note: Option<String>,
+}
+
+impl MemoryLabel {
+    pub fn contains(&self, addr: u16) -> bool {
+        self.range.contains(&addr)
+    }
+}
+
+impl Hachi {
+    // Names an address range so the debugging helpers can refer to it.
+    // Labels may overlap; lookups prefer the narrowest match.
+    pub fn add_label(&mut self, range: Range<u16>, name: &str, note: Option<&str>) {
+        let label = MemoryLabel {
+            range,
+            name: name.to_string(),
+            note: note.map(str::to_string),
+        };
+        let idx = self
+            .labels
+            .partition_point(|existing| existing.range.start <= label.range.start);
+        self.labels.insert(idx, label);
+    }
+
+    // Removes every label called `name`, returning whether any existed.
+    pub fn remove_label(&mut self, name: &str) -> bool {
+        let before = self.labels.len();
+        self.labels.retain(|label| label.name != name);
+        self.labels.len() != before
+    }
+
+    pub fn clear_labels(&mut self) {
+        self.labels.clear();
+    }
+
+    // All labels ordered by start address.
+    pub fn labels(&self) -> &[MemoryLabel] {
+        &self.labels
+    }
+
+    pub fn label_at(&self, addr: u16) -> Option<&MemoryLabel> {
+        self.labels
+            .iter()
+            .filter(|label| label.contains(addr))
+            .min_by_key(|label| label.range.len())
+    }
+
+    // Lists RAM in `range` as instructions for the current variant. Each
+    // line gets the label starting at its address, and jumps, calls and
+    // loads of I are followed by the name of the label they point into,
+    // e.g. `CALL 0x2A0  ; draw_score`.
+    pub fn disassemble_ram(&self, range: Range<usize>) -> Vec<DisasmLine> {
+        let start = range.start.min(self.ram.len());
+        let end = range.end.clamp(start, self.ram.len());
+        let mut lines = disassemble_rom_for(&self.ram[start..end], start as u16, self.variant);
+
+        for line in &mut lines {
+            line.label = self
+                .labels
+                .iter()
+                .find(|label| label.range.start == line.addr)
+                .map(|label| label.name.clone());
+
+            let op = match line.bytes[..] {
+                [high, low, ..] => u16::from_be_bytes([high, low]),
+                _ => continue,
+            };
+            let target = match Instruction::decode_for(op, self.variant) {
+                Instruction::Jump { nnn }
+                | Instruction::Call { nnn }
+                | Instruction::SetI { nnn }
+                | Instruction::JumpOffset { nnn } => nnn,
+                _ => continue,
+            };
+            if let Some(label) = self.label_at(target) {
+                let _ = write!(line.text, "  ; {}", label.name);
+            }
+        }
+
+        lines
+    }
+
+    // Formats `range` as a classic hex dump, sixteen bytes per line with an
+    // ASCII column, announcing each label where its range begins.
+    pub fn hexdump(&self, range: Range<usize>) -> String {
+        let start = range.start.min(self.ram.len());
+        let end = range.end.min(self.ram.len());
+        let mut out = String::new();
+        let mut next_label = self
+            .labels
+            .partition_point(|label| (label.range.start as usize) < start);
+
+        let mut line_start = start;
+        while line_start < end {
+            let line_end = (line_start + HEXDUMP_WIDTH).min(end);
+
+            while let Some(label) = self.labels.get(next_label) {
+                if label.range.start as usize >= line_end {
+                    break;
+                }
+                let _ = write!(
+                    out,
+                    "; {} [{:03X}..{:03X})",
+                    label.name, label.range.start, label.range.end
+                );
+                if let Some(note) = &label.note {
+                    let _ = write!(out, " {}", note);
+                }
+                out.push('\n');
+                next_label += 1;
+            }
+
+            let bytes = &self.ram[line_start..line_end];
+            let _ = write!(out, "{:03X}:", line_start);
+            for byte in bytes {
+                let _ = write!(out, " {:02X}", byte);
+            }
+            for _ in bytes.len()..HEXDUMP_WIDTH {
+                out.push_str("   ");
+            }
+            out.push_str("  ");
+            for &byte in bytes {
+                out.push(if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                });
+            }
+            out.push('\n');
+
+            line_start = line_end;
+        }
+
+        out
+    }
+}
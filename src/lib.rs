@@ -1,11 +1,156 @@
-use rand::random;
+#![no_std]
+
+#[cfg(feature = "std")]
+extern crate std;
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::Ordering;
+
+use battery::BatteryRam;
+use bus::Bus;
+use error::Diagnostics;
+use input::KeyLatch;
+use recording::{InputPlayback, InputRecorder};
+use schip::{BIG_FONTSET, BIG_FONT_ADDRESS, NUM_RPL_FLAGS};
+use shared::{Arc, DisplayBuffer};
+use sound::SoundEvents;
+use timeline::TimelineRecorder;
+use trace::Tracer;
+use xochip::{AUDIO_PATTERN_SIZE, DEFAULT_PITCH};
+
+pub mod asm;
+mod battery;
+mod breakpoint;
+mod builder;
+mod bus;
+pub mod c8b;
+mod chip8x;
+mod debug;
+mod diff;
+mod dirty;
+pub mod disasm;
+mod error;
+mod flags;
+mod font;
+mod frame;
+mod halt;
+mod hash;
+mod history;
+mod host;
+mod hot;
+mod input;
+mod instruction;
+mod key;
+mod labels;
+pub mod lint;
+#[cfg(feature = "megachip")]
+mod megachip;
+mod memory;
+mod observer;
+pub mod octocart;
+mod orientation;
+mod palette;
+mod pipeline;
+mod profile;
+mod quirks;
+mod recording;
+mod registers;
+mod render;
+mod report;
+mod rewind;
+mod romdb;
+#[cfg(feature = "roms")]
+mod roms;
+mod savestate;
+mod scheduler;
+mod schip;
+mod shared;
+mod slots;
+mod sound;
+mod speed;
+mod sprite;
+mod stack;
+mod state;
+mod status;
+mod timeline;
+mod timer;
+mod trace;
+mod twopage;
+mod variant;
+mod watchdog;
+mod watchpoint;
+mod xochip;
+
+pub use battery::{BatteryStorage, MemoryBattery, DEFAULT_BATTERY_RAM};
+pub use builder::{BuildError, HachiBuilder};
+pub use bus::{
+    Peripheral, PeripheralId, SerialPort, SERIAL_STATUS_RX_READY, SERIAL_STATUS_TX_READY,
+};
+pub use chip8x::Chip8xColor;
+pub use debug::Debugger;
+pub use diff::{RegisterChange, StateDiff};
+pub use error::{ErrorPolicy, HachiError, LoadError};
+pub use flags::{FlagStorage, MemoryFlags};
+pub use frame::{Frame, FrameSummary};
+pub use halt::{HaltReason, ZeroOpcodePolicy};
+pub use history::{HistoryEntry, PcHistory, DEFAULT_PC_HISTORY_LEN};
+#[cfg(feature = "std")]
+pub use host::ThreadRandom;
+pub use host::{RandomFn, RandomSource, Xorshift};
+pub use hot::HotState;
+pub use input::InputMode;
+pub use instruction::{Instruction, Register};
+pub use key::Key;
+pub use labels::MemoryLabel;
+#[cfg(feature = "megachip")]
+pub use megachip::{BlendMode, MegaSound, MEGA_DISPLAY_HEIGHT, MEGA_DISPLAY_WIDTH, MEGA_RAM_SIZE};
+pub use observer::HachiObserver;
+pub use orientation::{Orientation, Rotation};
+pub use palette::DEFAULT_PALETTE;
+pub use pipeline::TickRecord;
+pub use quirks::Quirks;
+pub use recording::{InputEvent, InputRecording};
+pub use render::{PixelLayout, RenderError, RenderFormat};
+pub use report::{CrashReport, RamWindow, ReportCause};
+pub use rewind::Rewind;
+#[cfg(feature = "romdb")]
+pub use romdb::ROM_PROFILES;
+pub use romdb::{lookup_rom, RomProfile};
+#[cfg(feature = "roms")]
+pub use roms::{builtin_rom, builtin_roms, BuiltinRom};
+pub use savestate::{StateBuf, StateError, StatePool};
+pub use scheduler::TimingMode;
+pub use schip::{DisplayMode, HIRES_DISPLAY_HEIGHT, HIRES_DISPLAY_WIDTH};
+pub use shared::SharedDisplay;
+pub use slots::{SaveSlots, SlotError, SlotInfo, SlotStorage};
+pub use sound::SoundEvent;
+pub use speed::{SpeedProfile, DEFAULT_INSTRUCTIONS_PER_FRAME};
+pub use sprite::{Sprite, SPRITE_WIDTH};
+pub use stack::{StackStats, MAX_STACK_DEPTH};
+pub use state::HachiState;
+pub use status::TickStatus;
+pub use timeline::{AvEvent, Timeline};
+pub use timer::{TimerAccumulator, DEFAULT_TIMER_HZ};
+pub use trace::{TraceEntry, TraceSink};
+pub use twopage::TWO_PAGE_DISPLAY_HEIGHT;
+pub use variant::Variant;
+pub use watchdog::RunOutcome;
+pub use watchpoint::{MemoryAccess, WatchKind, Watchpoint};
+pub use xochip::XO_RAM_SIZE;
 
 pub const DISPLAY_WIDTH: usize = 64;
 pub const DISPLAY_HEIGHT: usize = 32;
+pub const DEFAULT_STACK_DEPTH: usize = 16;
 
-const RAM_SIZE: usize = 4096;
+pub const RAM_SIZE: usize = 4096;
+// A stock COSMAC VIP had 2 KB.
+pub const VIP_RAM_SIZE: usize = 2048;
 const NUM_REGISTERS: usize = 16;
-const STACK_SIZE: usize = 16;
 const NUM_KEYS: usize = 16;
 
 const START_ADDRESS: u16 = 0x200;
@@ -32,58 +177,284 @@ const FONTSET: [u8; FONTSET_SIZE] = [
 
 pub struct Hachi {
     program_counter: u16,
-    ram: [u8; RAM_SIZE],
-    display: [bool; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+    start_address: u16,
+    ram: Vec<u8>,
+    // Set by the host to fix RAM at one size whatever the variant.
+    custom_ram_size: Option<usize>,
+    display: Arc<DisplayBuffer>,
+    display_width: usize,
+    display_height: usize,
+    second_plane: DisplayBuffer,
+    plane_mask: u8,
+    palette: [[u8; 4]; 4],
+    font: [u8; FONTSET_SIZE],
+    big_font: [u8; BIG_FONTSET.len()],
     v_registers: [u8; NUM_REGISTERS],
     i_register: u16,
     stack_pointer: u16,
-    stack: [u16; STACK_SIZE],
+    stack: Vec<u16>,
+    stack_high_water: u16,
     keys: [bool; NUM_KEYS],
     delay_timer: u8,
     sound_timer: u8,
+    sound_on: bool,
+    sound_events: SoundEvents,
+    frame_count: u64,
+    display_dirty: bool,
+    dirty_rows: Vec<bool>,
+    instructions_per_frame: u32,
+    timer_hz: u32,
+    timer_accumulator: TimerAccumulator,
+    clock_hz: Option<u32>,
+    cycle_accumulator: TimerAccumulator,
+    timing_mode: TimingMode,
+    // VIP machine cycles left in the current frame; negative after an
+    // instruction overran the last one.
+    vip_cycles: i32,
+    instruction_count: u64,
+    input_recorder: Option<InputRecorder>,
+    input_playback: Option<InputPlayback>,
+    rom: Vec<u8>,
+    instruction_pc: u16,
+    error_policy: ErrorPolicy,
+    diagnostics: Diagnostics,
+    watchdog_limit: Option<u64>,
+    idle_instructions: u64,
+    labels: Vec<MemoryLabel>,
+    pc_history: PcHistory,
+    quirks: Quirks,
+    orientation: Orientation,
+    input_mode: InputMode,
+    key_latch: KeyLatch,
+    key_wait: Option<Key>,
+    // Whether the last FX0A executed is still waiting.
+    waiting_for_key: bool,
+    // The frame a DXYN held back by the display-wait quirk started waiting
+    // in.
+    display_wait: Option<u64>,
+    observer: Option<Box<dyn HachiObserver>>,
+    timeline: Option<TimelineRecorder>,
+    random: Box<dyn RandomSource>,
+    hot_state: Option<&'static HotState>,
+    halted: Option<HaltReason>,
+    zero_opcode_policy: ZeroOpcodePolicy,
+    battery: Option<BatteryRam>,
+    bus: Bus,
+    pipeline_trace: bool,
+    two_page_detection: bool,
+    count_opcodes: bool,
+    opcode_counts: BTreeMap<&'static str, u64>,
+    last_tick: Option<TickRecord>,
+    variant: Variant,
+    chip8x: chip8x::Chip8x,
+    #[cfg(feature = "megachip")]
+    mega: megachip::MegaChip,
+    rpl_flags: [u8; NUM_RPL_FLAGS],
+    flag_storage: Option<Box<dyn FlagStorage>>,
+    audio_pattern: [u8; AUDIO_PATTERN_SIZE],
+    pitch: u8,
+    audio_phase: u32,
+    breakpoints: BTreeSet<u16>,
+    stopped_at: Option<u16>,
+    watchpoints: Vec<Watchpoint>,
+    trace: Option<Tracer>,
 }
 
 impl Hachi {
     pub fn new() -> Self {
         let mut hachi = Self {
             program_counter: START_ADDRESS,
-            ram: [0; RAM_SIZE],
-            display: [false; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+            start_address: START_ADDRESS,
+            ram: vec![0; RAM_SIZE],
+            custom_ram_size: None,
+            display: Arc::new(vec![false; DISPLAY_WIDTH * DISPLAY_HEIGHT]),
+            display_width: DISPLAY_WIDTH,
+            display_height: DISPLAY_HEIGHT,
+            second_plane: vec![false; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+            plane_mask: 1,
+            palette: DEFAULT_PALETTE,
+            font: FONTSET,
+            big_font: BIG_FONTSET,
             v_registers: [0; NUM_REGISTERS],
             i_register: 0,
             stack_pointer: 0,
-            stack: [0; STACK_SIZE],
+            stack: vec![0; DEFAULT_STACK_DEPTH],
+            stack_high_water: 0,
             keys: [false; NUM_KEYS],
             delay_timer: 0,
             sound_timer: 0,
+            sound_on: false,
+            sound_events: SoundEvents::new(),
+            frame_count: 0,
+            display_dirty: false,
+            dirty_rows: vec![true; DISPLAY_HEIGHT],
+            instructions_per_frame: DEFAULT_INSTRUCTIONS_PER_FRAME,
+            timer_hz: DEFAULT_TIMER_HZ,
+            timer_accumulator: TimerAccumulator::new(),
+            clock_hz: None,
+            cycle_accumulator: TimerAccumulator::new(),
+            timing_mode: TimingMode::default(),
+            vip_cycles: 0,
+            instruction_count: 0,
+            input_recorder: None,
+            input_playback: None,
+            rom: Vec::new(),
+            instruction_pc: START_ADDRESS,
+            error_policy: ErrorPolicy::default(),
+            diagnostics: Diagnostics::new(),
+            watchdog_limit: None,
+            idle_instructions: 0,
+            labels: Vec::new(),
+            pc_history: PcHistory::new(DEFAULT_PC_HISTORY_LEN),
+            quirks: Quirks::default(),
+            orientation: Orientation::default(),
+            input_mode: InputMode::default(),
+            key_latch: KeyLatch::default(),
+            key_wait: None,
+            waiting_for_key: false,
+            display_wait: None,
+            observer: None,
+            timeline: None,
+            random: host::default_random_source(),
+            hot_state: None,
+            halted: None,
+            zero_opcode_policy: ZeroOpcodePolicy::default(),
+            battery: None,
+            bus: Bus::default(),
+            pipeline_trace: false,
+            two_page_detection: false,
+            count_opcodes: false,
+            opcode_counts: BTreeMap::new(),
+            last_tick: None,
+            variant: Variant::default(),
+            chip8x: chip8x::Chip8x::default(),
+            #[cfg(feature = "megachip")]
+            mega: megachip::MegaChip::default(),
+            rpl_flags: [0; NUM_RPL_FLAGS],
+            flag_storage: None,
+            audio_pattern: [0; AUDIO_PATTERN_SIZE],
+            pitch: DEFAULT_PITCH,
+            audio_phase: 0,
+            breakpoints: BTreeSet::new(),
+            stopped_at: None,
+            watchpoints: Vec::new(),
+            trace: None,
         };
 
-        hachi.ram[..FONTSET_SIZE].copy_from_slice(&FONTSET);
+        hachi.load_fonts();
 
         hachi
     }
 
     pub fn reset(&mut self) {
-        self.program_counter = START_ADDRESS;
-        self.ram = [0; RAM_SIZE];
-        self.display = [false; DISPLAY_WIDTH * DISPLAY_HEIGHT];
+        self.flush_battery_ram();
+        self.program_counter = self.start_address;
+        self.ram.fill(0);
+        #[cfg(feature = "megachip")]
+        if self.is_megachip_mode() {
+            self.set_megachip_mode(false);
+        }
+        self.set_hires(false);
+        self.plane_mask = 1;
+        self.clear_display();
         self.v_registers = [0; NUM_REGISTERS];
-        self.i_register = 0;
+        self.set_i_address(0);
         self.stack_pointer = 0;
-        self.stack = [0; STACK_SIZE];
+        self.stack.fill(0);
+        self.stack_high_water = 0;
         self.keys = [false; NUM_KEYS];
+        self.key_latch = KeyLatch::default();
+        self.key_wait = None;
+        self.waiting_for_key = false;
+        self.display_wait = None;
+        self.chip8x = chip8x::Chip8x::default();
         self.delay_timer = 0;
         self.sound_timer = 0;
-        self.ram[..FONTSET_SIZE].copy_from_slice(&FONTSET);
+        self.audio_pattern = [0; AUDIO_PATTERN_SIZE];
+        self.pitch = DEFAULT_PITCH;
+        self.audio_phase = 0;
+        self.frame_count = 0;
+        self.timer_accumulator.clear();
+        self.cycle_accumulator.clear();
+        self.vip_cycles = 0;
+        self.instruction_pc = self.start_address;
+        self.diagnostics.clear();
+        self.idle_instructions = 0;
+        self.pc_history.clear();
+        self.halted = None;
+        self.stopped_at = None;
+        self.last_tick = None;
+        self.load_fonts();
+        self.reload_battery_ram();
+        self.update_sound();
+        self.publish_timers();
+    }
+
+    // Restarts the last loaded ROM from scratch while keeping configuration
+    // and the keys currently held by the host.
+    pub fn soft_reset(&mut self) {
+        let keys = self.keys;
+        let rom = core::mem::take(&mut self.rom);
+
+        self.reset();
+        // The ROM fitted when it was first loaded, and if there never was
+        // one there is nothing to restore
+        let _ = self.load(&rom);
+        self.keys = keys;
     }
 
-    pub fn tick(&mut self) {
-        let op = self.fetch();
+    pub fn tick(&mut self) -> Result<TickStatus, HachiError> {
+        self.pull_hot_keys();
+        if let Some(reason) = self.halted {
+            return Ok(TickStatus::Halted { reason });
+        }
+        if let Some(status) = self.check_breakpoint() {
+            return Ok(status);
+        }
+        self.play_due_input();
+        self.instruction_pc = self.program_counter;
+        self.instruction_count += 1;
+
+        let result = self.fetch().and_then(|op| {
+            self.pc_history.record(self.instruction_pc, op);
+            self.watch_for_activity(op);
+            self.record_pipeline(op);
+            self.count_opcode(op);
+            let watch_hit = self.pending_watch_hit(op);
+            self.execute(op)?;
+            self.trace_instruction(op);
+            if let Some(reason) = self.halted {
+                return Ok(TickStatus::Halted { reason });
+            }
+            Ok(watch_hit.unwrap_or(TickStatus::Executed))
+        });
+
+        result.or_else(|err| self.recover(err).map(|()| TickStatus::Executed))
+    }
+
+    pub fn tick_n(&mut self, n: u64) -> Result<RunOutcome, HachiError> {
+        for _ in 0..n {
+            match self.tick()? {
+                TickStatus::Executed => (),
+                TickStatus::Breakpoint { pc } => return Ok(RunOutcome::Breakpoint { pc }),
+                TickStatus::Watchpoint { pc, addr, access } => {
+                    return Ok(RunOutcome::Watchpoint { pc, addr, access })
+                }
+                TickStatus::Halted { reason } => return Ok(RunOutcome::Halted { reason }),
+            }
+
+            if let Some(outcome) = self.check_watchdog() {
+                return Ok(outcome);
+            }
+        }
 
-        self.execute(op);
+        Ok(RunOutcome::Completed)
     }
 
     pub fn tick_timers(&mut self) {
+        self.record_timeline();
+        self.frame_count += 1;
+
         if self.delay_timer > 0 {
             self.delay_timer -= 1;
         }
@@ -91,76 +462,195 @@ impl Hachi {
         if self.sound_timer > 0 {
             self.sound_timer -= 1;
         }
+
+        self.update_sound();
+        self.publish_timers();
+        self.flush_battery_ram();
     }
 
     pub fn get_display(&self) -> &[bool] {
-        &self.display
+        &self.display[..]
+    }
+
+    // The display one row at a time, top to bottom, each
+    // `display_width()` pixels long.
+    pub fn display_rows(&self) -> impl Iterator<Item = &[bool]> + '_ {
+        self.display.chunks_exact(self.display_width)
+    }
+
+    pub fn display_width(&self) -> usize {
+        self.display_width
+    }
+
+    pub fn display_height(&self) -> usize {
+        self.display_height
+    }
+
+    pub fn frame(&mut self) -> Frame {
+        let frame = Frame::pack(
+            &self.display[..],
+            self.display_width,
+            self.display_height,
+            self.frame_count,
+            self.display_dirty,
+        );
+        self.display_dirty = false;
+        frame
     }
 
     pub fn get_audio(&self) -> u8 {
         self.sound_timer
     }
 
-    pub fn keypress(&mut self, idx: usize, pressed: bool) {
-        self.keys[idx] = pressed;
+    pub fn keypress(&mut self, key: Key, pressed: bool) {
+        if self.input_playback.is_some() {
+            return;
+        }
+        self.record_key(key, pressed);
+        self.apply_key(key, pressed);
+    }
+
+    fn apply_key(&mut self, key: Key, pressed: bool) {
+        self.keys[key.index()] = pressed;
+        self.latch_key(key, pressed);
+        self.publish_key(key, pressed);
+        self.idle_instructions = 0;
     }
 
-    pub fn load(&mut self, data: &[u8]) {
-        let start = START_ADDRESS as usize;
-        let end = (START_ADDRESS as usize) + data.len();
+    pub fn load(&mut self, data: &[u8]) -> Result<(), LoadError> {
+        let start = self.start_address as usize;
+        let max = self.ram.len().saturating_sub(start);
+        if data.is_empty() {
+            return Err(LoadError::Empty);
+        }
+        if data.len() > max {
+            return Err(LoadError::RomTooLarge {
+                size: data.len(),
+                max,
+            });
+        }
+
+        let end = start + data.len();
         self.ram[start..end].copy_from_slice(data);
+        self.rom.clear();
+        self.rom.extend_from_slice(data);
+        self.program_counter = self.start_address;
+        self.detect_two_page_hires();
+        Ok(())
     }
 
-    fn execute(&mut self, op: u16) {
+    fn execute(&mut self, op: u16) -> Result<(), HachiError> {
         let d1 = (op & 0xF000) >> 12;
         let d2 = (op & 0x0F00) >> 8;
         let d3 = (op & 0x00F0) >> 4;
         let d4 = op & 0x000F;
 
+        if self.variant == Variant::Chip8X && self.execute_chip8x(op) {
+            return Ok(());
+        }
+        #[cfg(feature = "megachip")]
+        if self.variant == Variant::MegaChip && self.execute_megachip(op)? {
+            return Ok(());
+        }
+
         // parse opcode via pattern matching
 
         match (d1, d2, d3, d4) {
-            (0, 0, 0, 0) => (), // no-op
+            (0, 0, 0, 0) => match self.zero_opcode_policy {
+                ZeroOpcodePolicy::NoOp => (),
+                ZeroOpcodePolicy::Halt => {
+                    self.program_counter = self.instruction_pc;
+                    self.halt(HaltReason::FellOffProgram {
+                        pc: self.instruction_pc,
+                    });
+                }
+                ZeroOpcodePolicy::Error => {
+                    return Err(HachiError::FellOffProgram {
+                        pc: self.instruction_pc,
+                    })
+                }
+            },
+            (0, 0, 0xC, n) if self.variant.has_schip_opcodes() => {
+                self.scroll_down(n as usize);
+            }
+            (0, 0, 0xD, n) if self.variant.has_xochip_opcodes() => {
+                self.scroll_up(n as usize);
+            }
+            (0, 2, 3, 0) if self.is_two_page_hires() => {
+                // the two-page interpreter's own clear screen
+                self.clear_display();
+            }
             (0, 0, 0xE, 0) => {
                 // clear display
-                self.display = [false; DISPLAY_WIDTH * DISPLAY_HEIGHT]
+                self.clear_selected_planes();
             }
             (0, 0, 0xE, 0xE) => {
                 // return from subroutine
-                let return_address = self.pop();
+                let return_address = self.pop()?;
                 self.program_counter = return_address;
+                let pc = self.instruction_pc;
+                self.notify(|observer| observer.on_return(pc, return_address));
+            }
+            (0, 0, 0xF, 0xB) if self.variant.has_schip_opcodes() => {
+                self.scroll_sideways(4);
+            }
+            (0, 0, 0xF, 0xC) if self.variant.has_schip_opcodes() => {
+                self.scroll_sideways(-4);
+            }
+            (0, 0, 0xF, 0xD) if self.variant.has_schip_opcodes() => {
+                self.program_counter = self.instruction_pc;
+                self.halt(HaltReason::Exited {
+                    pc: self.instruction_pc,
+                });
+            }
+            (0, 0, 0xF, 0xE) if self.variant.has_schip_opcodes() => {
+                self.switch_resolution(false);
+            }
+            (0, 0, 0xF, 0xF) if self.variant.has_schip_opcodes() => {
+                self.switch_resolution(true);
             }
             (1, _, _, _) => {
                 // jump program counter
                 let nnn = op & 0xFFF;
                 self.program_counter = nnn;
+                if nnn == self.instruction_pc {
+                    self.halt(HaltReason::SelfJump { pc: nnn });
+                }
             }
             (2, _, _, _) => {
                 let nnn = op & 0xFFF;
-                self.push(self.program_counter);
+                self.push(self.program_counter)?;
                 self.program_counter = nnn;
+                let pc = self.instruction_pc;
+                self.notify(|observer| observer.on_call(pc, nnn));
             }
             (3, _, _, _) => {
                 let x = d2 as usize;
                 let nn = (op & 0xFF) as u8;
                 if self.v_registers[x] == nn {
-                    self.program_counter += 2;
+                    self.skip_next_instruction();
                 }
             }
             (4, _, _, _) => {
                 let x = d2 as usize;
                 let nn = (op & 0xFF) as u8;
                 if self.v_registers[x] != nn {
-                    self.program_counter += 2;
+                    self.skip_next_instruction();
                 }
             }
             (5, _, _, 0) => {
                 let x = d2 as usize;
                 let y = d3 as usize;
                 if self.v_registers[x] == self.v_registers[y] {
-                    self.program_counter += 2;
+                    self.skip_next_instruction();
                 }
             }
+            (5, _, _, 2) if self.variant.has_xochip_opcodes() => {
+                self.save_register_range(d2 as usize, d3 as usize)?;
+            }
+            (5, _, _, 3) if self.variant.has_xochip_opcodes() => {
+                self.load_register_range(d2 as usize, d3 as usize)?;
+            }
             (6, _, _, _) => {
                 let x = d2 as usize;
                 let nn = (op & 0xFF) as u8;
@@ -181,18 +671,27 @@ impl Hachi {
                 let x = d2 as usize;
                 let y = d3 as usize;
                 self.v_registers[x] |= self.v_registers[y];
+                if self.quirks.logic_resets_vf {
+                    self.v_registers[0xF] = 0;
+                }
             }
             (8, _, _, 2) => {
                 // bitwise and
                 let x = d2 as usize;
                 let y = d3 as usize;
                 self.v_registers[x] &= self.v_registers[y];
+                if self.quirks.logic_resets_vf {
+                    self.v_registers[0xF] = 0;
+                }
             }
             (8, _, _, 3) => {
                 // bitwise xor
                 let x = d2 as usize;
                 let y = d3 as usize;
                 self.v_registers[x] ^= self.v_registers[y];
+                if self.quirks.logic_resets_vf {
+                    self.v_registers[0xF] = 0;
+                }
             }
             (8, _, _, 4) => {
                 let x = d2 as usize;
@@ -216,6 +715,10 @@ impl Hachi {
             }
             (8, _, _, 6) => {
                 let x = d2 as usize;
+                let y = d3 as usize;
+                if self.quirks.shift_uses_vy {
+                    self.v_registers[x] = self.v_registers[y];
+                }
                 let least_significant_bit = self.v_registers[x] & 1;
                 self.v_registers[x] >>= 1;
                 self.v_registers[0xF] = least_significant_bit;
@@ -232,6 +735,10 @@ impl Hachi {
             }
             (8, _, _, 0xE) => {
                 let x = d2 as usize;
+                let y = d3 as usize;
+                if self.quirks.shift_uses_vy {
+                    self.v_registers[x] = self.v_registers[y];
+                }
                 let most_significant_bit = (self.v_registers[x] >> 7) & 1;
                 self.v_registers[x] <<= 1;
                 self.v_registers[0xF] = most_significant_bit;
@@ -240,42 +747,48 @@ impl Hachi {
                 let x = d2 as usize;
                 let y = d3 as usize;
                 if self.v_registers[x] != self.v_registers[y] {
-                    self.program_counter += 2;
+                    self.skip_next_instruction();
                 }
             }
             (0xA, _, _, _) => {
                 let nnn = op & 0xFFF;
-                self.i_register = nnn;
+                self.set_i_address(nnn as usize);
             }
             (0xB, _, _, _) => {
                 let nnn = op & 0xFFF;
-                self.program_counter = (self.v_registers[0] as u16) + nnn;
+                let offset_reg = if self.quirks.jump_uses_vx {
+                    d2 as usize
+                } else {
+                    0
+                };
+                self.program_counter = (self.v_registers[offset_reg] as u16) + nnn;
             }
             (0xC, _, _, _) => {
                 let x = d2 as usize;
                 let nn = (op & 0xFF) as u8;
-                let rn: u8 = random();
+                let rn = self.random.next_byte();
                 self.v_registers[x] = rn & nn;
             }
+            (0xD, _, _, _) if self.awaiting_vblank() => {
+                self.program_counter = self.instruction_pc;
+            }
             (0xD, _, _, _) => {
-                let x_coord = self.v_registers[d2 as usize] as u16;
-                let y_coord = self.v_registers[d3 as usize] as u16;
-                let num_rows = d4;
-                let mut flipped = false;
+                let x_coord = self.v_registers[d2 as usize] as usize;
+                let y_coord = self.v_registers[d3 as usize] as usize;
+                let (sprite_width, num_rows) = self.sprite_size(d4 as u8);
+                // With both XO-CHIP planes selected the second plane's sprite
+                // follows the first one's in memory
+                let sprite_len = num_rows * sprite_width / 8;
+                let planes = self.plane_mask.count_ones() as usize;
+                self.check_range(self.i_address(), sprite_len * planes)?;
 
-                for y_line in 0..num_rows {
-                    let addr = self.i_register + y_line;
-                    let pixels = self.ram[addr as usize];
-
-                    for x_line in 0..8 {
-                        if (pixels & (0b1000_0000 >> x_line)) != 0 {
-                            // Sprites should wrap around screen, so apply modulo
-                            let x = (x_coord + x_line) as usize % DISPLAY_WIDTH;
-                            let y = (y_coord + y_line) as usize % DISPLAY_HEIGHT;
-                            let idx = x + DISPLAY_WIDTH * y;
-                            flipped |= self.display[idx];
-                            self.display[idx] ^= true;
-                        }
+                let mut flipped = false;
+                let mut addr = self.i_address();
+                for plane in 0..2 {
+                    if self.plane_mask & (1 << plane) != 0 {
+                        let sprite = (addr, sprite_width, num_rows);
+                        flipped |= self.draw_sprite(plane, x_coord, y_coord, sprite);
+                        addr += sprite_len;
                     }
                 }
 
@@ -284,21 +797,41 @@ impl Hachi {
                 } else {
                     self.v_registers[0xF] = 0;
                 }
+                let (pc, x, y, rows) = (
+                    self.instruction_pc,
+                    x_coord as u8,
+                    y_coord as u8,
+                    num_rows as u8,
+                );
+                self.notify(|observer| observer.on_draw(pc, x, y, rows, flipped));
             }
             (0xE, _, 9, 0xE) => {
                 let x = d2 as usize;
-                let vx = self.v_registers[x];
-                let key = self.keys[vx as usize];
-                if key {
-                    self.program_counter += 2;
+                let key = Key::from_nibble(self.v_registers[x]);
+                if self.is_key_down(key) {
+                    self.skip_next_instruction();
                 }
             }
             (0xE, _, 0xA, 1) => {
                 let x = d2 as usize;
-                let vx = self.v_registers[x];
-                let key = self.keys[vx as usize];
-                if !key {
-                    self.program_counter += 2;
+                let key = Key::from_nibble(self.v_registers[x]);
+                if !self.is_key_down(key) {
+                    self.skip_next_instruction();
+                }
+            }
+            (0xF, 0, 0, 0) if self.variant.has_xochip_opcodes() => {
+                // i := long NNNN, the address is in the following word
+                let addr = self.fetch()?;
+                self.set_i_address(addr as usize);
+            }
+            (0xF, _, 0, 1) if self.variant.has_xochip_opcodes() => {
+                self.plane_mask = d2 as u8 & 0b11;
+            }
+            (0xF, 0, 0, 2) if self.variant.has_xochip_opcodes() => {
+                let i = self.i_address();
+                self.check_range(i, AUDIO_PATTERN_SIZE)?;
+                for idx in 0..AUDIO_PATTERN_SIZE {
+                    self.audio_pattern[idx] = self.bus_read(i + idx);
                 }
             }
             (0xF, _, 0, 7) => {
@@ -307,17 +840,19 @@ impl Hachi {
             }
             (0xF, _, 0, 0xA) => {
                 let x = d2 as usize;
-                let mut pressed = false;
-                for i in 00..self.keys.len() {
-                    if self.keys[i] {
-                        self.v_registers[x] = i as u8;
-                        pressed = true;
-                        break;
+                match self.wait_for_key() {
+                    Some(key) => {
+                        self.v_registers[x] = key.into();
+                        self.waiting_for_key = false;
+                    }
+                    None => {
+                        self.program_counter = self.instruction_pc;
+                        if !self.waiting_for_key {
+                            self.waiting_for_key = true;
+                            let pc = self.instruction_pc;
+                            self.notify(|observer| observer.on_key_wait(pc));
+                        }
                     }
-                }
-
-                if !pressed {
-                    self.program_counter -= 2;
                 }
             }
             (0xF, _, 1, 5) => {
@@ -327,64 +862,239 @@ impl Hachi {
             (0xF, _, 1, 8) => {
                 let x = d2 as usize;
                 self.sound_timer = self.v_registers[x];
+                self.update_sound();
+                self.publish_timers();
             }
             (0xF, _, 1, 0xE) => {
                 let x = d2 as usize;
                 let vx = self.v_registers[x] as u16;
-                self.i_register = self.i_register.wrapping_add(vx);
+                if self.quirks.i_overflow_sets_vf {
+                    let sum = self.i_register.wrapping_add(vx);
+                    self.v_registers[0xF] = if sum > 0x0FFF { 1 } else { 0 };
+                    self.set_i_address((sum & 0x0FFF) as usize);
+                } else {
+                    self.set_i_address(self.i_address() + vx as usize);
+                }
             }
             (0xF, _, 2, 9) => {
                 let x = d2 as usize;
                 let c = self.v_registers[x] as u16;
-                self.i_register = c * 5;
+                self.set_i_address(c as usize * 5);
+            }
+            (0xF, _, 3, 0) if self.variant.has_schip_opcodes() => {
+                let x = d2 as usize;
+                let c = (self.v_registers[x] & 0xF) as usize;
+                self.set_i_address(BIG_FONT_ADDRESS + c * 10);
+            }
+            (0xF, _, 3, 0xA) if self.variant.has_xochip_opcodes() => {
+                self.pitch = self.v_registers[d2 as usize];
             }
             (0xF, _, 3, 3) => {
                 // binary coded decimal
                 let x = d2 as usize;
-                let vx = self.v_registers[x] as f32;
+                let vx = self.v_registers[x];
 
-                let hundreds = (vx / 100.0).floor() as u8;
-                let tens = ((vx / 10.0) % 10.0).floor() as u8;
-                let ones = (vx % 10.0) as u8;
+                let hundreds = vx / 100;
+                let tens = vx / 10 % 10;
+                let ones = vx % 10;
 
-                self.ram[self.i_register as usize] = hundreds;
-                self.ram[(self.i_register + 1) as usize] = tens;
-                self.ram[(self.i_register + 2) as usize] = ones;
+                self.check_range(self.i_address(), 3)?;
+
+                let i = self.i_address();
+                self.bus_write(i, hundreds);
+                self.bus_write(i + 1, tens);
+                self.bus_write(i + 2, ones);
+                self.note_ram_write(i, 3);
             }
             (0xF, _, 5, 5) => {
                 let x = d2 as usize;
-                let i = self.i_register as usize;
+                let i = self.i_address();
+                self.check_range(i, x + 1)?;
                 for idx in 0..=x {
-                    self.ram[i + idx] = self.v_registers[idx];
+                    self.bus_write(i + idx, self.v_registers[idx]);
+                }
+                self.note_ram_write(i, x + 1);
+                if self.quirks.load_store_increments_i {
+                    self.set_i_address(i + x + 1);
                 }
             }
             (0xF, _, 6, 5) => {
                 let x = d2 as usize;
-                let i = self.i_register as usize;
+                let i = self.i_address();
+                self.check_range(i, x + 1)?;
                 for idx in 0..=x {
-                    self.v_registers[idx] = self.ram[i + idx];
+                    self.v_registers[idx] = self.bus_read(i + idx);
+                }
+                if self.quirks.load_store_increments_i {
+                    self.set_i_address(i + x + 1);
+                }
+            }
+            (0xF, _, 7, 5) if self.variant.has_schip_opcodes() => {
+                let x = (d2 as usize).min(self.rpl_flag_count() - 1);
+                self.rpl_flags[..=x].copy_from_slice(&self.v_registers[..=x]);
+                self.save_rpl_flags();
+            }
+            (0xF, _, 8, 5) if self.variant.has_schip_opcodes() => {
+                let x = (d2 as usize).min(self.rpl_flag_count() - 1);
+                self.v_registers[..=x].copy_from_slice(&self.rpl_flags[..=x]);
+            }
+            (_, _, _, _) => {
+                return Err(HachiError::UnknownOpcode {
+                    opcode: op,
+                    pc: self.instruction_pc,
+                })
+            }
+        }
+
+        Ok(())
+    }
+
+    // XORs a sprite of `(addr, width, rows)` onto one plane, returning
+    // whether any lit pixel was turned off.
+    fn draw_sprite(
+        &mut self,
+        plane: usize,
+        x_coord: usize,
+        y_coord: usize,
+        (addr, sprite_width, num_rows): (usize, usize, usize),
+    ) -> bool {
+        let width = self.display_width;
+        let height = self.display_height;
+        let row_bytes = sprite_width / 8;
+        let mut flipped = false;
+        let (display, hot) = if plane == 0 {
+            (Arc::make_mut(&mut self.display), self.hot_state)
+        } else {
+            (&mut self.second_plane, None)
+        };
+
+        for y_line in 0..num_rows {
+            let row = addr + y_line * row_bytes;
+            let pixels = if row_bytes == 2 {
+                u16::from_be_bytes([self.ram[row], self.ram[row + 1]])
+            } else {
+                (self.ram[row] as u16) << 8
+            };
+
+            for x_line in 0..sprite_width {
+                if (pixels & (0x8000 >> x_line)) != 0 {
+                    let (x, y) = (x_coord % width + x_line, y_coord % height + y_line);
+                    if !self.quirks.sprite_wrap && (x >= width || y >= height) {
+                        continue;
+                    }
+                    let (x, y) = (x % width, y % height);
+                    let idx = x + width * y;
+                    flipped |= display[idx];
+                    display[idx] ^= true;
+                    self.display_dirty = true;
+                    self.dirty_rows[y] = true;
+                    if let Some(hot) = hot {
+                        hot.display[idx].store(display[idx] as u8, Ordering::Relaxed);
+                    }
                 }
             }
-            (_, _, _, _) => unimplemented!("implement opcode {}", op),
         }
+
+        flipped
     }
 
-    fn fetch(&mut self) -> u16 {
-        let h_byte = self.ram[self.program_counter as usize] as u16;
-        let l_byte = self.ram[(self.program_counter + 1) as usize] as u16;
-        let op = (h_byte << 8) | l_byte;
-        self.program_counter += 2;
-        op
+    fn load_fonts(&mut self) {
+        self.ram[..FONTSET_SIZE].copy_from_slice(&self.font);
+        self.ram[BIG_FONT_ADDRESS..BIG_FONT_ADDRESS + BIG_FONTSET.len()]
+            .copy_from_slice(&self.big_font);
     }
 
-    fn push(&mut self, val: u16) {
-        self.stack[self.stack_pointer as usize] = val;
+    // The address in I. MEGA-CHIP's 01NN NNNN can point it past 64 KB.
+    fn i_address(&self) -> usize {
+        #[cfg(feature = "megachip")]
+        let high = self.mega.i_high as usize;
+        #[cfg(not(feature = "megachip"))]
+        let high = 0;
+        (high << 16) | self.i_register as usize
+    }
+
+    fn set_i_address(&mut self, addr: usize) {
+        self.i_register = addr as u16;
+        #[cfg(feature = "megachip")]
+        {
+            self.mega.i_high = (addr >> 16) as u8;
+        }
+    }
+
+    fn check_range(&self, addr: usize, len: usize) -> Result<(), HachiError> {
+        if addr + len > self.ram.len() {
+            return Err(HachiError::MemoryFault {
+                addr: addr + len - 1,
+                pc: self.instruction_pc,
+            });
+        }
+        Ok(())
+    }
+
+    fn peek_opcode(&self) -> Option<u16> {
+        let pc = self.program_counter as usize;
+        let h_byte = *self.ram.get(pc)? as u16;
+        let l_byte = *self.ram.get(pc + 1)? as u16;
+        Some((h_byte << 8) | l_byte)
+    }
+
+    fn fetch(&mut self) -> Result<u16, HachiError> {
+        // For the second word of a long instruction the fault belongs to
+        // the instruction, not to the word's own address
+        let op = self.peek_opcode().ok_or(HachiError::MemoryFault {
+            addr: self.program_counter as usize + 1,
+            pc: self.instruction_pc,
+        })?;
+        self.program_counter = self.program_counter.wrapping_add(2);
+        Ok(op)
+    }
+
+    // Steps over the next instruction for a taken skip. XO-CHIP's F000 NNNN
+    // and MEGA-CHIP's 01NN NNNN are four bytes long, so skipping them has to
+    // step over the address too.
+    fn skip_next_instruction(&mut self) {
+        let len = self.next_instruction_len();
+        self.program_counter = self.program_counter.wrapping_add(len);
+    }
+
+    // Length in bytes of the instruction at the PC.
+    pub(crate) fn next_instruction_len(&self) -> u16 {
+        let next = self.peek_opcode();
+        let long = matches!(
+            next.map(|op| Instruction::decode_for(op, self.variant)),
+            Some(Instruction::SetILong | Instruction::LoadIHigh { .. })
+        );
+        if long {
+            4
+        } else {
+            2
+        }
+    }
+
+    fn push(&mut self, val: u16) -> Result<(), HachiError> {
+        let sp = self.stack_pointer as usize;
+        if sp >= self.stack.len() {
+            return Err(HachiError::StackOverflow {
+                pc: self.instruction_pc,
+                depth: self.stack.len(),
+            });
+        }
+
+        self.stack[sp] = val;
         self.stack_pointer += 1;
+        self.stack_high_water = self.stack_high_water.max(self.stack_pointer);
+        Ok(())
     }
 
-    fn pop(&mut self) -> u16 {
+    fn pop(&mut self) -> Result<u16, HachiError> {
+        if self.stack_pointer == 0 {
+            return Err(HachiError::StackUnderflow {
+                pc: self.instruction_pc,
+            });
+        }
+
         self.stack_pointer -= 1;
-        self.stack[self.stack_pointer as usize]
+        Ok(self.stack[self.stack_pointer as usize])
     }
 }
 
@@ -393,3 +1103,82 @@ impl Default for Hachi {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An XO-CHIP machine with `code` placed at the very top of its 64 KB.
+    fn machine_at_top_of_ram(code: &[u8]) -> Hachi {
+        let mut hachi = Hachi::new();
+        hachi.set_variant(Variant::XoChip);
+        let addr = XO_RAM_SIZE - code.len();
+        hachi.load_at(addr, code).unwrap();
+        assert!(hachi.set_pc(addr as u16));
+        hachi
+    }
+
+    #[test]
+    fn waits_for_a_key_at_the_top_of_ram() {
+        let mut hachi = machine_at_top_of_ram(&[0xF0, 0x0A]);
+        hachi.tick().unwrap();
+        hachi.tick().unwrap();
+        assert_eq!(hachi.pc(), 0xFFFE);
+    }
+
+    #[test]
+    fn waits_for_the_display_at_the_top_of_ram() {
+        let mut hachi = machine_at_top_of_ram(&[0xD0, 0x01]);
+        let mut quirks = hachi.quirks();
+        quirks.display_wait = true;
+        hachi.set_quirks(quirks);
+        hachi.tick().unwrap();
+        assert_eq!(hachi.pc(), 0xFFFE);
+    }
+
+    #[test]
+    fn skips_the_whole_of_a_long_i_load() {
+        // Each skip is taken with V0 = 0, V1 = 1 and key 0 held
+        let skips: [[u8; 2]; 6] = [
+            [0x30, 0x00],
+            [0x40, 0x01],
+            [0x50, 0x00],
+            [0x90, 0x10],
+            [0xE0, 0x9E],
+            [0xE1, 0xA1],
+        ];
+
+        for skip in skips {
+            let mut hachi = Hachi::new();
+            hachi.set_variant(Variant::XoChip);
+            hachi
+                .load(&[skip[0], skip[1], 0xF0, 0x00, 0x12, 0x34])
+                .unwrap();
+            hachi.set_v(1, 1);
+            hachi.keypress(Key::Key0, true);
+            hachi.tick().unwrap();
+            assert_eq!(hachi.pc(), 0x206, "{skip:02X?}");
+        }
+    }
+
+    #[cfg(feature = "megachip")]
+    #[test]
+    fn skips_the_whole_of_a_mega_i_load() {
+        let mut hachi = Hachi::new();
+        hachi.set_variant(Variant::MegaChip);
+        hachi.load(&[0x30, 0x00, 0x01, 0x12, 0x34, 0x56]).unwrap();
+        hachi.tick().unwrap();
+        assert_eq!(hachi.pc(), 0x206);
+    }
+
+    #[test]
+    fn wraps_a_skip_past_the_top_of_ram() {
+        let mut hachi = machine_at_top_of_ram(&[0x30, 0x00, 0x00, 0xE0]);
+        hachi.tick().unwrap();
+        assert_eq!(hachi.pc(), 0x0000);
+
+        let mut hachi = machine_at_top_of_ram(&[0x30, 0x00, 0xF0, 0x00]);
+        hachi.tick().unwrap();
+        assert_eq!(hachi.pc(), 0x0002);
+    }
+}
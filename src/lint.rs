@@ -0,0 +1,215 @@
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+use crate::{ErrorPolicy, Hachi, START_ADDRESS};
+
+// How many instructions after FX55/FX65 to look for a use of I.
+const INCREMENT_LOOKAHEAD: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LintKind {
+    // 8XY6/8XYE with X != Y: some interpreters shift VY into VX, others
+    // shift VX in place.
+    ShiftSource,
+    // I is used soon after FX55/FX65 without being reloaded: the original
+    // interpreter advanced I past the registers, most modern ones don't.
+    LoadStoreIncrement,
+    // 8XY1/8XY2/8XY3 followed by a read of VF: the original interpreter
+    // reset VF after logic operations.
+    LogicResetsVf,
+    // BNNN: CHIP-48 and SUPER-CHIP read it as BXNN, jumping to XNN + VX.
+    JumpWithOffset,
+    // Arithmetic with VF as an operand: whether the result or the flag
+    // ends up in VF differs between interpreters.
+    VfOperand,
+    // A sprite was drawn across the edge of the screen: some interpreters
+    // clip it, others wrap it around.
+    EdgeDraw,
+}
+
+impl LintKind {
+    pub fn explanation(self) -> &'static str {
+        match self {
+            LintKind::ShiftSource => {
+                "shift with X != Y; result depends on whether VX or VY is shifted"
+            }
+            LintKind::LoadStoreIncrement => {
+                "I used after FX55/FX65 without reloading; some interpreters advance I"
+            }
+            LintKind::LogicResetsVf => {
+                "VF read after a logic op; the original interpreter reset VF to 0"
+            }
+            LintKind::JumpWithOffset => "BNNN is decoded as BXNN (VX + XNN) by SUPER-CHIP",
+            LintKind::VfOperand => {
+                "arithmetic on VF; interpreters disagree on whether the flag or result wins"
+            }
+            LintKind::EdgeDraw => {
+                "sprite crosses the screen edge; clipped on some, wrapped on others"
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintFinding {
+    pub addr: u16,
+    pub opcode: u16,
+    pub kind: LintKind,
+    // Whether the instruction was actually executed during a dynamic pass.
+    // Static findings may come from data misread as code.
+    pub executed: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintReport {
+    pub findings: Vec<LintFinding>,
+}
+
+impl LintReport {
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    pub fn of_kind(&self, kind: LintKind) -> impl Iterator<Item = &LintFinding> + '_ {
+        self.findings
+            .iter()
+            .filter(move |finding| finding.kind == kind)
+    }
+
+    fn add(&mut self, addr: u16, opcode: u16, kind: LintKind, executed: bool) {
+        let existing = self
+            .findings
+            .iter_mut()
+            .find(|finding| finding.addr == addr && finding.kind == kind);
+        match existing {
+            Some(finding) => finding.executed |= executed,
+            None => self.findings.push(LintFinding {
+                addr,
+                opcode,
+                kind,
+                executed,
+            }),
+        }
+    }
+}
+
+// Scans every instruction-aligned word of `rom` for constructs that behave
+// differently across interpreters. Data interleaved with code can produce
+// false positives; `lint_rom_dynamic` marks which findings really execute.
+pub fn lint_rom(rom: &[u8]) -> LintReport {
+    let ops: Vec<u16> = rom
+        .chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect();
+    let mut report = LintReport::default();
+
+    for (idx, &op) in ops.iter().enumerate() {
+        let addr = START_ADDRESS + (idx * 2) as u16;
+        let x = (op >> 8) & 0xF;
+        let y = (op >> 4) & 0xF;
+
+        match (op >> 12, op & 0xF) {
+            (8, 6) | (8, 0xE) if x != y => report.add(addr, op, LintKind::ShiftSource, false),
+            (8, 1..=3) if ops.get(idx + 1).is_some_and(|&next| reads_vf(next)) => {
+                report.add(addr, op, LintKind::LogicResetsVf, false)
+            }
+            (8, 4..=7) | (8, 0xE) if x == 0xF || y == 0xF => {
+                report.add(addr, op, LintKind::VfOperand, false)
+            }
+            (0xB, _) => report.add(addr, op, LintKind::JumpWithOffset, false),
+            (0xF, _) if matches!(op & 0xFF, 0x55 | 0x65) => {
+                let reuses_i = ops[idx + 1..]
+                    .iter()
+                    .take(INCREMENT_LOOKAHEAD)
+                    .take_while(|&&next| !sets_i_or_branches(next))
+                    .any(|&next| uses_i(next));
+                if reuses_i {
+                    report.add(addr, op, LintKind::LoadStoreIncrement, false);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    report
+}
+
+// Runs `rom` for `frames` frames on a scratch machine, adding findings only
+// observable at runtime (such as sprites drawn across the screen edge) and
+// marking static findings whose instructions were reached.
+pub fn lint_rom_dynamic(rom: &[u8], frames: u32) -> LintReport {
+    let mut report = lint_rom(rom);
+    let mut executed = BTreeSet::new();
+
+    let mut probe = Hachi::new();
+    probe.set_error_policy(ErrorPolicy::Skip);
+    if probe.load(rom).is_err() {
+        return report;
+    }
+
+    for _ in 0..frames {
+        for _ in 0..probe.instructions_per_frame() {
+            let pc = probe.program_counter;
+            let op = match probe.peek_opcode() {
+                Some(op) => op,
+                None => break,
+            };
+            executed.insert(pc);
+
+            if op & 0xF000 == 0xD000 && draws_across_edge(&probe, op) {
+                report.add(pc, op, LintKind::EdgeDraw, true);
+            }
+
+            if probe.tick().is_err() || probe.is_halted() {
+                break;
+            }
+        }
+        probe.tick_timers();
+    }
+
+    for finding in report.findings.iter_mut() {
+        finding.executed |= executed.contains(&finding.addr);
+    }
+    report
+        .findings
+        .sort_by_key(|finding| (finding.addr, finding.kind));
+    report
+}
+
+fn draws_across_edge(hachi: &Hachi, op: u16) -> bool {
+    let (width, height) = (hachi.display_width(), hachi.display_height());
+    let x = hachi.v_registers[((op >> 8) & 0xF) as usize] as usize % width;
+    let y = hachi.v_registers[((op >> 4) & 0xF) as usize] as usize % height;
+    let rows = (op & 0xF) as usize;
+    let i = hachi.i_register as usize;
+
+    (0..rows).any(|row| {
+        let bits = hachi.ram.get(i + row).copied().unwrap_or(0);
+        let crosses_right = x + 8 > width && bits << (width - x) != 0;
+        let crosses_bottom = y + row >= height && bits != 0;
+        crosses_right || crosses_bottom
+    })
+}
+
+fn reads_vf(op: u16) -> bool {
+    let x = (op >> 8) & 0xF;
+    let y = (op >> 4) & 0xF;
+    match op >> 12 {
+        3 | 4 | 7 => x == 0xF,
+        5 | 9 => x == 0xF || y == 0xF,
+        8 => y == 0xF || (x == 0xF && op & 0xF != 0),
+        0xE => x == 0xF,
+        0xF => x == 0xF && !matches!(op & 0xFF, 0x07 | 0x0A | 0x65),
+        _ => false,
+    }
+}
+
+fn uses_i(op: u16) -> bool {
+    op >> 12 == 0xD || (op >> 12 == 0xF && matches!(op & 0xFF, 0x1E | 0x33 | 0x55 | 0x65))
+}
+
+fn sets_i_or_branches(op: u16) -> bool {
+    matches!(op >> 12, 0x1 | 0x2 | 0xA | 0xB)
+        || op == 0x00EE
+        || (op >> 12 == 0xF && op & 0xFF == 0x29)
+}
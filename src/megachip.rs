@@ -0,0 +1,301 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::shared::Arc;
+use crate::{Hachi, HachiError, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+
+pub const MEGA_DISPLAY_WIDTH: usize = 256;
+pub const MEGA_DISPLAY_HEIGHT: usize = 192;
+pub const MEGA_RAM_SIZE: usize = 0x100_0000;
+
+// DXYN with I below this draws the old one-bit font sprites in white.
+const FONT_AREA_END: usize = 0x100;
+
+// 060N's sample header: a big-endian 16-bit rate, a 24-bit length and a
+// reserved byte.
+const SOUND_HEADER_LEN: usize = 6;
+
+// How 080N combines a sprite pixel with the one beneath it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Alpha25,
+    Alpha50,
+    Add,
+    Multiply,
+}
+
+impl BlendMode {
+    pub(crate) fn from_nibble(n: u16) -> Self {
+        match n {
+            1 => BlendMode::Alpha25,
+            2 => BlendMode::Alpha50,
+            3 => BlendMode::Add,
+            4 => BlendMode::Multiply,
+            _ => BlendMode::Normal,
+        }
+    }
+
+    pub(crate) fn nibble(self) -> u8 {
+        match self {
+            BlendMode::Normal => 0,
+            BlendMode::Alpha25 => 1,
+            BlendMode::Alpha50 => 2,
+            BlendMode::Add => 3,
+            BlendMode::Multiply => 4,
+        }
+    }
+
+    // Both colors are ARGB; the result is opaque.
+    fn blend(self, src: u32, dst: u32) -> u32 {
+        let [_, sr, sg, sb] = src.to_be_bytes();
+        let [_, dr, dg, db] = dst.to_be_bytes();
+        let mix = |s: u8, d: u8| -> u8 {
+            let (s, d) = (s as u32, d as u32);
+            (match self {
+                BlendMode::Normal => s,
+                BlendMode::Alpha25 => (s + 3 * d) / 4,
+                BlendMode::Alpha50 => (s + d) / 2,
+                BlendMode::Add => (s + d).min(0xFF),
+                BlendMode::Multiply => s * d / 0xFF,
+            }) as u8
+        };
+        u32::from_be_bytes([0xFF, mix(sr, dr), mix(sg, dg), mix(sb, db)])
+    }
+}
+
+// A digitised sample started by 060N: 8-bit unsigned mono PCM in RAM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MegaSound {
+    pub address: usize,
+    pub len: usize,
+    pub sample_rate: u16,
+    pub looping: bool,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct MegaChip {
+    pub(crate) enabled: bool,
+    // Bits 16 to 23 of I, set by 01NN NNNN.
+    pub(crate) i_high: u8,
+    // ARGB; index 0 is transparent in sprites.
+    pub(crate) palette: [u32; 256],
+    pub(crate) sprite_width: usize,
+    pub(crate) sprite_height: usize,
+    pub(crate) blend: BlendMode,
+    pub(crate) collision_index: u8,
+    pub(crate) alpha: u8,
+    // The frame being drawn, as palette indices (for collisions) and
+    // colors. 00E0 presents it.
+    pub(crate) indices: Vec<u8>,
+    pub(crate) back: Vec<u32>,
+    pub(crate) front: Vec<u32>,
+    pub(crate) sound: Option<MegaSound>,
+}
+
+impl Default for MegaChip {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            i_high: 0,
+            palette: [0; 256],
+            sprite_width: 8,
+            sprite_height: 8,
+            blend: BlendMode::Normal,
+            collision_index: 0,
+            alpha: 0xFF,
+            indices: Vec::new(),
+            back: Vec::new(),
+            front: Vec::new(),
+            sound: None,
+        }
+    }
+}
+
+impl Hachi {
+    // Whether 0011 has switched the machine into 256x192 color mode.
+    pub fn is_megachip_mode(&self) -> bool {
+        self.mega.enabled
+    }
+
+    // The last frame 00E0 presented, 256x192 ARGB pixels, or nothing
+    // outside MEGA-CHIP mode. `get_display` shows which pixels aren't black.
+    pub fn megachip_frame(&self) -> &[u32] {
+        &self.mega.front
+    }
+
+    // The fade level set by 05NN, for the frontend to apply.
+    pub fn megachip_alpha(&self) -> u8 {
+        self.mega.alpha
+    }
+
+    pub fn megachip_sound(&self) -> Option<MegaSound> {
+        self.mega.sound
+    }
+
+    // Runs `op` if it's one MEGA-CHIP adds or changes, returning false to
+    // leave it to the SUPER-CHIP decoder.
+    pub(crate) fn execute_megachip(&mut self, op: u16) -> Result<bool, HachiError> {
+        let nn = op & 0xFF;
+        let enabled = self.mega.enabled;
+
+        match op & 0xFF00 {
+            0x0000 => match op {
+                0x0010 => self.set_megachip_mode(false),
+                0x0011 => self.set_megachip_mode(true),
+                0x00E0 if enabled => self.present_megachip_frame(),
+                0x00FB if enabled => self.scroll_megachip(4, 0),
+                0x00FC if enabled => self.scroll_megachip(-4, 0),
+                _ if enabled && op & 0xFFF0 == 0x00B0 => {
+                    self.scroll_megachip(0, -((op & 0xF) as isize))
+                }
+                _ if enabled && op & 0xFFF0 == 0x00C0 => {
+                    self.scroll_megachip(0, (op & 0xF) as isize)
+                }
+                _ => return Ok(false),
+            },
+            0x0100 => {
+                // I := NN NNNN, the low 16 bits are in the following word
+                let low = self.fetch()?;
+                self.set_i_address((nn as usize) << 16 | low as usize);
+            }
+            0x0200 => {
+                let i = self.i_address();
+                let count = nn as usize;
+                self.check_range(i, count * 4)?;
+                for idx in 0..count {
+                    let bytes = &self.ram[i + idx * 4..i + idx * 4 + 4];
+                    self.mega.palette[idx + 1] =
+                        u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                }
+            }
+            0x0300 => self.mega.sprite_width = if nn == 0 { 256 } else { nn as usize },
+            0x0400 => self.mega.sprite_height = if nn == 0 { 256 } else { nn as usize },
+            0x0500 => self.mega.alpha = nn as u8,
+            0x0600 => {
+                let i = self.i_address();
+                self.check_range(i, SOUND_HEADER_LEN)?;
+                let header = &self.ram[i..i + SOUND_HEADER_LEN];
+                let sample_rate = u16::from_be_bytes([header[0], header[1]]);
+                let len = u32::from_be_bytes([0, header[2], header[3], header[4]]) as usize;
+                let address = i + SOUND_HEADER_LEN;
+                self.mega.sound = Some(MegaSound {
+                    address,
+                    len: len.min(self.ram.len() - address),
+                    sample_rate,
+                    looping: op & 0xF == 0,
+                });
+            }
+            0x0700 => self.mega.sound = None,
+            0x0800 => self.mega.blend = BlendMode::from_nibble(op & 0xF),
+            0x0900 => self.mega.collision_index = nn as u8,
+            _ if enabled && op & 0xF000 == 0xD000 => {
+                let x = self.v_registers[((op & 0x0F00) >> 8) as usize] as usize;
+                let y = self.v_registers[((op & 0x00F0) >> 4) as usize] as usize;
+                let hit = self.draw_megachip_sprite(x, y, (op & 0xF) as usize)?;
+                self.v_registers[0xF] = hit as u8;
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    pub(crate) fn set_megachip_mode(&mut self, enabled: bool) {
+        let len = MEGA_DISPLAY_WIDTH * MEGA_DISPLAY_HEIGHT;
+        self.mega.enabled = enabled;
+        if enabled {
+            self.mega.indices = vec![0; len];
+            self.mega.back = vec![0; len];
+            self.mega.front = vec![0; len];
+            self.resize_display(MEGA_DISPLAY_WIDTH, MEGA_DISPLAY_HEIGHT);
+        } else {
+            self.mega.indices = Vec::new();
+            self.mega.back = Vec::new();
+            self.mega.front = Vec::new();
+            self.resize_display(DISPLAY_WIDTH, DISPLAY_HEIGHT);
+        }
+    }
+
+    // MEGA-CHIP's 00E0: shows the frame drawn so far and starts a new one.
+    fn present_megachip_frame(&mut self) {
+        let mega = &mut self.mega;
+        mega.front.copy_from_slice(&mega.back);
+        mega.back.fill(0);
+        mega.indices.fill(0);
+
+        let display = Arc::make_mut(&mut self.display);
+        for (pixel, &color) in display.iter_mut().zip(&mega.front) {
+            *pixel = color & 0x00FF_FFFF != 0;
+        }
+        self.mark_all_rows_dirty();
+        self.publish_display();
+    }
+
+    fn draw_megachip_sprite(&mut self, x: usize, y: usize, n: usize) -> Result<bool, HachiError> {
+        let i = self.i_address();
+        let (width, height) = (self.mega.sprite_width, self.mega.sprite_height);
+        let font = i < FONT_AREA_END;
+        self.check_range(i, if font { n } else { width * height })?;
+        let ram = &self.ram;
+        let mega = &mut self.mega;
+
+        if font {
+            for (row, &bits) in ram[i..i + n].iter().enumerate() {
+                for col in 0..8 {
+                    let (px, py) = (x + col, y + row);
+                    if bits & (0x80 >> col) != 0
+                        && px < MEGA_DISPLAY_WIDTH
+                        && py < MEGA_DISPLAY_HEIGHT
+                    {
+                        mega.back[px + py * MEGA_DISPLAY_WIDTH] = 0xFFFF_FFFF;
+                    }
+                }
+            }
+            return Ok(false);
+        }
+
+        let mut hit = false;
+        for row in 0..height {
+            let py = y + row;
+            if py >= MEGA_DISPLAY_HEIGHT {
+                break;
+            }
+            for col in 0..width {
+                let px = x + col;
+                let index = ram[i + row * width + col];
+                if px >= MEGA_DISPLAY_WIDTH || index == 0 {
+                    continue;
+                }
+                let p = px + py * MEGA_DISPLAY_WIDTH;
+                hit |= mega.indices[p] == mega.collision_index;
+                mega.indices[p] = index;
+                mega.back[p] = mega.blend.blend(mega.palette[index as usize], mega.back[p]);
+            }
+        }
+        Ok(hit)
+    }
+
+    // Moves the frame being drawn by `dx` columns and `dy` rows, blanking
+    // what scrolls in.
+    fn scroll_megachip(&mut self, dx: isize, dy: isize) {
+        shift(&mut self.mega.indices, dx, dy);
+        shift(&mut self.mega.back, dx, dy);
+    }
+}
+
+fn shift<T: Copy + Default>(buf: &mut [T], dx: isize, dy: isize) {
+    let (width, height) = (MEGA_DISPLAY_WIDTH as isize, MEGA_DISPLAY_HEIGHT as isize);
+    let source = buf.to_vec();
+    for y in 0..height {
+        for x in 0..width {
+            let (sx, sy) = (x - dx, y - dy);
+            buf[(x + y * width) as usize] = if (0..width).contains(&sx) && (0..height).contains(&sy)
+            {
+                source[(sx + sy * width) as usize]
+            } else {
+                T::default()
+            };
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::schip::{BIG_FONTSET, BIG_FONT_ADDRESS};
+use crate::{Hachi, LoadError, Variant, XO_RAM_SIZE};
+
+// RAM has to hold at least the fonts.
+const MIN_RAM_SIZE: usize = BIG_FONT_ADDRESS + BIG_FONTSET.len();
+
+impl Hachi {
+    // Checks that `load` would accept a ROM of `len` bytes after switching
+    // to `variant`, so loaders can fail before changing any settings.
+    pub(crate) fn check_rom_fits(&self, variant: Variant, len: usize) -> Result<(), LoadError> {
+        let max = self
+            .ram_size_for(variant)
+            .saturating_sub(variant.start_address() as usize);
+        if len == 0 {
+            Err(LoadError::Empty)
+        } else if len > max {
+            Err(LoadError::RomTooLarge { size: len, max })
+        } else {
+            Ok(())
+        }
+    }
+
+    // Where `load` places ROMs and execution starts after a reset.
+    pub fn start_address(&self) -> u16 {
+        self.start_address
+    }
+
+    // Moves the load and start address, e.g. to 0x600 for ETI-660 ROMs.
+    // Takes effect at the next `load` or reset.
+    // Returns false, changing nothing, if `addr` is outside RAM.
+    pub fn set_start_address(&mut self, addr: u16) -> bool {
+        if addr as usize >= self.ram.len() {
+            return false;
+        }
+
+        self.start_address = addr;
+        true
+    }
+
+    // Fixes RAM at `len` bytes whatever the variant, e.g. `VIP_RAM_SIZE`
+    // for programs written for a stock VIP, or less to save memory on a
+    // small target. `None` goes back to the variant's own size. Contents
+    // that still fit are kept. Returns false, changing nothing, if `len`
+    // can't hold the fonts and the start address or is over 64 KB.
+    pub fn set_ram_size(&mut self, len: Option<usize>) -> bool {
+        if let Some(len) = len {
+            if !self.fits_ram_size(len) || len > XO_RAM_SIZE {
+                return false;
+            }
+        }
+
+        self.custom_ram_size = len;
+        self.resize_ram(self.ram_size_for_variant());
+        true
+    }
+
+    // Whether `len` bytes of RAM hold the fonts and the start address.
+    pub(crate) fn fits_ram_size(&self, len: usize) -> bool {
+        len >= MIN_RAM_SIZE && len > self.start_address as usize
+    }
+
+    // All of RAM, font and interpreter area included.
+    pub fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    // Reads RAM directly, bypassing any attached peripherals.
+    pub fn read_ram(&self, addr: usize) -> Option<u8> {
+        self.ram.get(addr).copied()
+    }
+
+    // Copies `data` into RAM at `addr`, e.g. a custom font or a second
+    // program. Unlike `load` it leaves the PC and the ROM that `soft_reset`
+    // reloads alone, and nothing is written unless all of `data` fits.
+    pub fn load_at(&mut self, addr: usize, data: &[u8]) -> Result<(), LoadError> {
+        if addr >= self.ram.len() {
+            return Err(LoadError::AddressOutOfRange { addr });
+        }
+        let max = self.ram.len() - addr;
+        if data.len() > max {
+            return Err(LoadError::RomTooLarge {
+                size: data.len(),
+                max,
+            });
+        }
+
+        self.ram[addr..addr + data.len()].copy_from_slice(data);
+        self.note_ram_write(addr, data.len());
+        Ok(())
+    }
+
+    // Pokes a byte from the host side, e.g. for a cheat or a debugger's
+    // memory editor. Returns false if `addr` is outside RAM. Peripherals
+    // aren't notified, but battery-backed RAM picks up the change.
+    pub fn write_ram(&mut self, addr: usize, byte: u8) -> bool {
+        match self.ram.get_mut(addr) {
+            Some(cell) => {
+                *cell = byte;
+                self.note_ram_write(addr, 1);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_a_ram_size_the_new_start_address_outgrows() {
+        let mut hachi = Hachi::new();
+        assert!(hachi.set_ram_size(Some(0x280)));
+        hachi.set_variant(Variant::Chip8X);
+
+        assert_eq!(hachi.ram().len(), hachi.ram_size_for(Variant::Chip8X));
+        assert!(hachi.ram().len() > 0x300);
+        assert_eq!(hachi.load(&[0x12, 0x00]), Ok(()));
+    }
+
+    #[test]
+    fn refuses_a_rom_when_ram_ends_before_the_start_address() {
+        let mut hachi = Hachi::new();
+        assert!(hachi.set_ram_size(Some(0x280)));
+        hachi.start_address = 0x280;
+
+        assert_eq!(
+            hachi.load(&[0x12, 0x00]),
+            Err(LoadError::RomTooLarge { size: 2, max: 0 })
+        );
+    }
+}
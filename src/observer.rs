@@ -0,0 +1,45 @@
+use alloc::boxed::Box;
+
+use crate::{Hachi, HachiError};
+
+// Callbacks for things a debugger, achievement tracker or logger wants to
+// react to as they happen. Every method has an empty default, so an
+// observer only implements the ones it cares about. `pc` is always the
+// address of the instruction responsible.
+pub trait HachiObserver {
+    // A sprite was drawn at (`x`, `y`), `rows` tall; `collision` is what
+    // VF was set to.
+    fn on_draw(&mut self, _pc: u16, _x: u8, _y: u8, _rows: u8, _collision: bool) {}
+
+    // The buzzer started or stopped.
+    fn on_sound_change(&mut self, _on: bool) {}
+
+    // FX0A started waiting for a key. Called once per wait, not on every
+    // tick spent waiting.
+    fn on_key_wait(&mut self, _pc: u16) {}
+
+    fn on_call(&mut self, _pc: u16, _target: u16) {}
+
+    // `target` is the address returned to.
+    fn on_return(&mut self, _pc: u16, _target: u16) {}
+
+    // An instruction faulted and was skipped under `ErrorPolicy::Log`.
+    fn on_error(&mut self, _err: &HachiError) {}
+}
+
+impl Hachi {
+    // Replaces any observer already attached.
+    pub fn set_observer(&mut self, observer: Box<dyn HachiObserver>) {
+        self.observer = Some(observer);
+    }
+
+    pub fn take_observer(&mut self) -> Option<Box<dyn HachiObserver>> {
+        self.observer.take()
+    }
+
+    pub(crate) fn notify(&mut self, event: impl FnOnce(&mut dyn HachiObserver)) {
+        if let Some(observer) = &mut self.observer {
+            event(observer.as_mut());
+        }
+    }
+}
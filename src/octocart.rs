@@ -0,0 +1,736 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::asm::{assemble, AsmError};
+use crate::{Hachi, LoadError, Quirks, Variant};
+
+// An Octo "Octocart": a GIF whose pixels carry the program's Octo source
+// and the options it was saved with. Each byte of the payload is spread
+// over the low two bits of four consecutive palette indices, across every
+// frame in turn; the payload is a big-endian length followed by that many
+// bytes of JSON, `{"program": "...", "options": {...}}`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Octocart {
+    pub program: String,
+    pub options: OctoOptions,
+}
+
+// The options Octo stores alongside the program. Everything is optional
+// since older carts predate most of them. Colors are 0xRRGGBB.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OctoOptions {
+    // Instructions per frame.
+    pub tickrate: Option<u32>,
+    // 3215 for CHIP-8, 3583 for SUPER-CHIP, 65024 for XO-CHIP.
+    pub max_size: Option<u32>,
+    pub shift_quirks: Option<bool>,
+    pub load_store_quirks: Option<bool>,
+    pub vf_order_quirks: Option<bool>,
+    pub clip_quirks: Option<bool>,
+    pub jump_quirks: Option<bool>,
+    pub logic_quirks: Option<bool>,
+    pub vblank_quirks: Option<bool>,
+    pub fill_color: Option<u32>,
+    pub fill_color2: Option<u32>,
+    pub blend_color: Option<u32>,
+    pub background_color: Option<u32>,
+    pub buzz_color: Option<u32>,
+    pub quiet_color: Option<u32>,
+    pub screen_rotation: Option<u16>,
+    pub font_style: Option<String>,
+}
+
+impl OctoOptions {
+    // The platform the cart was saved for, going by how much memory it
+    // allowed the program.
+    pub fn variant(&self) -> Variant {
+        match self.max_size {
+            Some(size) if size > 3583 => Variant::XoChip,
+            Some(size) if size > 3215 => Variant::SuperChip,
+            Some(_) => Variant::Chip8,
+            None => Variant::XoChip,
+        }
+    }
+
+    // `base` with the cart's quirk settings applied. Octo's quirks are
+    // named for the deviation from the VIP, so e.g. `shiftQuirks` means
+    // shifting VX in place. Settings this core has no quirk for are left
+    // out.
+    pub fn quirks(&self, base: Quirks) -> Quirks {
+        let mut quirks = base;
+        if let Some(shift) = self.shift_quirks {
+            quirks.shift_uses_vy = !shift;
+        }
+        if let Some(load_store) = self.load_store_quirks {
+            quirks.load_store_increments_i = !load_store;
+        }
+        if let Some(jump) = self.jump_quirks {
+            quirks.jump_uses_vx = jump;
+        }
+        if let Some(logic) = self.logic_quirks {
+            quirks.logic_resets_vf = logic;
+        }
+        if let Some(clip) = self.clip_quirks {
+            quirks.sprite_wrap = !clip;
+        }
+        if let Some(vblank) = self.vblank_quirks {
+            quirks.display_wait = vblank;
+        }
+        quirks
+    }
+
+    // Background, first plane, second plane and both, as RGBA. Colors the
+    // cart doesn't set keep the ones in `base`.
+    pub fn palette(&self, base: [[u8; 4]; 4]) -> [[u8; 4]; 4] {
+        let mut palette = base;
+        let colors = [
+            self.background_color,
+            self.fill_color,
+            self.fill_color2,
+            self.blend_color,
+        ];
+        for (entry, color) in palette.iter_mut().zip(colors) {
+            if let Some(rgb) = color {
+                let [_, r, g, b] = rgb.to_be_bytes();
+                *entry = [r, g, b, 0xFF];
+            }
+        }
+        palette
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OctocartError {
+    NotGif,
+    // The GIF ended in the middle of a block.
+    Truncated,
+    // The image data isn't valid LZW.
+    BadImageData,
+    // The pixels don't hold a payload of the length they claim.
+    BadPayload,
+    // The payload isn't the JSON object Octo writes.
+    BadJson,
+    Asm(AsmError),
+    Load(LoadError),
+}
+
+impl fmt::Display for OctocartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OctocartError::NotGif => write!(f, "not a GIF image"),
+            OctocartError::Truncated => write!(f, "GIF data ends unexpectedly"),
+            OctocartError::BadImageData => write!(f, "GIF image data is corrupt"),
+            OctocartError::BadPayload => write!(f, "image does not contain an Octo cartridge"),
+            OctocartError::BadJson => write!(f, "cartridge payload is not valid JSON"),
+            OctocartError::Asm(err) => write!(f, "cartridge program: {}", err),
+            OctocartError::Load(err) => write!(f, "cartridge program: {}", err),
+        }
+    }
+}
+
+impl core::error::Error for OctocartError {}
+
+// Extracts the program source and options from an Octocart GIF.
+pub fn parse_octocart(gif: &[u8]) -> Result<Octocart, OctocartError> {
+    let pixels = decode_gif(gif)?;
+    let payload: Vec<u8> = pixels
+        .chunks_exact(4)
+        .map(|quad| quad.iter().fold(0, |byte, &px| (byte << 2) | (px & 3)))
+        .collect();
+
+    let len = match payload.get(..4) {
+        Some(len) => u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize,
+        None => return Err(OctocartError::BadPayload),
+    };
+    let json = payload.get(4..4 + len).ok_or(OctocartError::BadPayload)?;
+    // Octo writes the JSON one UTF-16 unit per byte
+    let text: String = json.iter().map(|&byte| byte as char).collect();
+
+    let root = Json::parse(&text).ok_or(OctocartError::BadJson)?;
+    let program = match root.get("program") {
+        Some(Json::String(program)) => program.clone(),
+        _ => return Err(OctocartError::BadJson),
+    };
+    let options = root.get("options").map(read_options).unwrap_or_default();
+
+    Ok(Octocart { program, options })
+}
+
+fn read_options(json: &Json) -> OctoOptions {
+    let flag = |key| match json.get(key) {
+        Some(Json::Bool(value)) => Some(*value),
+        _ => None,
+    };
+    let number = |key| match json.get(key) {
+        Some(Json::Number(value)) if *value >= 0.0 => Some(*value as u32),
+        _ => None,
+    };
+    let color = |key| match json.get(key) {
+        Some(Json::String(value)) => parse_color(value),
+        _ => None,
+    };
+
+    OctoOptions {
+        tickrate: number("tickrate"),
+        max_size: number("maxSize"),
+        shift_quirks: flag("shiftQuirks"),
+        load_store_quirks: flag("loadStoreQuirks"),
+        vf_order_quirks: flag("vfOrderQuirks"),
+        clip_quirks: flag("clipQuirks"),
+        jump_quirks: flag("jumpQuirks"),
+        logic_quirks: flag("logicQuirks"),
+        vblank_quirks: flag("vBlankQuirks"),
+        fill_color: color("fillColor"),
+        fill_color2: color("fillColor2"),
+        blend_color: color("blendColor"),
+        background_color: color("backgroundColor"),
+        buzz_color: color("buzzColor"),
+        quiet_color: color("quietColor"),
+        screen_rotation: number("screenRotation").map(|deg| deg as u16),
+        font_style: match json.get("fontStyle") {
+            Some(Json::String(style)) => Some(style.clone()),
+            _ => None,
+        },
+    }
+}
+
+// "#RRGGBB" or "#RGB".
+fn parse_color(text: &str) -> Option<u32> {
+    let hex = text.strip_prefix('#')?;
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    match hex.len() {
+        6 => Some(value),
+        3 => {
+            let (r, g, b) = ((value >> 8) & 0xF, (value >> 4) & 0xF, value & 0xF);
+            Some(((r * 0x11) << 16) | ((g * 0x11) << 8) | (b * 0x11))
+        }
+        _ => None,
+    }
+}
+
+impl Hachi {
+    // Loads an Octocart: assembles its program and switches to the
+    // variant, quirks, speed and colors it was saved with. Nothing changes
+    // unless the program assembles and fits. Returns the cart so the
+    // frontend can pick up the other display options.
+    pub fn load_octocart(&mut self, gif: &[u8]) -> Result<Octocart, OctocartError> {
+        let cart = parse_octocart(gif)?;
+        let rom = assemble(&cart.program).map_err(OctocartError::Asm)?;
+        let variant = cart.options.variant();
+        self.check_rom_fits(variant, rom.len())
+            .map_err(OctocartError::Load)?;
+
+        self.set_variant(variant);
+        self.set_quirks(cart.options.quirks(Quirks::for_variant(variant)));
+        self.set_palette(cart.options.palette(self.palette()));
+        if let Some(tickrate) = cart.options.tickrate {
+            self.set_instructions_per_frame(tickrate);
+        }
+        self.reset();
+        self.load(&rom).map_err(OctocartError::Load)?;
+
+        Ok(cart)
+    }
+}
+
+// The palette indices of every frame, concatenated.
+fn decode_gif(data: &[u8]) -> Result<Vec<u8>, OctocartError> {
+    let mut reader = GifReader { data, pos: 0 };
+
+    let signature = reader.bytes(6).map_err(|_| OctocartError::NotGif)?;
+    if signature != b"GIF87a" && signature != b"GIF89a" {
+        return Err(OctocartError::NotGif);
+    }
+    reader.bytes(4)?; // screen size
+    let flags = reader.u8()?;
+    reader.bytes(2)?; // background color, aspect ratio
+    if flags & 0x80 != 0 {
+        reader.bytes(3 << ((flags & 7) + 1))?;
+    }
+
+    let mut pixels = Vec::new();
+    loop {
+        match reader.u8()? {
+            // extension
+            0x21 => {
+                reader.u8()?;
+                reader.sub_blocks()?;
+            }
+            // image
+            0x2C => {
+                reader.bytes(4)?; // position
+                let width = reader.u16()? as usize;
+                let height = reader.u16()? as usize;
+                let flags = reader.u8()?;
+                if flags & 0x80 != 0 {
+                    reader.bytes(3 << ((flags & 7) + 1))?;
+                }
+                let min_code_size = reader.u8()?;
+                let image = reader.sub_blocks()?;
+
+                let mut frame = decode_lzw(&image, min_code_size, width * height)?;
+                if flags & 0x40 != 0 {
+                    frame = deinterlace(&frame, width, height);
+                }
+                pixels.extend_from_slice(&frame);
+            }
+            // trailer
+            0x3B => break,
+            _ => return Err(OctocartError::BadImageData),
+        }
+    }
+    Ok(pixels)
+}
+
+struct GifReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> GifReader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], OctocartError> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or(OctocartError::Truncated)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, OctocartError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, OctocartError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    // A run of length-prefixed blocks ending with an empty one.
+    fn sub_blocks(&mut self) -> Result<Vec<u8>, OctocartError> {
+        let mut out = Vec::new();
+        loop {
+            let len = self.u8()? as usize;
+            if len == 0 {
+                return Ok(out);
+            }
+            out.extend_from_slice(self.bytes(len)?);
+        }
+    }
+}
+
+const MAX_LZW_CODES: usize = 4096;
+
+fn decode_lzw(data: &[u8], min_code_size: u8, len: usize) -> Result<Vec<u8>, OctocartError> {
+    if !(1..=11).contains(&min_code_size) {
+        return Err(OctocartError::BadImageData);
+    }
+    let clear = 1usize << min_code_size;
+    let end = clear + 1;
+
+    // Each code is the code of its prefix plus one final byte
+    let mut prefix = vec![0u16; MAX_LZW_CODES];
+    let mut suffix = vec![0u8; MAX_LZW_CODES];
+    for (code, byte) in suffix.iter_mut().enumerate().take(clear) {
+        *byte = code as u8;
+    }
+
+    let mut out = Vec::with_capacity(len);
+    let mut next = end + 1;
+    let mut code_size = min_code_size as u32 + 1;
+    let mut previous: Option<usize> = None;
+    let mut bits = 0u32;
+    let mut bit_count = 0;
+    let mut scratch = Vec::new();
+
+    for &byte in data {
+        bits |= (byte as u32) << bit_count;
+        bit_count += 8;
+
+        while bit_count >= code_size {
+            let code = (bits & ((1 << code_size) - 1)) as usize;
+            bits >>= code_size;
+            bit_count -= code_size;
+
+            if code == clear {
+                next = end + 1;
+                code_size = min_code_size as u32 + 1;
+                previous = None;
+                continue;
+            }
+            if code == end {
+                return Ok(out);
+            }
+
+            let prev = match previous {
+                Some(prev) => prev,
+                None => {
+                    if code >= clear {
+                        return Err(OctocartError::BadImageData);
+                    }
+                    out.push(code as u8);
+                    previous = Some(code);
+                    continue;
+                }
+            };
+            if code > next || (code == next && next >= MAX_LZW_CODES) {
+                return Err(OctocartError::BadImageData);
+            }
+
+            // A code not yet in the table is the previous string plus its
+            // own first byte
+            scratch.clear();
+            let mut walk = if code == next { prev } else { code };
+            while walk > end {
+                scratch.push(suffix[walk]);
+                walk = prefix[walk] as usize;
+            }
+            scratch.push(walk as u8);
+            let first = walk as u8;
+            out.extend(scratch.iter().rev());
+            if code == next {
+                out.push(first);
+            }
+
+            if next < MAX_LZW_CODES {
+                prefix[next] = prev as u16;
+                suffix[next] = first;
+                next += 1;
+                if next == 1 << code_size && code_size < 12 {
+                    code_size += 1;
+                }
+            }
+            previous = Some(code);
+        }
+    }
+    Ok(out)
+}
+
+// Interlaced images store rows 0, 8, 16... then 4, 12... then 2, 6...
+// then the odd rows.
+fn deinterlace(frame: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let mut out = vec![0; frame.len()];
+    let order = [(0, 8), (4, 8), (2, 4), (1, 2)]
+        .iter()
+        .flat_map(|&(start, step)| (start..height).step_by(step));
+
+    for (src, dst) in order.enumerate() {
+        let (src, dst) = (src * width, dst * width);
+        if let (Some(from), Some(to)) = (frame.get(src..src + width), out.get_mut(dst..dst + width))
+        {
+            to.copy_from_slice(from);
+        }
+    }
+    out
+}
+
+// Just enough JSON for the cartridge payload.
+#[derive(Debug, Clone, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn parse(text: &str) -> Option<Json> {
+        let mut parser = JsonParser {
+            chars: text.chars().collect(),
+            pos: 0,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        (parser.pos == parser.chars.len()).then_some(value)
+    }
+
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+// Cartridges nest two deep; this keeps a crafted payload from exhausting
+// the stack.
+const MAX_JSON_DEPTH: usize = 32;
+
+struct JsonParser {
+    chars: Vec<char>,
+    pos: usize,
+    depth: usize,
+}
+
+impl JsonParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Option<Json> {
+        for expected in word.chars() {
+            if self.next()? != expected {
+                return None;
+            }
+        }
+        Some(value)
+    }
+
+    fn value(&mut self) -> Option<Json> {
+        if self.depth == MAX_JSON_DEPTH {
+            return None;
+        }
+        self.depth += 1;
+        let value = self.nested_value();
+        self.depth -= 1;
+        value
+    }
+
+    fn nested_value(&mut self) -> Option<Json> {
+        self.skip_whitespace();
+        match self.peek()? {
+            'n' => self.literal("null", Json::Null),
+            't' => self.literal("true", Json::Bool(true)),
+            'f' => self.literal("false", Json::Bool(false)),
+            '"' => self.string().map(Json::String),
+            '[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                self.skip_whitespace();
+                if self.peek()? == ']' {
+                    self.pos += 1;
+                    return Some(Json::Array(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    self.skip_whitespace();
+                    match self.next()? {
+                        ',' => continue,
+                        ']' => return Some(Json::Array(items)),
+                        _ => return None,
+                    }
+                }
+            }
+            '{' => {
+                self.pos += 1;
+                let mut fields = Vec::new();
+                self.skip_whitespace();
+                if self.peek()? == '}' {
+                    self.pos += 1;
+                    return Some(Json::Object(fields));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    self.skip_whitespace();
+                    if self.next()? != ':' {
+                        return None;
+                    }
+                    fields.push((key, self.value()?));
+                    self.skip_whitespace();
+                    match self.next()? {
+                        ',' => continue,
+                        '}' => return Some(Json::Object(fields)),
+                        _ => return None,
+                    }
+                }
+            }
+            _ => self.number(),
+        }
+    }
+
+    fn number(&mut self) -> Option<Json> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.parse().ok().map(Json::Number)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        if self.next()? != '"' {
+            return None;
+        }
+        let mut out = String::new();
+        loop {
+            match self.next()? {
+                '"' => return Some(out),
+                '\\' => match self.next()? {
+                    'n' => out.push('\n'),
+                    't' => out.push('\t'),
+                    'r' => out.push('\r'),
+                    'b' => out.push('\u{8}'),
+                    'f' => out.push('\u{c}'),
+                    'u' => {
+                        let unit = self.hex4()?;
+                        let c = if (0xD800..0xDC00).contains(&unit) {
+                            // high surrogate; the low half follows as \uXXXX
+                            if self.next()? != '\\' || self.next()? != 'u' {
+                                return None;
+                            }
+                            let low = self.hex4()?;
+                            let code =
+                                0x10000 + ((unit - 0xD800) << 10) + (low.checked_sub(0xDC00)?);
+                            char::from_u32(code)?
+                        } else {
+                            char::from_u32(unit)?
+                        };
+                        out.push(c);
+                    }
+                    c => out.push(c),
+                },
+                c => out.push(c),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        (0..4).try_fold(0, |value, _| Some(value << 4 | self.next()?.to_digit(16)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    // A one-row GIF carrying `json` the way Octo does. The LZW stream
+    // clears after every two pixels, so each code stays three bits wide.
+    fn cartridge(json: &str) -> Vec<u8> {
+        let mut payload = (json.len() as u32).to_be_bytes().to_vec();
+        payload.extend(json.bytes());
+        let pixels: Vec<u8> = payload
+            .iter()
+            .flat_map(|&byte| [6, 4, 2, 0].map(|shift| (byte >> shift) & 3))
+            .collect();
+
+        let (clear, end) = (4, 5);
+        let mut codes = Vec::new();
+        for pair in pixels.chunks(2) {
+            codes.push(clear);
+            codes.extend_from_slice(pair);
+        }
+        codes.push(end);
+        let mut image = Vec::new();
+        let (mut bits, mut count) = (0u32, 0);
+        for code in codes {
+            bits |= (code as u32) << count;
+            count += 3;
+            while count >= 8 {
+                image.push(bits as u8);
+                bits >>= 8;
+                count -= 8;
+            }
+        }
+        if count > 0 {
+            image.push(bits as u8);
+        }
+
+        let width = (pixels.len() as u16).to_le_bytes();
+        let mut gif = b"GIF89a".to_vec();
+        gif.extend_from_slice(&width);
+        gif.extend_from_slice(&[1, 0]);
+        // A global table of four colors, then the image descriptor
+        gif.extend_from_slice(&[0x81, 0, 0]);
+        gif.extend_from_slice(&[0; 12]);
+        gif.extend_from_slice(&[0x2C, 0, 0, 0, 0]);
+        gif.extend_from_slice(&width);
+        gif.extend_from_slice(&[1, 0, 0, 2]);
+        for block in image.chunks(255) {
+            gif.push(block.len() as u8);
+            gif.extend_from_slice(block);
+        }
+        gif.extend_from_slice(&[0, 0x3B]);
+        gif
+    }
+
+    const CART: &str = r##"{"program": ": main\n\tv0 := 1\n", "options": {"tickrate": 20, "maxSize": 3583, "shiftQuirks": true, "fillColor": "#F80", "backgroundColor": "#102030"}}"##;
+
+    #[test]
+    fn parses_program_and_options() {
+        let cart = parse_octocart(&cartridge(CART)).unwrap();
+        assert_eq!(cart.program, ": main\n\tv0 := 1\n");
+        assert_eq!(cart.options.tickrate, Some(20));
+        assert_eq!(cart.options.shift_quirks, Some(true));
+        assert_eq!(cart.options.logic_quirks, None);
+        assert_eq!(cart.options.fill_color, Some(0xFF8800));
+        assert_eq!(cart.options.background_color, Some(0x102030));
+        assert_eq!(cart.options.variant(), Variant::SuperChip);
+    }
+
+    #[test]
+    fn loads_the_assembled_program() {
+        let mut hachi = Hachi::new();
+        hachi.load_octocart(&cartridge(CART)).unwrap();
+        assert_eq!(hachi.variant(), Variant::SuperChip);
+        assert!(!hachi.quirks().shift_uses_vy);
+        assert_eq!(hachi.instructions_per_frame(), 20);
+        assert_eq!(hachi.palette()[1], [0xFF, 0x88, 0x00, 0xFF]);
+        assert_eq!(hachi.ram[0x200..0x202], [0x60, 0x01]);
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_cartridge() {
+        assert_eq!(parse_octocart(b"PNG"), Err(OctocartError::NotGif));
+        let gif = cartridge(CART);
+        assert_eq!(
+            parse_octocart(&gif[..gif.len() - 4]),
+            Err(OctocartError::Truncated)
+        );
+        assert_eq!(
+            parse_octocart(&cartridge(r#"{"options": {}}"#)),
+            Err(OctocartError::BadJson)
+        );
+    }
+
+    #[test]
+    fn leaves_the_machine_alone_when_the_program_fails() {
+        let cart = cartridge(r#"{"program": ": main v0 := 300"}"#);
+        let mut hachi = Hachi::new();
+        hachi.set_variant(Variant::Chip8);
+        assert!(matches!(
+            hachi.load_octocart(&cart),
+            Err(OctocartError::Asm(AsmError::ValueOutOfRange { .. }))
+        ));
+        assert_eq!(hachi.variant(), Variant::Chip8);
+    }
+
+    #[test]
+    fn limits_json_nesting() {
+        let nested = |depth| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(Json::parse(&nested(MAX_JSON_DEPTH)).is_some());
+        assert_eq!(Json::parse(&nested(MAX_JSON_DEPTH + 1)), None);
+        assert_eq!(Json::parse(&nested(100_000)), None);
+        assert_eq!(
+            Json::parse(r#"{"a": [1, "é"]}"#),
+            Some(Json::Object(vec![(
+                "a".to_string(),
+                Json::Array(vec![Json::Number(1.0), Json::String("é".to_string())])
+            )]))
+        );
+    }
+}
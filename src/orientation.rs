@@ -0,0 +1,84 @@
+use crate::Hachi;
+
+// Clockwise rotation applied to the display on output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rotation {
+    #[default]
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+// How the display is presented to the host, for screens that are mounted
+// sideways or mirrored. Rotation is applied first, then the flips.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Orientation {
+    pub rotation: Rotation,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+}
+
+impl Orientation {
+    // Size of the output for a display of `width` x `height`.
+    pub fn output_size(&self, width: usize, height: usize) -> (usize, usize) {
+        match self.rotation {
+            Rotation::None | Rotation::Cw180 => (width, height),
+            Rotation::Cw90 | Rotation::Cw270 => (height, width),
+        }
+    }
+
+    // Maps output coordinates back to the display pixel they show.
+    pub fn to_display(&self, x: usize, y: usize, width: usize, height: usize) -> (usize, usize) {
+        let (out_w, out_h) = self.output_size(width, height);
+        let x = if self.flip_horizontal {
+            out_w - 1 - x
+        } else {
+            x
+        };
+        let y = if self.flip_vertical { out_h - 1 - y } else { y };
+
+        match self.rotation {
+            Rotation::None => (x, y),
+            Rotation::Cw90 => (y, height - 1 - x),
+            Rotation::Cw180 => (width - 1 - x, height - 1 - y),
+            Rotation::Cw270 => (width - 1 - y, x),
+        }
+    }
+}
+
+impl Hachi {
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
+    // Display size after the orientation is applied.
+    pub fn output_width(&self) -> usize {
+        self.orientation
+            .output_size(self.display_width, self.display_height)
+            .0
+    }
+
+    pub fn output_height(&self) -> usize {
+        self.orientation
+            .output_size(self.display_width, self.display_height)
+            .1
+    }
+
+    // Reads a pixel in output coordinates, i.e. with the orientation
+    // applied. Out-of-range coordinates read as off.
+    pub fn get_pixel(&self, x: usize, y: usize) -> bool {
+        if x >= self.output_width() || y >= self.output_height() {
+            return false;
+        }
+
+        let (x, y) = self
+            .orientation
+            .to_display(x, y, self.display_width, self.display_height);
+        self.display[x + self.display_width * y]
+    }
+}
@@ -0,0 +1,46 @@
+use crate::{Hachi, RenderError};
+
+// Colors (RGBA) for the four pixel values of `pixel_color`: neither plane
+// lit, the first, the second, and both. Programs that only draw to the
+// first plane use just the first two.
+pub const DEFAULT_PALETTE: [[u8; 4]; 4] = [
+    [0x00, 0x00, 0x00, 0xFF],
+    [0xFF, 0xFF, 0xFF, 0xFF],
+    [0xAA, 0xAA, 0xAA, 0xFF],
+    [0x55, 0x55, 0x55, 0xFF],
+];
+
+impl Hachi {
+    pub fn palette(&self) -> [[u8; 4]; 4] {
+        self.palette
+    }
+
+    // XO-CHIP games are usually drawn for a particular palette; Octocarts
+    // and .c8b files carry theirs and set it when loaded.
+    pub fn set_palette(&mut self, palette: [[u8; 4]; 4]) {
+        self.palette = palette;
+        self.display_dirty = true;
+    }
+
+    // Like `render_rgba`, but with both planes combined through the
+    // palette.
+    pub fn render_palette_rgba(&self, out: &mut [u8]) -> Result<(), RenderError> {
+        let width = self.output_width();
+        let needed = width * self.output_height() * 4;
+        if out.len() < needed {
+            return Err(RenderError::BufferTooSmall {
+                needed,
+                actual: out.len(),
+            });
+        }
+
+        let orientation = self.orientation;
+        let (src_width, src_height) = (self.display_width, self.display_height);
+        for (idx, dst) in out[..needed].chunks_exact_mut(4).enumerate() {
+            let (sx, sy) = orientation.to_display(idx % width, idx / width, src_width, src_height);
+            dst.copy_from_slice(&self.palette[self.pixel_color(sx, sy) as usize]);
+        }
+
+        Ok(())
+    }
+}
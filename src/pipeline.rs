@@ -0,0 +1,105 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::instruction::{Instruction, Register};
+use crate::Hachi;
+
+// One trip through fetch, decode and execute, for visualizers that want to
+// animate the pipeline. Register and memory effects are the ones the decoded
+// instruction is allowed to have; a skip that isn't taken still lists the
+// PC as written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TickRecord {
+    pub address: u16,
+    pub bytes: [u8; 2],
+    pub instruction: Instruction,
+    pub registers_read: Vec<Register>,
+    pub registers_written: Vec<Register>,
+    pub memory_read: Option<Range<usize>>,
+    pub memory_written: Option<Range<usize>>,
+    pub display_written: bool,
+}
+
+impl Hachi {
+    // Off by default, since building a record allocates on every tick.
+    pub fn set_pipeline_trace(&mut self, enabled: bool) {
+        self.pipeline_trace = enabled;
+        if !enabled {
+            self.last_tick = None;
+        }
+    }
+
+    pub fn is_tracing_pipeline(&self) -> bool {
+        self.pipeline_trace
+    }
+
+    // The record for the most recent instruction fetched, if tracing.
+    pub fn last_tick(&self) -> Option<&TickRecord> {
+        self.last_tick.as_ref()
+    }
+
+    pub(crate) fn record_pipeline(&mut self, op: u16) {
+        if !self.pipeline_trace {
+            return;
+        }
+
+        let instruction = Instruction::decode_for(op, self.variant);
+        let (memory_read, memory_written) = self.memory_access(instruction);
+
+        self.last_tick = Some(TickRecord {
+            address: self.instruction_pc,
+            bytes: op.to_be_bytes(),
+            instruction,
+            registers_read: instruction.reads(),
+            registers_written: instruction.writes(),
+            memory_read,
+            memory_written,
+            display_written: matches!(
+                instruction,
+                Instruction::ClearScreen
+                    | Instruction::Draw { .. }
+                    | Instruction::ScrollDown { .. }
+                    | Instruction::ScrollUp { .. }
+                    | Instruction::ScrollRight
+                    | Instruction::ScrollLeft
+                    | Instruction::LowRes
+                    | Instruction::HighRes
+                    | Instruction::CycleBackground
+                    | Instruction::ColorZones { .. }
+                    | Instruction::MegaOff
+                    | Instruction::MegaOn
+                    | Instruction::ScrollUpMega { .. }
+            ),
+        });
+    }
+
+    // RAM the instruction reads and writes through I, given the current
+    // register values.
+    pub(crate) fn memory_access(
+        &self,
+        instruction: Instruction,
+    ) -> (Option<Range<usize>>, Option<Range<usize>>) {
+        let i = self.i_address();
+        let span = |len: usize| Some(i..i + len);
+        let planes = self.plane_mask.count_ones() as usize;
+
+        match instruction {
+            Instruction::Draw { n, .. } => {
+                let (width, rows) = self.sprite_size(n);
+                match width * rows / 8 {
+                    0 => (None, None),
+                    len => (span(len * planes), None),
+                }
+            }
+            Instruction::Load { x } => (span(x as usize + 1), None),
+            Instruction::LoadAudio => (span(16), None),
+            Instruction::LoadRange { x, y } => (span(x.abs_diff(y) as usize + 1), None),
+            Instruction::SaveRange { x, y } => (None, span(x.abs_diff(y) as usize + 1)),
+            Instruction::Bcd { .. } => (None, span(3)),
+            Instruction::Store { x } => (None, span(x as usize + 1)),
+            Instruction::LoadPalette { nn } => (span(nn as usize * 4), None),
+            Instruction::PlaySample { .. } => (span(6), None),
+            _ => (None, None),
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use alloc::collections::BTreeMap;
+
+use crate::{Hachi, Instruction};
+
+impl Hachi {
+    // Opt-in, since it decodes and counts every instruction executed.
+    // Turning it off keeps the counts gathered so far.
+    pub fn set_opcode_counting(&mut self, enabled: bool) {
+        self.count_opcodes = enabled;
+    }
+
+    pub fn is_counting_opcodes(&self) -> bool {
+        self.count_opcodes
+    }
+
+    // How often each opcode pattern ("DXYN", "8XY4", ...) has been fetched
+    // for execution since counting started or was last cleared. Patterns
+    // that never ran are absent.
+    pub fn opcode_counts(&self) -> &BTreeMap<&'static str, u64> {
+        &self.opcode_counts
+    }
+
+    pub fn clear_opcode_counts(&mut self) {
+        self.opcode_counts.clear();
+    }
+
+    pub(crate) fn count_opcode(&mut self, op: u16) {
+        if self.count_opcodes {
+            let pattern = Instruction::decode_for(op, self.variant).pattern();
+            *self.opcode_counts.entry(pattern).or_insert(0) += 1;
+        }
+    }
+}
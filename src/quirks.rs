@@ -0,0 +1,122 @@
+use crate::{Hachi, Variant};
+
+// Behaviours that differ between CHIP-8 interpreters. The defaults match
+// what the core has always done, except that FX0A now waits for the key to
+// be released and sprites are clipped at the screen edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Quirks {
+    // FX1E sets VF to 1 when I + VX leaves the 12-bit address space and
+    // masks I back into it (the Amiga interpreter did this, and Spacefight
+    // 2091! relies on it).
+    pub i_overflow_sets_vf: bool,
+    // 8XY6 and 8XYE shift VY into VX, as on the COSMAC VIP, instead of
+    // shifting VX in place.
+    pub shift_uses_vy: bool,
+    // FX55 and FX65 leave I pointing past the last register transferred.
+    pub load_store_increments_i: bool,
+    // 8XY1, 8XY2 and 8XY3 clear VF afterwards.
+    pub logic_resets_vf: bool,
+    // BNNN is read as BXNN and jumps to XNN + VX, as CHIP-48 and SUPER-CHIP
+    // do.
+    pub jump_uses_vx: bool,
+    // FX0A completes as soon as a key is held, as most later interpreters
+    // do, rather than waiting for it to be released.
+    pub key_wait_on_press: bool,
+    // 00FE and 00FF keep the picture, scaled to the new resolution, as
+    // SUPER-CHIP 1.1 on the HP 48 did, instead of clearing the screen.
+    pub resolution_switch_preserves: bool,
+    // DXY0 draws an 8x16 sprite in low resolution, as SUPER-CHIP 1.1 on
+    // the HP 48 did, rather than the 16x16 one it draws in high resolution.
+    pub lores_dxy0_draws_8x16: bool,
+    // DXYN waits for the next vertical blank before drawing, as the VIP
+    // interpreter did, so at most one sprite is drawn per frame.
+    pub display_wait: bool,
+    // DXYN wraps the parts of a sprite that run off the screen around to
+    // the opposite edge, as XO-CHIP does, instead of clipping them. The
+    // starting position wraps either way.
+    pub sprite_wrap: bool,
+}
+
+// Presets matching the platforms in Timendus' quirks test ROM.
+impl Quirks {
+    // The COSMAC VIP interpreter.
+    pub const fn original_chip8() -> Self {
+        Self {
+            i_overflow_sets_vf: false,
+            shift_uses_vy: true,
+            load_store_increments_i: true,
+            logic_resets_vf: true,
+            jump_uses_vx: false,
+            key_wait_on_press: false,
+            resolution_switch_preserves: false,
+            lores_dxy0_draws_8x16: false,
+            display_wait: true,
+            sprite_wrap: false,
+        }
+    }
+
+    // CHIP-48 on the HP 48. It advanced I by X rather than X + 1 on FX55
+    // and FX65; leaving I alone is the closer of the two settings.
+    pub const fn chip48() -> Self {
+        Self {
+            i_overflow_sets_vf: false,
+            shift_uses_vy: false,
+            load_store_increments_i: false,
+            logic_resets_vf: false,
+            jump_uses_vx: true,
+            key_wait_on_press: true,
+            resolution_switch_preserves: false,
+            lores_dxy0_draws_8x16: false,
+            display_wait: false,
+            sprite_wrap: false,
+        }
+    }
+
+    // SUPER-CHIP 1.1 as most modern interpreters run it.
+    pub const fn schip_modern() -> Self {
+        Self::chip48()
+    }
+
+    // Octo's XO-CHIP, which went back to the VIP's shifts and loads.
+    pub const fn xochip() -> Self {
+        Self {
+            i_overflow_sets_vf: false,
+            shift_uses_vy: true,
+            load_store_increments_i: true,
+            logic_resets_vf: false,
+            jump_uses_vx: false,
+            key_wait_on_press: true,
+            resolution_switch_preserves: false,
+            lores_dxy0_draws_8x16: false,
+            display_wait: false,
+            sprite_wrap: true,
+        }
+    }
+
+    pub fn for_variant(variant: Variant) -> Self {
+        match variant {
+            Variant::Chip8 => Self::original_chip8(),
+            Variant::SuperChip => Self::schip_modern(),
+            Variant::XoChip => Self::xochip(),
+            Variant::Chip8X => Self::original_chip8(),
+            #[cfg(feature = "megachip")]
+            Variant::MegaChip => Self::schip_modern(),
+        }
+    }
+}
+
+impl Hachi {
+    pub fn new_with_quirks(quirks: Quirks) -> Self {
+        let mut hachi = Hachi::new();
+        hachi.quirks = quirks;
+        hachi
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+}
@@ -0,0 +1,160 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use crate::{Hachi, Key};
+
+// A key going down or up. `instruction` counts instructions from the start
+// of the recording, which is what playback goes by; `frame` is the frame
+// counter at the time, for tools that want to show the input on a
+// timeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputEvent {
+    pub instruction: u64,
+    pub frame: u64,
+    pub key: Key,
+    pub pressed: bool,
+}
+
+// Everything pressed during a recording, oldest first, and where the random
+// generator stood when it began. Replaying it from the state the recording
+// started in repeats the run exactly, provided the random source could
+// report its position; `random` is `None` when it couldn't.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputRecording {
+    pub random: Option<u64>,
+    pub events: Vec<InputEvent>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct InputRecorder {
+    origin: u64,
+    random: Option<u64>,
+    events: Vec<InputEvent>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct InputPlayback {
+    origin: u64,
+    events: VecDeque<InputEvent>,
+}
+
+impl Hachi {
+    // Instructions fetched since the machine was created. Unlike the frame
+    // counter this isn't cleared by `reset` or restored with a save state.
+    pub fn instruction_count(&self) -> u64 {
+        self.instruction_count
+    }
+
+    // Starts logging every `keypress`, discarding anything recorded before.
+    pub fn start_input_recording(&mut self) {
+        self.input_recorder = Some(InputRecorder {
+            origin: self.instruction_count,
+            random: self.random.saved_state(),
+            events: Vec::new(),
+        });
+    }
+
+    pub fn is_recording_input(&self) -> bool {
+        self.input_recorder.is_some()
+    }
+
+    pub fn stop_input_recording(&mut self) -> Option<InputRecording> {
+        let recorder = self.input_recorder.take()?;
+        Some(InputRecording {
+            random: recorder.random,
+            events: recorder.events,
+        })
+    }
+
+    // Feeds `recording` back in, each event just before the instruction it
+    // was recorded at, counting from now. Until it runs out, `keypress`
+    // from the host is ignored so live input can't desynchronize the run.
+    // The random generator goes back to where the recording began.
+    pub fn play_input(&mut self, recording: InputRecording) {
+        if let Some(state) = recording.random {
+            self.restore_random(state);
+        }
+        self.input_playback = Some(InputPlayback {
+            origin: self.instruction_count,
+            events: recording.events.into(),
+        });
+    }
+
+    pub fn is_playing_input(&self) -> bool {
+        self.input_playback.is_some()
+    }
+
+    // Hands input back to the host, leaving keys as the recording left them.
+    pub fn stop_input_playback(&mut self) {
+        self.input_playback = None;
+    }
+
+    pub(crate) fn record_key(&mut self, key: Key, pressed: bool) {
+        if let Some(recorder) = &mut self.input_recorder {
+            recorder.events.push(InputEvent {
+                instruction: self.instruction_count - recorder.origin,
+                frame: self.frame_count,
+                key,
+                pressed,
+            });
+        }
+    }
+
+    // Called before each instruction is fetched.
+    pub(crate) fn play_due_input(&mut self) {
+        let playback = match &mut self.input_playback {
+            Some(playback) => playback,
+            None => return,
+        };
+        let now = self.instruction_count - playback.origin;
+
+        let mut due = Vec::new();
+        while let Some(event) = playback.events.front() {
+            if event.instruction > now {
+                break;
+            }
+            due.push(*event);
+            playback.events.pop_front();
+        }
+        if playback.events.is_empty() {
+            self.input_playback = None;
+        }
+
+        for event in due {
+            self.apply_key(event.key, event.pressed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RandomSource, Xorshift};
+
+    // FX0A between two random draws, over and over.
+    const ROM: [u8; 8] = [0xF1, 0x0A, 0xC0, 0xFF, 0xC2, 0xFF, 0x12, 0x00];
+
+    #[test]
+    fn replays_random_draws_from_another_source() {
+        let mut original = Hachi::new_with_seed(7);
+        original.load(&ROM).unwrap();
+        original.start_input_recording();
+        for key in [Key::Key1, Key::Key5, Key::KeyA] {
+            original.keypress(key, true);
+            original.tick_n(2).unwrap();
+            original.keypress(key, false);
+            original.tick_n(4).unwrap();
+        }
+        let recording = original.stop_input_recording().unwrap();
+        assert_eq!(
+            recording.random,
+            Some(Xorshift::new(7).saved_state().unwrap())
+        );
+
+        let mut replay = Hachi::new();
+        replay.load(&ROM).unwrap();
+        replay.play_input(recording);
+        replay.tick_n(18).unwrap();
+        assert_eq!(replay.registers(), original.registers());
+    }
+}
@@ -0,0 +1,55 @@
+use crate::{Hachi, NUM_REGISTERS};
+
+impl Hachi {
+    // Only the low nibble of `reg` is used, as when it comes from an opcode.
+    pub fn v(&self, reg: u8) -> u8 {
+        self.v_registers[(reg & 0xF) as usize]
+    }
+
+    pub fn set_v(&mut self, reg: u8, value: u8) {
+        self.v_registers[(reg & 0xF) as usize] = value;
+    }
+
+    // V0 through VF.
+    pub fn registers(&self) -> &[u8; NUM_REGISTERS] {
+        &self.v_registers
+    }
+
+    pub fn set_registers(&mut self, values: [u8; NUM_REGISTERS]) {
+        self.v_registers = values;
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.program_counter
+    }
+
+    // Moves execution to `addr`, abandoning any FX0A or display wait in
+    // progress. Returns false, leaving the PC alone, if a whole instruction
+    // at `addr` wouldn't fit in RAM.
+    pub fn set_pc(&mut self, addr: u16) -> bool {
+        if addr as usize + 1 >= self.ram.len() {
+            return false;
+        }
+
+        self.program_counter = addr;
+        self.key_wait = None;
+        self.waiting_for_key = false;
+        self.display_wait = None;
+        self.stopped_at = None;
+        true
+    }
+
+    pub fn i(&self) -> u16 {
+        self.i_register
+    }
+
+    // Returns false, leaving I alone, if `addr` is outside RAM.
+    pub fn set_i(&mut self, addr: u16) -> bool {
+        if addr as usize >= self.ram.len() {
+            return false;
+        }
+
+        self.set_i_address(addr as usize);
+        true
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{Hachi, Rotation};
+
+// Byte order of a 32-bit pixel, named from most to least significant byte
+// of the `u32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PixelLayout {
+    #[default]
+    Argb8888,
+    Rgba8888,
+    Abgr8888,
+    Bgra8888,
+}
+
+impl PixelLayout {
+    pub fn pack(self, [r, g, b, a]: [u8; 4]) -> u32 {
+        let bytes = match self {
+            PixelLayout::Argb8888 => [a, r, g, b],
+            PixelLayout::Rgba8888 => [r, g, b, a],
+            PixelLayout::Abgr8888 => [a, b, g, r],
+            PixelLayout::Bgra8888 => [b, g, r, a],
+        };
+        u32::from_be_bytes(bytes)
+    }
+}
+
+// How lit and unlit pixels are written by `render_into`. Colors are RGBA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderFormat {
+    pub layout: PixelLayout,
+    pub foreground: [u8; 4],
+    pub background: [u8; 4],
+}
+
+impl Default for RenderFormat {
+    fn default() -> Self {
+        Self {
+            layout: PixelLayout::default(),
+            foreground: [0xFF, 0xFF, 0xFF, 0xFF],
+            background: [0x00, 0x00, 0x00, 0xFF],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderError {
+    StrideTooSmall { stride: usize, width: usize },
+    BufferTooSmall { needed: usize, actual: usize },
+    ZeroScale,
+}
+
+impl Hachi {
+    // Writes the display straight into a caller-owned surface whose rows
+    // are `stride` pixels apart, e.g. a locked SDL texture or a mapped
+    // framebuffer. The orientation is applied, so the surface must be
+    // `output_width()` x `output_height()`. Padding between rows is left
+    // untouched.
+    pub fn render_into(
+        &self,
+        out: &mut [u32],
+        stride: usize,
+        format: RenderFormat,
+    ) -> Result<(), RenderError> {
+        let width = self.output_width();
+        let height = self.output_height();
+
+        if stride < width {
+            return Err(RenderError::StrideTooSmall { stride, width });
+        }
+        let needed = stride * (height - 1) + width;
+        if out.len() < needed {
+            return Err(RenderError::BufferTooSmall {
+                needed,
+                actual: out.len(),
+            });
+        }
+
+        let on = format.layout.pack(format.foreground);
+        let off = format.layout.pack(format.background);
+        let orientation = self.orientation;
+        let (src_width, src_height) = (self.display_width, self.display_height);
+        let upright = orientation.rotation == Rotation::None
+            && !orientation.flip_horizontal
+            && !orientation.flip_vertical;
+
+        for (y, row) in out.chunks_mut(stride).take(height).enumerate() {
+            if upright {
+                let src = &self.display[y * src_width..(y + 1) * src_width];
+                for (dst, &lit) in row.iter_mut().zip(src) {
+                    *dst = if lit { on } else { off };
+                }
+            } else {
+                for (x, dst) in row[..width].iter_mut().enumerate() {
+                    let (sx, sy) = orientation.to_display(x, y, src_width, src_height);
+                    *dst = if self.display[sx + src_width * sy] {
+                        on
+                    } else {
+                        off
+                    };
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Expands the display into tightly packed RGBA bytes, four per pixel,
+    // the layout `pixels`, SDL's ABGR8888 (on little-endian hosts) and wgpu's
+    // Rgba8Unorm textures all expect. The orientation is applied, so `out`
+    // must hold `output_width() * output_height() * 4` bytes.
+    pub fn render_rgba(&self, fg: [u8; 4], bg: [u8; 4], out: &mut [u8]) -> Result<(), RenderError> {
+        let width = self.output_width();
+        let needed = width * self.output_height() * 4;
+        if out.len() < needed {
+            return Err(RenderError::BufferTooSmall {
+                needed,
+                actual: out.len(),
+            });
+        }
+
+        let orientation = self.orientation;
+        let (src_width, src_height) = (self.display_width, self.display_height);
+        for (idx, dst) in out[..needed].chunks_exact_mut(4).enumerate() {
+            let (sx, sy) = orientation.to_display(idx % width, idx / width, src_width, src_height);
+            let lit = self.display[sx + src_width * sy];
+            dst.copy_from_slice(if lit { &fg } else { &bg });
+        }
+
+        Ok(())
+    }
+
+    // The display blown up `scale` times with nearest-neighbor sampling,
+    // one byte per pixel: 0xFF lit, 0x00 unlit. The orientation is applied,
+    // so `out` must hold `output_width() * scale` x `output_height() * scale`
+    // bytes.
+    pub fn render_scaled(&self, scale: usize, out: &mut [u8]) -> Result<(), RenderError> {
+        self.render_scaled_pixels(scale, &[0xFF], &[0x00], out)
+    }
+
+    // `render_scaled` into tightly packed RGBA bytes, as `render_rgba`.
+    pub fn render_scaled_rgba(
+        &self,
+        scale: usize,
+        fg: [u8; 4],
+        bg: [u8; 4],
+        out: &mut [u8],
+    ) -> Result<(), RenderError> {
+        self.render_scaled_pixels(scale, &fg, &bg, out)
+    }
+
+    // Each source row is expanded once and then copied down the remaining
+    // `scale - 1` output rows.
+    fn render_scaled_pixels(
+        &self,
+        scale: usize,
+        on: &[u8],
+        off: &[u8],
+        out: &mut [u8],
+    ) -> Result<(), RenderError> {
+        if scale == 0 {
+            return Err(RenderError::ZeroScale);
+        }
+        let width = self.output_width();
+        let height = self.output_height();
+        let row_len = width * scale * on.len();
+        let needed = row_len * height * scale;
+        if out.len() < needed {
+            return Err(RenderError::BufferTooSmall {
+                needed,
+                actual: out.len(),
+            });
+        }
+
+        let orientation = self.orientation;
+        let (src_width, src_height) = (self.display_width, self.display_height);
+        for (y, block) in out[..needed].chunks_exact_mut(row_len * scale).enumerate() {
+            let (first, rest) = block.split_at_mut(row_len);
+            for (x, run) in first.chunks_exact_mut(on.len() * scale).enumerate() {
+                let (sx, sy) = orientation.to_display(x, y, src_width, src_height);
+                let pixel = if self.display[sx + src_width * sy] {
+                    on
+                } else {
+                    off
+                };
+                for dst in run.chunks_exact_mut(on.len()) {
+                    dst.copy_from_slice(pixel);
+                }
+            }
+            for dst in rest.chunks_exact_mut(row_len) {
+                dst.copy_from_slice(first);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,221 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use crate::hash::crc32;
+use crate::trace::Tracer;
+use crate::{Hachi, HachiError, HistoryEntry, NUM_REGISTERS};
+
+// Bytes of RAM captured either side of PC and I.
+const RAM_WINDOW_RADIUS: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReportCause {
+    Error(HachiError),
+    Divergence(String),
+    Manual,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RamWindow {
+    pub start: usize,
+    pub bytes: Vec<u8>,
+}
+
+// Everything needed to make sense of a crash or desync after the fact,
+// meant to be attached to a bug report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrashReport {
+    pub cause: ReportCause,
+    pub program_counter: u16,
+    pub i_register: usize,
+    pub v_registers: [u8; NUM_REGISTERS],
+    pub stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub frame_count: u64,
+    pub history: Vec<HistoryEntry>,
+    // The buffered trace as log lines, oldest first, when tracing with
+    // `trace_to_buffer`; empty otherwise.
+    pub trace: Vec<String>,
+    pub ram_at_pc: RamWindow,
+    pub ram_at_i: RamWindow,
+    pub rom_crc32: u32,
+    pub rom_len: usize,
+}
+
+impl CrashReport {
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        out.push('{');
+
+        match &self.cause {
+            ReportCause::Error(err) => {
+                let _ = write!(
+                    out,
+                    "\"cause\":\"error\",\"message\":{}",
+                    json_string(&err.to_string())
+                );
+            }
+            ReportCause::Divergence(note) => {
+                let _ = write!(
+                    out,
+                    "\"cause\":\"divergence\",\"message\":{}",
+                    json_string(note)
+                );
+            }
+            ReportCause::Manual => out.push_str("\"cause\":\"manual\",\"message\":null"),
+        }
+
+        let _ = write!(
+            out,
+            ",\"pc\":{},\"i\":{},\"v\":{},\"stack\":{},\"dt\":{},\"st\":{},\"frame\":{}",
+            self.program_counter,
+            self.i_register,
+            json_array(self.v_registers.iter()),
+            json_array(self.stack.iter()),
+            self.delay_timer,
+            self.sound_timer,
+            self.frame_count,
+        );
+
+        out.push_str(",\"history\":[");
+        for (idx, entry) in self.history.iter().enumerate() {
+            if idx > 0 {
+                out.push(',');
+            }
+            let _ = write!(out, "{{\"pc\":{},\"opcode\":{}}}", entry.pc, entry.opcode);
+        }
+        out.push(']');
+
+        out.push_str(",\"trace\":[");
+        for (idx, line) in self.trace.iter().enumerate() {
+            if idx > 0 {
+                out.push(',');
+            }
+            out.push_str(&json_string(line));
+        }
+        out.push(']');
+
+        for (name, window) in [("ram_at_pc", &self.ram_at_pc), ("ram_at_i", &self.ram_at_i)] {
+            let _ = write!(
+                out,
+                ",\"{}\":{{\"start\":{},\"bytes\":{}}}",
+                name,
+                window.start,
+                json_array(window.bytes.iter())
+            );
+        }
+
+        let _ = write!(
+            out,
+            ",\"rom_crc32\":\"{:08x}\",\"rom_len\":{}}}",
+            self.rom_crc32, self.rom_len
+        );
+        out
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_json().into_bytes()
+    }
+}
+
+fn json_array<T: core::fmt::Display>(items: impl Iterator<Item = T>) -> String {
+    let mut out = String::from("[");
+    for (idx, item) in items.enumerate() {
+        if idx > 0 {
+            out.push(',');
+        }
+        let _ = write!(out, "{}", item);
+    }
+    out.push(']');
+    out
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+impl Hachi {
+    pub fn crash_report(&self, cause: ReportCause) -> CrashReport {
+        let pc = match &cause {
+            ReportCause::Error(err) => err.pc(),
+            _ => self.program_counter,
+        };
+
+        CrashReport {
+            cause,
+            program_counter: pc,
+            i_register: self.i_address(),
+            v_registers: self.v_registers,
+            stack: self.stack[..self.stack_pointer as usize].to_vec(),
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            frame_count: self.frame_count,
+            history: self.pc_history.iter().copied().collect(),
+            trace: match &self.trace {
+                Some(Tracer::Buffer { entries, .. }) => {
+                    entries.iter().map(|entry| entry.to_string()).collect()
+                }
+                _ => Vec::new(),
+            },
+            ram_at_pc: self.ram_window(pc as usize),
+            ram_at_i: self.ram_window(self.i_address()),
+            rom_crc32: crc32(&self.rom),
+            rom_len: self.rom.len(),
+        }
+    }
+
+    fn ram_window(&self, center: usize) -> RamWindow {
+        let start = center.saturating_sub(RAM_WINDOW_RADIUS).min(self.ram.len());
+        let end = (center + RAM_WINDOW_RADIUS).min(self.ram.len());
+        RamWindow {
+            start,
+            bytes: self.ram[start..end].to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Variant;
+
+    #[test]
+    fn includes_the_buffered_trace_and_a_long_i() {
+        let mut hachi = Hachi::new();
+        hachi.set_variant(Variant::XoChip);
+        hachi.trace_to_buffer(4);
+        // v0 := 5, i := long 0xFFF0, then an unknown opcode
+        hachi
+            .load(&[0x60, 0x05, 0xF0, 0x00, 0xFF, 0xF0, 0xFF, 0xFF])
+            .unwrap();
+        hachi.tick_n(2).unwrap();
+        let err = hachi.tick().unwrap_err();
+
+        let report = hachi.crash_report(ReportCause::Error(err));
+        assert_eq!(report.program_counter, 0x206);
+        assert_eq!(report.i_register, 0xFFF0);
+        assert_eq!(report.ram_at_i.start, 0xFFF0 - RAM_WINDOW_RADIUS);
+        assert_eq!(report.trace.len(), 2);
+        assert!(report.trace[0].starts_with("200: 6005"));
+        assert!(report
+            .to_json()
+            .contains(&alloc::format!("\"trace\":[\"{}\"", report.trace[0])));
+
+        hachi.stop_trace();
+        assert!(hachi.crash_report(ReportCause::Manual).trace.is_empty());
+    }
+}
@@ -0,0 +1,98 @@
+use alloc::collections::VecDeque;
+
+use crate::{Hachi, StateBuf, StatePool};
+
+// Keeps the last `capacity` snapshots of a machine, one every `interval`
+// calls to `record`, so the host can step the emulation backwards. Buffers
+// are recycled through a `StatePool`, so once the ring is full recording
+// no longer allocates.
+#[derive(Debug)]
+pub struct Rewind {
+    snapshots: VecDeque<StateBuf>,
+    pool: StatePool,
+    capacity: usize,
+    interval: u32,
+    calls: u32,
+}
+
+impl Rewind {
+    pub fn new(capacity: usize, interval: u32) -> Self {
+        Self {
+            snapshots: VecDeque::with_capacity(capacity),
+            pool: StatePool::with_capacity(capacity),
+            capacity,
+            interval: interval.max(1),
+            calls: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn interval(&self) -> u32 {
+        self.interval
+    }
+
+    // Snapshots currently held, i.e. how far back `rewind` can go.
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    // Call after every tick (or every frame, depending on what `interval`
+    // counts). Every `interval`th call takes a snapshot, dropping the
+    // oldest once the ring is full.
+    pub fn record(&mut self, hachi: &Hachi) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.calls += 1;
+        if self.calls < self.interval {
+            return;
+        }
+        self.calls = 0;
+
+        let mut buf = if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front().unwrap_or_default()
+        } else {
+            self.pool.acquire()
+        };
+        hachi.save_into(&mut buf);
+        self.snapshots.push_back(buf);
+    }
+
+    // Restores the snapshot `steps` back, discarding it and everything newer.
+    // Returns how many steps were actually taken, which is less than asked
+    // when the history runs out.
+    pub fn rewind(&mut self, hachi: &mut Hachi, steps: usize) -> usize {
+        let steps = steps.min(self.snapshots.len());
+        if steps == 0 {
+            return 0;
+        }
+
+        for _ in 1..steps {
+            if let Some(buf) = self.snapshots.pop_back() {
+                self.pool.release(buf);
+            }
+        }
+        if let Some(buf) = self.snapshots.pop_back() {
+            // Only states this machine saved itself are ever stored
+            let restored = hachi.restore_from(&buf);
+            debug_assert!(restored.is_ok(), "rewind snapshot failed to restore");
+            self.pool.release(buf);
+        }
+        self.calls = 0;
+        steps
+    }
+
+    pub fn clear(&mut self) {
+        while let Some(buf) = self.snapshots.pop_back() {
+            self.pool.release(buf);
+        }
+        self.calls = 0;
+    }
+}
@@ -0,0 +1,68 @@
+use crate::hash::crc32;
+use crate::{Hachi, Quirks, Variant};
+
+// Settings a particular ROM image is known to need, keyed by the CRC-32
+// of the whole file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RomProfile {
+    pub crc32: u32,
+    pub name: &'static str,
+    pub variant: Variant,
+    pub quirks: Quirks,
+    // The speed the ROM was written for.
+    pub instructions_per_frame: u32,
+}
+
+// The bundled ROMs. Frontends with their own collection can pass a larger
+// table to `configure_for_rom_in`.
+#[cfg(feature = "romdb")]
+pub static ROM_PROFILES: [RomProfile; 3] = [
+    RomProfile {
+        crc32: 0xAF88_4319,
+        name: "hex-font",
+        variant: Variant::Chip8,
+        quirks: Quirks::original_chip8(),
+        instructions_per_frame: 10,
+    },
+    RomProfile {
+        crc32: 0xF43D_81F1,
+        name: "smoke-test",
+        variant: Variant::Chip8,
+        quirks: Quirks::original_chip8(),
+        instructions_per_frame: 10,
+    },
+    RomProfile {
+        crc32: 0x871C_61B2,
+        name: "sketch",
+        variant: Variant::Chip8,
+        quirks: Quirks::original_chip8(),
+        instructions_per_frame: 10,
+    },
+];
+
+pub fn lookup_rom(data: &[u8], profiles: &'static [RomProfile]) -> Option<&'static RomProfile> {
+    let crc = crc32(data);
+    profiles.iter().find(|profile| profile.crc32 == crc)
+}
+
+impl Hachi {
+    // Switches to the variant, quirks and speed the built-in table lists
+    // for `rom`, before loading it. Returns the matching entry, or `None`
+    // with nothing changed if the ROM isn't known.
+    #[cfg(feature = "romdb")]
+    pub fn configure_for_rom(&mut self, rom: &[u8]) -> Option<&'static RomProfile> {
+        self.configure_for_rom_in(rom, &ROM_PROFILES)
+    }
+
+    pub fn configure_for_rom_in(
+        &mut self,
+        rom: &[u8],
+        profiles: &'static [RomProfile],
+    ) -> Option<&'static RomProfile> {
+        let profile = lookup_rom(rom, profiles)?;
+        self.set_variant(profile.variant);
+        self.set_quirks(profile.quirks);
+        self.set_instructions_per_frame(profile.instructions_per_frame);
+        Some(profile)
+    }
+}
@@ -0,0 +1,179 @@
+// Small ROMs written for this crate and dedicated to the public domain
+// (CC0), so examples and smoke tests have something to run without going
+// looking for ROM files. Each one is listed next to its Octo source.
+
+pub struct BuiltinRom {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub data: &'static [u8],
+}
+
+pub fn builtin_roms() -> &'static [BuiltinRom] {
+    &BUILTIN_ROMS
+}
+
+pub fn builtin_rom(name: &str) -> Option<&'static BuiltinRom> {
+    BUILTIN_ROMS.iter().find(|rom| rom.name == name)
+}
+
+static BUILTIN_ROMS: [BuiltinRom; 3] = [
+    BuiltinRom {
+        name: "hex-font",
+        description: "Draws the sixteen built-in hex digits in two rows, then halts.",
+        data: &HEX_FONT,
+    },
+    BuiltinRom {
+        name: "smoke-test",
+        description: "Runs eight basic opcode checks and draws how many passed (8 when all do).",
+        data: &SMOKE_TEST,
+    },
+    BuiltinRom {
+        name: "sketch",
+        description: "Moves a drawing cursor with keys 2, 4, 6 and 8.",
+        data: &SKETCH,
+    },
+];
+
+#[rustfmt::skip]
+const HEX_FONT: [u8; 32] = [
+    0x00, 0xE0, // 200 clear
+    0x60, 0x00, // 202 v0 := 0  digit
+    0x61, 0x00, // 204 v1 := 0  x
+    0x62, 0x01, // 206 v2 := 1  y
+    // loop:
+    0xF0, 0x29, // 208 i := hex v0
+    0xD1, 0x25, // 20A sprite v1 v2 5
+    0x70, 0x01, // 20C v0 += 1
+    0x71, 0x08, // 20E v1 += 8
+    0x41, 0x40, // 210 if v1 == 64 then
+    0x12, 0x16, // 212   jump newline
+    0x12, 0x08, // 214 jump loop
+    // newline:
+    0x61, 0x00, // 216 v1 := 0
+    0x72, 0x08, // 218 v2 += 8
+    0x30, 0x10, // 21A if v0 != 16 then
+    0x12, 0x08, // 21C   jump loop
+    // halt:
+    0x12, 0x1E, // 21E jump halt
+];
+
+#[rustfmt::skip]
+const SMOKE_TEST: [u8; 111] = [
+    0x65, 0x00, // 200 v5 := 0  passed
+    0x6A, 0xFF, // 202 va := 0xFF
+    0x7A, 0x02, // 204 va += 2
+    0x3A, 0x01, // 206 if va != 1 then
+    0x12, 0x0C, // 208   jump t1
+    0x75, 0x01, // 20A v5 += 1
+    // t1:
+    0x6A, 0xFF, // 20C va := 0xFF
+    0x6B, 0x01, // 20E vb := 1
+    0x8A, 0xB4, // 210 va += vb
+    0x3F, 0x01, // 212 if vf != 1 then
+    0x12, 0x18, // 214   jump t2
+    0x75, 0x01, // 216 v5 += 1
+    // t2:
+    0x6A, 0x01, // 218 va := 1
+    0x6B, 0x02, // 21A vb := 2
+    0x8A, 0xB5, // 21C va -= vb
+    0x3F, 0x00, // 21E if vf != 0 then
+    0x12, 0x24, // 220   jump t3
+    0x75, 0x01, // 222 v5 += 1
+    // t3:
+    0x6A, 0x05, // 224 va := 5
+    0x6B, 0x03, // 226 vb := 3
+    0x8A, 0xB5, // 228 va -= vb
+    0x3A, 0x02, // 22A if va != 2 then
+    0x12, 0x30, // 22C   jump t4
+    0x75, 0x01, // 22E v5 += 1
+    // t4:
+    0x6A, 0x9C, // 230 va := 156
+    0xA2, 0x6C, // 232 i := scratch
+    0xFA, 0x33, // 234 bcd va
+    0xF2, 0x65, // 236 load v2
+    0x32, 0x06, // 238 if v2 != 6 then
+    0x12, 0x3E, // 23A   jump t5
+    0x75, 0x01, // 23C v5 += 1
+    // t5:
+    0x6A, 0x81, // 23E va := 0x81
+    0x8A, 0xA6, // 240 va >>= va
+    0x3A, 0x40, // 242 if va != 0x40 then
+    0x12, 0x48, // 244   jump t6
+    0x75, 0x01, // 246 v5 += 1
+    // t6:
+    0x6A, 0x81, // 248 va := 0x81
+    0x8A, 0xAE, // 24A va <<= va
+    0x3F, 0x01, // 24C if vf != 1 then
+    0x12, 0x52, // 24E   jump t7
+    0x75, 0x01, // 250 v5 += 1
+    // t7:
+    0x6C, 0x00, // 252 vc := 0
+    0x22, 0x68, // 254 sub
+    0x3C, 0x2A, // 256 if vc != 42 then
+    0x12, 0x5C, // 258   jump t8
+    0x75, 0x01, // 25A v5 += 1
+    // t8:
+    0x00, 0xE0, // 25C clear
+    0xF5, 0x29, // 25E i := hex v5
+    0x60, 0x1C, // 260 v0 := 28
+    0x61, 0x0D, // 262 v1 := 13
+    0xD0, 0x15, // 264 sprite v0 v1 5
+    // halt:
+    0x12, 0x66, // 266 jump halt
+    // sub:
+    0x6C, 0x2A, // 268 vc := 42
+    0x00, 0xEE, // 26A return
+    // scratch:
+    0x00, 0x00, 0x00, // 26C 3 bytes
+];
+
+#[rustfmt::skip]
+const SKETCH: [u8; 79] = [
+    0x00, 0xE0, // 200 clear
+    0x60, 0x20, // 202 v0 := 32  x
+    0x61, 0x10, // 204 v1 := 16  y
+    0xA2, 0x4E, // 206 i := dot
+    0xD0, 0x11, // 208 sprite v0 v1 1
+    // loop:
+    0x66, 0x00, // 20A v6 := 0  moved
+    0x62, 0x04, // 20C v2 := 4
+    0xF2, 0x15, // 20E delay := v2
+    // wait:
+    0xF2, 0x07, // 210 v2 := delay
+    0x32, 0x00, // 212 if v2 != 0 then
+    0x12, 0x10, // 214   jump wait
+    0x63, 0x02, // 216 v3 := 2
+    0xE3, 0x9E, // 218 if v3 -key then
+    0x12, 0x20, // 21A   jump k2
+    0x71, 0xFF, // 21C v1 += -1
+    0x66, 0x01, // 21E v6 := 1
+    // k2:
+    0x63, 0x08, // 220 v3 := 8
+    0xE3, 0x9E, // 222 if v3 -key then
+    0x12, 0x2A, // 224   jump k8
+    0x71, 0x01, // 226 v1 += 1
+    0x66, 0x01, // 228 v6 := 1
+    // k8:
+    0x63, 0x04, // 22A v3 := 4
+    0xE3, 0x9E, // 22C if v3 -key then
+    0x12, 0x34, // 22E   jump k4
+    0x70, 0xFF, // 230 v0 += -1
+    0x66, 0x01, // 232 v6 := 1
+    // k4:
+    0x63, 0x06, // 234 v3 := 6
+    0xE3, 0x9E, // 236 if v3 -key then
+    0x12, 0x3E, // 238   jump k6
+    0x70, 0x01, // 23A v0 += 1
+    0x66, 0x01, // 23C v6 := 1
+    // k6:
+    0x64, 0x3F, // 23E v4 := 63
+    0x80, 0x42, // 240 v0 &= v4
+    0x64, 0x1F, // 242 v4 := 31
+    0x81, 0x42, // 244 v1 &= v4
+    0x36, 0x01, // 246 if v6 != 1 then
+    0x12, 0x0A, // 248   jump loop
+    0xD0, 0x11, // 24A sprite v0 v1 1
+    0x12, 0x0A, // 24C jump loop
+    // dot:
+    0x80, // 24E one pixel
+];
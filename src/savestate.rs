@@ -0,0 +1,810 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::chip8x::{Chip8x, Chip8xColor, BACKGROUNDS, NUM_ZONES};
+use crate::input::KeyLatch;
+use crate::schip::NUM_RPL_FLAGS;
+use crate::shared::Arc;
+use crate::xochip::{AUDIO_PATTERN_SIZE, DEFAULT_PITCH};
+#[cfg(feature = "megachip")]
+use crate::{megachip::MegaChip, BlendMode, MegaSound, MEGA_DISPLAY_HEIGHT, MEGA_DISPLAY_WIDTH};
+use crate::{
+    Hachi, HaltReason, Key, Variant, DEFAULT_STACK_DEPTH, DISPLAY_HEIGHT, DISPLAY_WIDTH, NUM_KEYS,
+    NUM_REGISTERS, RAM_SIZE,
+};
+
+const MAGIC: [u8; 4] = *b"HCHI";
+const VERSION: u16 = 6;
+// States from every version back to this one can still be restored.
+const OLDEST_VERSION: u16 = 1;
+
+const HEADER_SIZE: usize = MAGIC.len() + 2;
+
+// Version 6's block of in-flight execution state: random generator, flags,
+// FX0A key, latched key, display wait and halt.
+const EXECUTION_SIZE: usize = 9 + 1 + 1 + 1 + 8 + 3;
+
+// Byte standing for "no key" in the key fields.
+const NO_KEY: u8 = 0xFF;
+
+fn state_size(stack_depth: usize, display_len: usize, ram_len: usize) -> usize {
+    HEADER_SIZE
+        + 2 // program counter
+        + 2 // i register
+        + 2 // stack pointer
+        + 1 // delay timer
+        + 1 // sound timer
+        + NUM_REGISTERS
+        + 1 // variant
+        + 2 // display width
+        + 2 // display height
+        + NUM_RPL_FLAGS
+        + 1 // selected planes
+        + 1 // pitch
+        + AUDIO_PATTERN_SIZE
+        + 4 // RAM size
+        + 1 // MEGA-CHIP block present
+        + EXECUTION_SIZE
+        + 2 // stack depth
+        + 2 // stack high-water mark
+        + stack_depth * 2
+        + 2 // keys
+        + 8 // frame count
+        + ram_len
+        + display_len.div_ceil(8) * 2 // both planes
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateError {
+    BadMagic,
+    UnsupportedVersion(u16),
+    Truncated { expected: usize, actual: usize },
+    // A field holds a value this build can't represent, such as an unknown
+    // variant or display size.
+    Corrupt,
+}
+
+// A reusable buffer holding one serialized machine state. Saving into a
+// buffer that already has capacity never touches the allocator, which is
+// what makes per-frame snapshots for rewind and rollback affordable.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateBuf {
+    data: Vec<u8>,
+}
+
+impl StateBuf {
+    pub fn new() -> Self {
+        Self {
+            data: Vec::with_capacity(state_size(
+                DEFAULT_STACK_DEPTH,
+                DISPLAY_WIDTH * DISPLAY_HEIGHT,
+                RAM_SIZE,
+            )),
+        }
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Self {
+        Self { data }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+// A free list of state buffers. Buffers handed back with `release` are
+// reused by later calls to `acquire`.
+#[derive(Debug, Default)]
+pub struct StatePool {
+    free: Vec<StateBuf>,
+}
+
+impl StatePool {
+    pub fn new() -> Self {
+        Self { free: Vec::new() }
+    }
+
+    pub fn with_capacity(count: usize) -> Self {
+        Self {
+            free: (0..count).map(|_| StateBuf::new()).collect(),
+        }
+    }
+
+    pub fn acquire(&mut self) -> StateBuf {
+        self.free.pop().unwrap_or_default()
+    }
+
+    pub fn release(&mut self, buf: StateBuf) {
+        self.free.push(buf);
+    }
+
+    pub fn available(&self) -> usize {
+        self.free.len()
+    }
+}
+
+impl Hachi {
+    pub fn save_into(&self, buf: &mut StateBuf) {
+        let out = &mut buf.data;
+        out.clear();
+        out.reserve(state_size(
+            self.stack.len(),
+            self.display.len(),
+            self.ram.len(),
+        ));
+
+        out.extend_from_slice(&MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&self.program_counter.to_le_bytes());
+        out.extend_from_slice(&self.i_register.to_le_bytes());
+        out.extend_from_slice(&self.stack_pointer.to_le_bytes());
+        out.push(self.delay_timer);
+        out.push(self.sound_timer);
+        out.extend_from_slice(&self.v_registers);
+        out.push(self.variant.id());
+        out.extend_from_slice(&(self.display_width as u16).to_le_bytes());
+        out.extend_from_slice(&(self.display_height as u16).to_le_bytes());
+        out.extend_from_slice(&self.rpl_flags);
+        out.push(self.plane_mask);
+        out.push(self.pitch);
+        out.extend_from_slice(&self.audio_pattern);
+        out.extend_from_slice(&(self.ram.len() as u32).to_le_bytes());
+        #[cfg(feature = "megachip")]
+        let mega = self.variant == Variant::MegaChip;
+        #[cfg(not(feature = "megachip"))]
+        let mega = false;
+        out.push(mega as u8);
+        #[cfg(feature = "megachip")]
+        if mega {
+            self.save_megachip(out);
+        }
+        if self.variant == Variant::Chip8X {
+            self.save_chip8x(out);
+        }
+        self.save_execution(out);
+        out.extend_from_slice(&(self.stack.len() as u16).to_le_bytes());
+        out.extend_from_slice(&self.stack_high_water.to_le_bytes());
+        for entry in self.stack.iter() {
+            out.extend_from_slice(&entry.to_le_bytes());
+        }
+
+        let keys = (0..NUM_KEYS)
+            .filter(|&idx| self.keys[idx])
+            .fold(0u16, |mask, idx| mask | (1 << idx));
+        out.extend_from_slice(&keys.to_le_bytes());
+        out.extend_from_slice(&self.frame_count.to_le_bytes());
+        out.extend_from_slice(&self.ram);
+
+        for plane in [&self.display[..], &self.second_plane[..]] {
+            for chunk in plane.chunks(8) {
+                let byte = chunk
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &on)| on)
+                    .fold(0u8, |byte, (bit, _)| byte | (0b1000_0000 >> bit));
+                out.push(byte);
+            }
+        }
+    }
+
+    pub fn restore_from(&mut self, buf: &StateBuf) -> Result<(), StateError> {
+        self.load_state(buf.as_bytes())
+    }
+
+    // Serializes the whole machine into a fresh buffer. Hosts saving every
+    // frame should prefer `save_into` with a reused `StateBuf`.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut buf = StateBuf::new();
+        self.save_into(&mut buf);
+        buf.into_bytes()
+    }
+
+    // Restores a state written by `save_state` or `save_into`, including ones
+    // saved by older versions of the crate. Fields an older state doesn't
+    // have take the values a freshly created machine starts with.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        let mut reader = Reader { data, pos: 0 };
+
+        if reader.bytes(MAGIC.len())? != MAGIC {
+            return Err(StateError::BadMagic);
+        }
+        let version = reader.u16()?;
+        if !(OLDEST_VERSION..=VERSION).contains(&version) {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+
+        let program_counter = reader.u16()?;
+        let i_register = reader.u16()?;
+        let stack_pointer = reader.u16()?;
+        let delay_timer = reader.u8()?;
+        let sound_timer = reader.u8()?;
+        let v_registers = reader.bytes(NUM_REGISTERS)?;
+
+        let mut variant = Variant::Chip8;
+        let mut display_width = DISPLAY_WIDTH;
+        let mut display_height = DISPLAY_HEIGHT;
+        let mut rpl_flags = [0; NUM_RPL_FLAGS];
+        if version >= 3 {
+            variant = Variant::from_id(reader.u8()?).ok_or(StateError::Corrupt)?;
+            display_width = reader.u16()? as usize;
+            display_height = reader.u16()? as usize;
+            // Version 3 predates XO-CHIP's extra eight flags
+            let count = if version == 3 { 8 } else { NUM_RPL_FLAGS };
+            rpl_flags[..count].copy_from_slice(reader.bytes(count)?);
+        }
+
+        let mut plane_mask = 1;
+        let mut pitch = DEFAULT_PITCH;
+        let mut audio_pattern = [0; AUDIO_PATTERN_SIZE];
+        let mut ram_len = RAM_SIZE;
+        let mut planes = 1;
+        if version >= 4 {
+            plane_mask = reader.u8()? & 0b11;
+            pitch = reader.u8()?;
+            audio_pattern.copy_from_slice(reader.bytes(AUDIO_PATTERN_SIZE)?);
+            ram_len = reader.u32()? as usize;
+            planes = 2;
+        }
+
+        // Version 5 adds the extra hardware of MEGA-CHIP and CHIP-8X, saved
+        // only while running that variant. First MEGA-CHIP's registers,
+        // palette and frame buffers, behind a flag...
+        #[cfg(feature = "megachip")]
+        let mut mega = None;
+        if version >= 5 {
+            match reader.u8()? {
+                0 => (),
+                #[cfg(feature = "megachip")]
+                1 => mega = Some(read_megachip(&mut reader)?),
+                _ => return Err(StateError::Corrupt),
+            }
+        }
+        // ...then CHIP-8X's color zones, background, tone and second keypad.
+        let mut chip8x = Chip8x::default();
+        if version >= 5 && variant == Variant::Chip8X {
+            chip8x = read_chip8x(&mut reader)?;
+        }
+
+        // Version 6 adds what an instruction in flight leaves behind: the
+        // random generator, key waits, the display wait and halts.
+        let execution = if version >= 6 {
+            read_execution(&mut reader)?
+        } else {
+            Execution::default()
+        };
+
+        let (stack_depth, stack_high_water) = if version >= 2 {
+            (reader.u16()? as usize, reader.u16()?)
+        } else {
+            (DEFAULT_STACK_DEPTH, stack_pointer)
+        };
+
+        // Everything after the stack depth has a fixed size, so the whole
+        // state can be validated before any of it is applied.
+        let display_len = display_width * display_height;
+        if !variant.has_display_mode(display_width, display_height)
+            || !self.fits_ram_size(ram_len)
+            || ram_len > variant.max_ram_size()
+            || stack_depth == 0
+            || stack_pointer as usize > stack_depth
+        {
+            return Err(StateError::Corrupt);
+        }
+        #[cfg(feature = "megachip")]
+        if let Some(mega) = &mega {
+            let mega_display = (MEGA_DISPLAY_WIDTH, MEGA_DISPLAY_HEIGHT);
+            if variant != Variant::MegaChip
+                || mega.enabled != ((display_width, display_height) == mega_display)
+                || mega
+                    .sound
+                    .is_some_and(|sound| sound.address + sound.len > ram_len)
+            {
+                return Err(StateError::Corrupt);
+            }
+        } else if version >= 5 && variant == Variant::MegaChip {
+            return Err(StateError::Corrupt);
+        }
+        let expected = reader.pos
+            + stack_depth * 2
+            + 2 // keys
+            + 8 // frame count
+            + ram_len
+            + display_len.div_ceil(8) * planes;
+        if data.len() < expected {
+            return Err(StateError::Truncated {
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        self.program_counter = program_counter;
+        self.i_register = i_register;
+        self.stack_pointer = stack_pointer;
+        self.delay_timer = delay_timer;
+        self.sound_timer = sound_timer;
+        self.v_registers.copy_from_slice(v_registers);
+        self.variant = variant;
+        self.rpl_flags = rpl_flags;
+        self.plane_mask = plane_mask;
+        self.pitch = pitch;
+        self.audio_pattern = audio_pattern;
+        self.stack_high_water = stack_high_water;
+        #[cfg(feature = "megachip")]
+        {
+            self.mega = mega.unwrap_or_default();
+        }
+        self.chip8x = chip8x;
+        self.apply_execution(&execution);
+        self.stack.clear();
+        for _ in 0..stack_depth {
+            self.stack.push(reader.u16()?);
+        }
+
+        let keys = reader.u16()?;
+        for (idx, key) in self.keys.iter_mut().enumerate() {
+            *key = keys & (1 << idx) != 0;
+        }
+        self.frame_count = reader.u64()?;
+        self.ram.clear();
+        self.ram.extend_from_slice(reader.bytes(ram_len)?);
+
+        let packed = reader.bytes(display_len.div_ceil(8))?;
+        self.display_width = display_width;
+        self.display_height = display_height;
+        let display = match Arc::get_mut(&mut self.display) {
+            Some(display) if display.len() == display_len => display,
+            _ => {
+                self.display = Arc::new(vec![false; display_len]);
+                Arc::get_mut(&mut self.display).unwrap()
+            }
+        };
+        for (idx, pixel) in display.iter_mut().enumerate() {
+            *pixel = packed[idx / 8] & (0b1000_0000 >> (idx % 8)) != 0;
+        }
+        self.second_plane.clear();
+        self.second_plane.resize(display_len, false);
+        if planes == 2 {
+            let packed = reader.bytes(display_len.div_ceil(8))?;
+            for (idx, pixel) in self.second_plane.iter_mut().enumerate() {
+                *pixel = packed[idx / 8] & (0b1000_0000 >> (idx % 8)) != 0;
+            }
+        }
+        self.mark_all_rows_dirty();
+        self.publish_display();
+        self.update_sound();
+        self.publish_timers();
+
+        Ok(())
+    }
+}
+
+// The execution block of a version 6 state. Older states restore to a
+// machine that isn't waiting on anything and keeps its random generator.
+#[derive(Debug, Default)]
+struct Execution {
+    random: Option<u64>,
+    waiting_for_key: bool,
+    sound_on: bool,
+    key_wait: Option<Key>,
+    key_latch: KeyLatch,
+    display_wait: Option<u64>,
+    halted: Option<HaltReason>,
+}
+
+impl Hachi {
+    fn save_execution(&self, out: &mut Vec<u8>) {
+        let random = self.random.saved_state();
+        out.push(random.is_some() as u8);
+        out.extend_from_slice(&random.unwrap_or(0).to_le_bytes());
+        let flags = self.waiting_for_key as u8
+            | (self.sound_on as u8) << 1
+            | (self.display_wait.is_some() as u8) << 2
+            | (self.key_latch.observed as u8) << 3
+            | (self.key_latch.released as u8) << 4;
+        out.push(flags);
+        out.push(self.key_wait.map_or(NO_KEY, |key| key.index() as u8));
+        out.push(self.key_latch.key.map_or(NO_KEY, |key| key.index() as u8));
+        out.extend_from_slice(&self.display_wait.unwrap_or(0).to_le_bytes());
+        let (kind, pc) = match self.halted {
+            None => (0, 0),
+            Some(HaltReason::FellOffProgram { pc }) => (1, pc),
+            Some(HaltReason::Exited { pc }) => (2, pc),
+            Some(HaltReason::SelfJump { pc }) => (3, pc),
+        };
+        out.push(kind);
+        out.extend_from_slice(&pc.to_le_bytes());
+    }
+
+    fn apply_execution(&mut self, execution: &Execution) {
+        if let Some(state) = execution.random {
+            self.restore_random(state);
+        }
+        self.waiting_for_key = execution.waiting_for_key;
+        self.sound_on = execution.sound_on;
+        self.key_wait = execution.key_wait;
+        self.key_latch = execution.key_latch;
+        self.display_wait = execution.display_wait;
+        self.halted = execution.halted;
+    }
+}
+
+fn read_execution(reader: &mut Reader) -> Result<Execution, StateError> {
+    let has_random = reader.u8()?;
+    let random = reader.u64()?;
+    let flags = reader.u8()?;
+    let key_wait = read_key(reader.u8()?)?;
+    let latched = read_key(reader.u8()?)?;
+    let display_wait = reader.u64()?;
+    let kind = reader.u8()?;
+    let pc = reader.u16()?;
+    if has_random > 1 || flags >> 5 != 0 {
+        return Err(StateError::Corrupt);
+    }
+    let halted = match kind {
+        0 => None,
+        1 => Some(HaltReason::FellOffProgram { pc }),
+        2 => Some(HaltReason::Exited { pc }),
+        3 => Some(HaltReason::SelfJump { pc }),
+        _ => return Err(StateError::Corrupt),
+    };
+    Ok(Execution {
+        random: (has_random == 1).then_some(random),
+        waiting_for_key: flags & 1 != 0,
+        sound_on: flags & 0b10 != 0,
+        key_wait,
+        key_latch: KeyLatch {
+            key: latched,
+            observed: flags & 0b1000 != 0,
+            released: flags & 0b1_0000 != 0,
+        },
+        display_wait: (flags & 0b100 != 0).then_some(display_wait),
+        halted,
+    })
+}
+
+fn read_key(byte: u8) -> Result<Option<Key>, StateError> {
+    match byte {
+        NO_KEY => Ok(None),
+        idx => Key::from_index(idx as usize)
+            .map(Some)
+            .ok_or(StateError::Corrupt),
+    }
+}
+
+impl Hachi {
+    fn save_chip8x(&self, out: &mut Vec<u8>) {
+        let chip8x = &self.chip8x;
+        out.push(chip8x.background as u8);
+        out.push(chip8x.tone);
+        let keys = (0..NUM_KEYS)
+            .filter(|&idx| chip8x.keys2[idx])
+            .fold(0u16, |mask, idx| mask | (1 << idx));
+        out.extend_from_slice(&keys.to_le_bytes());
+        out.extend(chip8x.zones.iter().map(|color| color.bits()));
+    }
+}
+
+fn read_chip8x(reader: &mut Reader) -> Result<Chip8x, StateError> {
+    let background = reader.u8()? as usize;
+    if background >= BACKGROUNDS.len() {
+        return Err(StateError::Corrupt);
+    }
+    let tone = reader.u8()?;
+    let keys = reader.u16()?;
+    let mut chip8x = Chip8x {
+        background,
+        tone,
+        ..Chip8x::default()
+    };
+    for (idx, key) in chip8x.keys2.iter_mut().enumerate() {
+        *key = keys & (1 << idx) != 0;
+    }
+    for (zone, &bits) in chip8x.zones.iter_mut().zip(reader.bytes(NUM_ZONES)?) {
+        *zone = Chip8xColor::from_bits(bits);
+    }
+    Ok(chip8x)
+}
+
+#[cfg(feature = "megachip")]
+impl Hachi {
+    fn save_megachip(&self, out: &mut Vec<u8>) {
+        let mega = &self.mega;
+        out.push(mega.enabled as u8);
+        out.push(mega.i_high);
+        for color in mega.palette {
+            out.extend_from_slice(&color.to_le_bytes());
+        }
+        out.extend_from_slice(&(mega.sprite_width as u16).to_le_bytes());
+        out.extend_from_slice(&(mega.sprite_height as u16).to_le_bytes());
+        out.push(mega.blend.nibble());
+        out.push(mega.collision_index);
+        out.push(mega.alpha);
+        match mega.sound {
+            None => out.push(0),
+            Some(sound) => {
+                out.push(if sound.looping { 2 } else { 1 });
+                out.extend_from_slice(&(sound.address as u32).to_le_bytes());
+                out.extend_from_slice(&(sound.len as u32).to_le_bytes());
+                out.extend_from_slice(&sound.sample_rate.to_le_bytes());
+            }
+        }
+        // The frame buffers only exist in color mode
+        if mega.enabled {
+            out.extend_from_slice(&mega.indices);
+            for buffer in [&mega.back, &mega.front] {
+                for color in buffer.iter() {
+                    out.extend_from_slice(&color.to_le_bytes());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "megachip")]
+fn read_megachip(reader: &mut Reader) -> Result<MegaChip, StateError> {
+    let enabled = match reader.u8()? {
+        0 => false,
+        1 => true,
+        _ => return Err(StateError::Corrupt),
+    };
+    let i_high = reader.u8()?;
+    let mut palette = [0; 256];
+    for color in palette.iter_mut() {
+        *color = reader.u32()?;
+    }
+    let sprite_width = reader.u16()? as usize;
+    let sprite_height = reader.u16()? as usize;
+    if !(1..=256).contains(&sprite_width) || !(1..=256).contains(&sprite_height) {
+        return Err(StateError::Corrupt);
+    }
+    let blend = BlendMode::from_nibble(reader.u8()? as u16);
+    let collision_index = reader.u8()?;
+    let alpha = reader.u8()?;
+    let sound = match reader.u8()? {
+        0 => None,
+        kind @ (1 | 2) => Some(MegaSound {
+            address: reader.u32()? as usize,
+            len: reader.u32()? as usize,
+            sample_rate: reader.u16()?,
+            looping: kind == 2,
+        }),
+        _ => return Err(StateError::Corrupt),
+    };
+
+    let mut mega = MegaChip {
+        enabled,
+        i_high,
+        palette,
+        sprite_width,
+        sprite_height,
+        blend,
+        collision_index,
+        alpha,
+        sound,
+        ..MegaChip::default()
+    };
+    if enabled {
+        let len = MEGA_DISPLAY_WIDTH * MEGA_DISPLAY_HEIGHT;
+        mega.indices = reader.bytes(len)?.to_vec();
+        mega.back = (0..len).map(|_| reader.u32()).collect::<Result<_, _>>()?;
+        mega.front = (0..len).map(|_| reader.u32()).collect::<Result<_, _>>()?;
+    }
+    Ok(mega)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or(StateError::Truncated {
+                expected: self.pos + len,
+                actual: self.data.len(),
+            })?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, StateError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, StateError> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.bytes(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn u64(&mut self) -> Result<u64, StateError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+
+    const SP_AT: usize = HEADER_SIZE + 4;
+    const DISPLAY_SIZE_AT: usize = HEADER_SIZE + 8 + NUM_REGISTERS + 1;
+    const RAM_LEN_AT: usize =
+        HEADER_SIZE + 8 + NUM_REGISTERS + 5 + NUM_RPL_FLAGS + 2 + AUDIO_PATTERN_SIZE;
+    const EXECUTION_AT: usize = RAM_LEN_AT + 5;
+    const STACK_DEPTH_AT: usize = EXECUTION_AT + EXECUTION_SIZE;
+
+    fn running_machine() -> Hachi {
+        let rom = assemble(
+            "
+            : main
+                v0 := 7
+                delay := v0
+                i := 0x300
+                sprite v0 v0 5
+                count
+            : count
+                v1 += 1
+                jump count
+            ",
+        )
+        .unwrap();
+        let mut hachi = Hachi::new();
+        hachi.load(&rom).unwrap();
+        hachi.tick_n(40).unwrap();
+        hachi
+    }
+
+    #[test]
+    fn restores_what_it_saved() {
+        let hachi = running_machine();
+        let state = hachi.save_state();
+
+        let mut restored = Hachi::new();
+        restored.load_state(&state).unwrap();
+        assert_eq!(restored.save_state(), state);
+        assert_eq!(restored.get_display(), hachi.get_display());
+        assert_eq!(restored.stack_pointer, 1);
+    }
+
+    #[test]
+    fn restores_chip8x_hardware() {
+        let mut hachi = Hachi::new();
+        hachi.set_variant(Variant::Chip8X);
+        hachi.chip8x.background = 2;
+        hachi.chip8x.tone = 0x40;
+        hachi.chip8x.keys2[5] = true;
+        hachi.chip8x.zones[17] = Chip8xColor::from_bits(3);
+        let state = hachi.save_state();
+
+        let mut restored = Hachi::new();
+        restored.load_state(&state).unwrap();
+        let chip8x = &restored.chip8x;
+        assert_eq!(chip8x.background, 2);
+        assert_eq!(chip8x.tone, 0x40);
+        assert_eq!(chip8x.keys2, hachi.chip8x.keys2);
+        assert_eq!(chip8x.zones, hachi.chip8x.zones);
+    }
+
+    #[cfg(feature = "megachip")]
+    #[test]
+    fn restores_megachip_hardware() {
+        let mut hachi = Hachi::new();
+        hachi.set_variant(Variant::MegaChip);
+        hachi.set_megachip_mode(true);
+        hachi.mega.i_high = 0x12;
+        hachi.mega.alpha = 0x80;
+        hachi.mega.palette[3] = 0xFF00_FF00;
+        hachi.ram[0x1_0000] = 0xAB;
+        let state = hachi.save_state();
+
+        let mut restored = Hachi::new();
+        restored.load_state(&state).unwrap();
+        assert_eq!(restored.save_state(), state);
+        assert_eq!(restored.ram.len(), hachi.ram.len());
+        assert_eq!(restored.mega.palette[3], 0xFF00_FF00);
+    }
+
+    #[test]
+    fn restores_the_generator_and_pending_key_wait() {
+        let rom = assemble(
+            "
+            : main
+                v0 := random 0xFF
+                v1 := key
+                v2 := random 0xFF
+                v3 := random 0xFF
+            ",
+        )
+        .unwrap();
+        let mut original = Hachi::new_with_seed(7);
+        original.load(&rom).unwrap();
+        original.tick_n(2).unwrap();
+        let state = original.save_state();
+
+        // A machine with an unseeded source picks up the saved generator
+        let mut restored = Hachi::new();
+        restored.load_state(&state).unwrap();
+        assert_eq!(restored.save_state(), state);
+
+        for hachi in [&mut original, &mut restored] {
+            hachi.keypress(Key::Key3, true);
+            hachi.tick().unwrap();
+            hachi.keypress(Key::Key3, false);
+            hachi.tick_n(3).unwrap();
+        }
+        assert_eq!(restored.registers(), original.registers());
+        assert_eq!(restored.save_state(), original.save_state());
+    }
+
+    #[test]
+    fn rejects_foreign_and_future_data() {
+        let mut hachi = Hachi::new();
+        assert_eq!(hachi.load_state(b"NOPE\x05\x00"), Err(StateError::BadMagic));
+
+        let mut state = hachi.save_state();
+        state[MAGIC.len()..HEADER_SIZE].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        assert_eq!(
+            hachi.load_state(&state),
+            Err(StateError::UnsupportedVersion(VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_state() {
+        let state = running_machine().save_state();
+        let mut hachi = Hachi::new();
+        assert!(matches!(
+            hachi.load_state(&state[..state.len() - 1]),
+            Err(StateError::Truncated { .. })
+        ));
+        assert!(matches!(
+            hachi.load_state(&state[..SP_AT]),
+            Err(StateError::Truncated { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_corrupt_fields_without_applying_any() {
+        let state = running_machine().save_state();
+        let corruptions: [(usize, &[u8]); 8] = [
+            (STACK_DEPTH_AT, &0u16.to_le_bytes()),
+            (SP_AT, &(DEFAULT_STACK_DEPTH as u16 + 1).to_le_bytes()),
+            (RAM_LEN_AT, &(RAM_SIZE as u32 * 64).to_le_bytes()),
+            (RAM_LEN_AT, &16u32.to_le_bytes()),
+            // 128x16 is no mode at all, and plain CHIP-8 has no 128x64
+            (DISPLAY_SIZE_AT, &[128, 0, 16, 0]),
+            (DISPLAY_SIZE_AT, &[128, 0, 64, 0]),
+            (EXECUTION_AT + 10, &[NUM_KEYS as u8]),
+            (EXECUTION_AT + 20, &[4]),
+        ];
+
+        for (at, bytes) in corruptions {
+            let mut corrupt = state.clone();
+            corrupt[at..at + bytes.len()].copy_from_slice(bytes);
+            let mut hachi = running_machine();
+            hachi.tick_n(3).unwrap();
+            let before = hachi.save_state();
+            assert_eq!(hachi.load_state(&corrupt), Err(StateError::Corrupt));
+            assert_eq!(hachi.save_state(), before);
+        }
+    }
+}
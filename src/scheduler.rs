@@ -0,0 +1,128 @@
+use crate::instruction::Instruction;
+use crate::{FrameSummary, Hachi, HachiError, RunOutcome};
+
+// Machine cycles (eight clocks of the 1.76 MHz CDP1802) the VIP has for
+// the interpreter each 60 Hz frame: 3668 in all, less what the display DMA
+// and the interrupt routine take.
+const VIP_CYCLES_PER_FRAME: i32 = 2572;
+
+// Fetching and dispatching any instruction.
+const VIP_FETCH_CYCLES: u32 = 40;
+
+// How `run_scheduled_frame` decides how much to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimingMode {
+    // A fixed instruction count per frame, or the clock rate if one is set.
+    #[default]
+    Flat,
+    // Each instruction costs roughly what it took on the COSMAC VIP, so a
+    // sprite draw is worth dozens of register moves. Games tuned on the
+    // original hardware run at the speed their authors saw.
+    Vip,
+}
+
+impl Hachi {
+    // Runs the CPU at `hz` instructions per second instead of a fixed count
+    // per frame. Rates that don't divide evenly by the timer frequency
+    // spread the remainder across frames, so 500 Hz at 60 Hz runs two frames
+    // of 8 instructions for every one of 9 and averages out exactly.
+    pub fn set_instructions_per_second(&mut self, hz: u32) {
+        self.clock_hz = Some(hz.max(1));
+        self.cycle_accumulator.clear();
+    }
+
+    // The configured clock rate, if one was set, as opposed to
+    // `instructions_per_second`, which also covers a fixed per-frame count.
+    pub fn clock_speed(&self) -> Option<u32> {
+        self.clock_hz
+    }
+
+    pub fn timing_mode(&self) -> TimingMode {
+        self.timing_mode
+    }
+
+    pub fn set_timing_mode(&mut self, mode: TimingMode) {
+        self.timing_mode = mode;
+        self.vip_cycles = 0;
+    }
+
+    // How many instructions the next frame should run. With a clock rate
+    // set this consumes the carried remainder, so call it once per frame.
+    // Not meaningful under `TimingMode::Vip`, where the count depends on
+    // which instructions run.
+    pub fn next_frame_cycles(&mut self) -> u32 {
+        match self.clock_hz {
+            Some(hz) => self.cycle_accumulator.add(hz as u64, self.timer_hz as u64) as u32,
+            None => self.instructions_per_frame,
+        }
+    }
+
+    // `run_frame` with the amount of work chosen by the scheduler.
+    pub fn run_scheduled_frame(&mut self) -> FrameSummary {
+        match self.timing_mode {
+            TimingMode::Flat => {
+                let cycles = self.next_frame_cycles();
+                self.run_frame(cycles)
+            }
+            TimingMode::Vip => self.run_frame_with(Self::run_vip_cycles),
+        }
+    }
+
+    // Spends one frame's worth of VIP machine cycles. The instruction that
+    // crosses the budget still runs in full and the overdraft comes out of
+    // the next frame, the way a long draw delays the interpreter on the
+    // real machine.
+    fn run_vip_cycles(&mut self) -> Result<RunOutcome, HachiError> {
+        self.vip_cycles = (self.vip_cycles + VIP_CYCLES_PER_FRAME).min(VIP_CYCLES_PER_FRAME);
+
+        while self.vip_cycles > 0 {
+            let cost = self.peek_opcode().map_or(VIP_FETCH_CYCLES, vip_cost);
+            match self.tick_n(1)? {
+                RunOutcome::Completed => self.vip_cycles -= cost as i32,
+                outcome => return Ok(outcome),
+            }
+        }
+
+        Ok(RunOutcome::Completed)
+    }
+}
+
+// Approximate machine cycles for one instruction in the VIP interpreter.
+// Draws depend on the sprite height, and FX55/FX65 on how many registers
+// they move; the exact cost of a draw also varies with the X offset, which
+// this averages out.
+fn vip_cost(op: u16) -> u32 {
+    let extra = match Instruction::decode(op) {
+        Instruction::ClearScreen => 1200,
+        Instruction::Return => 10,
+        Instruction::Jump { .. } => 12,
+        Instruction::Call { .. } => 26,
+        Instruction::SkipEqImm { .. } | Instruction::SkipNeImm { .. } => 14,
+        Instruction::SkipEqReg { .. } | Instruction::SkipNeReg { .. } => 18,
+        Instruction::SetImm { .. } => 6,
+        Instruction::AddImm { .. } => 10,
+        Instruction::Set { .. }
+        | Instruction::Or { .. }
+        | Instruction::And { .. }
+        | Instruction::Xor { .. }
+        | Instruction::Add { .. }
+        | Instruction::Sub { .. }
+        | Instruction::ShiftRight { .. }
+        | Instruction::SubReversed { .. }
+        | Instruction::ShiftLeft { .. } => 44,
+        Instruction::SetI { .. } => 12,
+        Instruction::JumpOffset { .. } => 22,
+        Instruction::Random { .. } => 36,
+        Instruction::Draw { n, .. } => 100 + 90 * n as u32,
+        Instruction::SkipKey { .. } | Instruction::SkipNotKey { .. } => 14,
+        Instruction::GetDelay { .. }
+        | Instruction::SetDelay { .. }
+        | Instruction::SetSound { .. } => 10,
+        Instruction::WaitKey { .. } => 20,
+        Instruction::AddI { .. } | Instruction::FontChar { .. } => 16,
+        Instruction::Bcd { .. } => 130,
+        Instruction::Store { x } | Instruction::Load { x } => 14 + 14 * (x as u32 + 1),
+        _ => 10,
+    };
+    VIP_FETCH_CYCLES + extra
+}
@@ -0,0 +1,190 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::shared::Arc;
+use crate::{Hachi, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+
+pub const HIRES_DISPLAY_WIDTH: usize = 128;
+pub const HIRES_DISPLAY_HEIGHT: usize = 64;
+
+// SUPER-CHIP has 8 flags, XO-CHIP extends them to 16.
+pub(crate) const NUM_RPL_FLAGS: usize = 16;
+
+// The resolution the display is in, for frontends choosing how to scale
+// it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayMode {
+    // 64x32, the CHIP-8 screen.
+    LowRes,
+    // SUPER-CHIP's 128x64, entered with 00FF.
+    HighRes,
+    // The VIP two-page hi-res CHIP-8's 64x64.
+    TwoPage,
+    // MEGA-CHIP's 256x192 color mode, entered with 0011.
+    #[cfg(feature = "megachip")]
+    Mega,
+}
+
+// The 8x10 digits FX30 points I at, stored straight after the small font.
+pub(crate) const BIG_FONT_ADDRESS: usize = 0x50;
+pub(crate) const BIG_FONTSET: [u8; 160] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+impl Hachi {
+    pub fn is_hires(&self) -> bool {
+        self.display_width == HIRES_DISPLAY_WIDTH
+    }
+
+    pub fn display_mode(&self) -> DisplayMode {
+        #[cfg(feature = "megachip")]
+        if self.is_megachip_mode() {
+            return DisplayMode::Mega;
+        }
+        if self.is_hires() {
+            DisplayMode::HighRes
+        } else if self.is_two_page_hires() {
+            DisplayMode::TwoPage
+        } else {
+            DisplayMode::LowRes
+        }
+    }
+
+    // The HP-48 RPL flags FX75 and FX85 save to and restore from.
+    pub fn rpl_flags(&self) -> [u8; NUM_RPL_FLAGS] {
+        self.rpl_flags
+    }
+
+    pub fn set_rpl_flags(&mut self, flags: [u8; NUM_RPL_FLAGS]) {
+        self.rpl_flags = flags;
+    }
+
+    pub(crate) fn rpl_flag_count(&self) -> usize {
+        if self.variant.has_xochip_opcodes() {
+            NUM_RPL_FLAGS
+        } else {
+            8
+        }
+    }
+
+    // 00FE and 00FF. Modern SUPER-CHIP clears the screen; with the
+    // `resolution_switch_preserves` quirk the picture is kept, each low-res
+    // pixel becoming a 2x2 block as on the HP 48, where both modes share
+    // one 128x64 buffer.
+    pub(crate) fn switch_resolution(&mut self, hires: bool) {
+        if !self.quirks.resolution_switch_preserves {
+            self.set_hires(hires);
+            return;
+        }
+
+        let (old_width, old_height) = (self.display_width, self.display_height);
+        let old = [self.display.to_vec(), self.second_plane.clone()];
+        self.set_hires(hires);
+
+        let (width, height) = (self.display_width, self.display_height);
+        if (width, height) == (old_width, old_height) {
+            return;
+        }
+        let scale = |plane: &[bool]| -> Vec<bool> {
+            (0..width * height)
+                .map(|idx| {
+                    let x = idx % width * old_width / width;
+                    let y = idx / width * old_height / height;
+                    plane[x + old_width * y]
+                })
+                .collect()
+        };
+        self.display = Arc::new(scale(&old[0]));
+        self.second_plane = scale(&old[1]);
+        self.publish_display();
+    }
+
+    // Switches resolution, clearing the screen.
+    pub(crate) fn set_hires(&mut self, hires: bool) {
+        let (width, height) = if hires {
+            (HIRES_DISPLAY_WIDTH, HIRES_DISPLAY_HEIGHT)
+        } else {
+            (DISPLAY_WIDTH, DISPLAY_HEIGHT)
+        };
+        self.resize_display(width, height);
+    }
+
+    // Replaces both planes with blank ones of the given size.
+    pub(crate) fn resize_display(&mut self, width: usize, height: usize) {
+        if (width, height) == (self.display_width, self.display_height) {
+            return;
+        }
+
+        self.display_width = width;
+        self.display_height = height;
+        self.display = Arc::new(vec![false; width * height]);
+        self.second_plane = vec![false; width * height];
+        self.mark_all_rows_dirty();
+        self.publish_display();
+    }
+
+    // 00CN: moves the picture down `rows` pixels, blanking the rows that
+    // scroll in at the top.
+    pub(crate) fn scroll_down(&mut self, rows: usize) {
+        let shift = rows * self.display_width;
+        self.for_each_selected_plane(|plane| {
+            let len = plane.len();
+            let shift = shift.min(len);
+            plane.copy_within(..len - shift, shift);
+            plane[..shift].fill(false);
+        });
+    }
+
+    // 00FB and 00FC: moves the picture `cols` pixels sideways. Negative
+    // values scroll left.
+    pub(crate) fn scroll_sideways(&mut self, cols: isize) {
+        let width = self.display_width;
+        let shift = cols.unsigned_abs().min(width);
+
+        self.for_each_selected_plane(|plane| {
+            for row in plane.chunks_mut(width) {
+                if cols > 0 {
+                    row.copy_within(..width - shift, shift);
+                    row[..shift].fill(false);
+                } else {
+                    row.copy_within(shift.., 0);
+                    row[width - shift..].fill(false);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Variant;
+
+    #[test]
+    fn points_i_at_the_big_digit() {
+        // v0 := 7, i := bighex v0
+        let mut hachi = Hachi::new();
+        hachi.set_variant(Variant::SuperChip);
+        hachi.load(&[0x60, 0x07, 0xF0, 0x30]).unwrap();
+        hachi.tick_n(2).unwrap();
+
+        let i = hachi.i() as usize;
+        assert_eq!(i, BIG_FONT_ADDRESS + 70);
+        assert_eq!(hachi.ram()[i..i + 10], BIG_FONTSET[70..80]);
+    }
+}
@@ -0,0 +1,86 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Deref;
+
+use crate::Hachi;
+
+// Cortex-M0 parts like the RP2040 have no compare-and-swap, so `Arc` doesn't
+// exist there. Nothing can be sent between threads on those targets anyway,
+// and `Rc` has the same copy-on-write API.
+#[cfg(not(target_has_atomic = "ptr"))]
+pub(crate) use alloc::rc::Rc as Arc;
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) use alloc::sync::Arc;
+
+// Row-major, `display_width * display_height` pixels.
+pub(crate) type DisplayBuffer = Vec<bool>;
+
+// A read-only handle on the display that can be sent to a render or audio
+// thread. Taking one only bumps a reference count; the core copies the
+// buffer lazily the next time it draws while a handle is still alive, so a
+// frontend that drops its handle before the next frame never pays for a
+// copy at all.
+#[derive(Debug, Clone)]
+pub struct SharedDisplay {
+    pixels: Arc<DisplayBuffer>,
+    width: usize,
+    height: usize,
+    frame_count: u64,
+}
+
+impl SharedDisplay {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn pixels(&self) -> &[bool] {
+        &self.pixels[..]
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.pixels[x + self.width * y]
+    }
+
+    // Whether two handles point at the very same buffer, i.e. nothing was
+    // drawn between taking them.
+    pub fn ptr_eq(&self, other: &SharedDisplay) -> bool {
+        Arc::ptr_eq(&self.pixels, &other.pixels)
+    }
+}
+
+impl Deref for SharedDisplay {
+    type Target = [bool];
+
+    fn deref(&self) -> &[bool] {
+        self.pixels()
+    }
+}
+
+impl Hachi {
+    pub fn shared_display(&self) -> SharedDisplay {
+        SharedDisplay {
+            pixels: Arc::clone(&self.display),
+            width: self.display_width,
+            height: self.display_height,
+            frame_count: self.frame_count,
+        }
+    }
+
+    pub(crate) fn clear_display(&mut self) {
+        match Arc::get_mut(&mut self.display) {
+            Some(display) => display.fill(false),
+            None => self.display = Arc::new(vec![false; self.display.len()]),
+        }
+        self.second_plane.fill(false);
+        self.mark_all_rows_dirty();
+        self.publish_display();
+    }
+}
@@ -0,0 +1,170 @@
+use alloc::vec::Vec;
+
+use crate::{Frame, Hachi, StateBuf, StateError};
+
+const SLOT_MAGIC: [u8; 4] = *b"HSLT";
+const SLOT_VERSION: u8 = 2;
+
+// Where slot data is kept: files, browser localStorage through a callback,
+// flash. Slots are plain byte blobs as far as the storage is concerned.
+pub trait SlotStorage {
+    fn read(&mut self, slot: usize) -> Option<Vec<u8>>;
+
+    fn write(&mut self, slot: usize, data: &[u8]);
+
+    fn delete(&mut self, slot: usize);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlotError {
+    OutOfRange { slot: usize, count: usize },
+    Empty { slot: usize },
+    Corrupt { slot: usize },
+    State(StateError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotInfo {
+    pub slot: usize,
+    // Whatever the host passed to `save`, typically seconds since the epoch.
+    pub timestamp: u64,
+    pub thumbnail: Option<Frame>,
+}
+
+// Manages `count` numbered save slots on top of the save-state format.
+pub struct SaveSlots<S: SlotStorage> {
+    storage: S,
+    count: usize,
+}
+
+impl<S: SlotStorage> SaveSlots<S> {
+    pub fn new(storage: S, count: usize) -> Self {
+        Self { storage, count }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    pub fn into_storage(self) -> S {
+        self.storage
+    }
+
+    pub fn save(
+        &mut self,
+        slot: usize,
+        hachi: &Hachi,
+        timestamp: u64,
+        with_thumbnail: bool,
+    ) -> Result<(), SlotError> {
+        self.check(slot)?;
+
+        let mut state = StateBuf::new();
+        hachi.save_into(&mut state);
+
+        let mut data = Vec::with_capacity(14 + state.as_bytes().len());
+        data.extend_from_slice(&SLOT_MAGIC);
+        data.push(SLOT_VERSION);
+        data.extend_from_slice(&timestamp.to_le_bytes());
+        data.push(with_thumbnail as u8);
+        if with_thumbnail {
+            let thumbnail = Frame::pack(
+                hachi.get_display(),
+                hachi.display_width(),
+                hachi.display_height(),
+                hachi.frame_count,
+                false,
+            );
+            // The thumbnail keeps whatever resolution the game was in
+            data.extend_from_slice(&(thumbnail.width() as u16).to_le_bytes());
+            data.extend_from_slice(&(thumbnail.height() as u16).to_le_bytes());
+            data.extend_from_slice(thumbnail.pixels());
+        }
+        data.extend_from_slice(state.as_bytes());
+
+        self.storage.write(slot, &data);
+        Ok(())
+    }
+
+    pub fn load(&mut self, slot: usize, hachi: &mut Hachi) -> Result<SlotInfo, SlotError> {
+        let data = self.read(slot)?;
+        let (info, state) = decode(slot, &data)?;
+
+        hachi
+            .restore_from(&StateBuf::from_bytes(state.to_vec()))
+            .map_err(SlotError::State)?;
+        Ok(info)
+    }
+
+    pub fn info(&mut self, slot: usize) -> Result<SlotInfo, SlotError> {
+        let data = self.read(slot)?;
+        decode(slot, &data).map(|(info, _)| info)
+    }
+
+    // Information on every occupied slot, in slot order.
+    pub fn list(&mut self) -> Vec<SlotInfo> {
+        (0..self.count)
+            .filter_map(|slot| self.info(slot).ok())
+            .collect()
+    }
+
+    pub fn delete(&mut self, slot: usize) -> Result<(), SlotError> {
+        self.check(slot)?;
+        self.storage.delete(slot);
+        Ok(())
+    }
+
+    fn check(&self, slot: usize) -> Result<(), SlotError> {
+        if slot >= self.count {
+            return Err(SlotError::OutOfRange {
+                slot,
+                count: self.count,
+            });
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, slot: usize) -> Result<Vec<u8>, SlotError> {
+        self.check(slot)?;
+        self.storage.read(slot).ok_or(SlotError::Empty { slot })
+    }
+}
+
+fn decode(slot: usize, data: &[u8]) -> Result<(SlotInfo, &[u8]), SlotError> {
+    let corrupt = SlotError::Corrupt { slot };
+
+    if data.len() < 14 || data[..4] != SLOT_MAGIC || data[4] != SLOT_VERSION {
+        return Err(corrupt);
+    }
+    let mut timestamp = [0; 8];
+    timestamp.copy_from_slice(&data[5..13]);
+    let timestamp = u64::from_le_bytes(timestamp);
+
+    let (thumbnail, rest) = match data[13] {
+        0 => (None, &data[14..]),
+        1 if data.len() >= 18 => {
+            let width = u16::from_le_bytes([data[14], data[15]]) as usize;
+            let height = u16::from_le_bytes([data[16], data[17]]) as usize;
+            let end = 18 + width.div_ceil(8) * height;
+            if data.len() < end {
+                return Err(corrupt);
+            }
+            let frame = Frame::from_packed(width, height, data[18..end].to_vec());
+            (Some(frame), &data[end..])
+        }
+        _ => return Err(corrupt),
+    };
+
+    Ok((
+        SlotInfo {
+            slot,
+            timestamp,
+            thumbnail,
+        },
+        rest,
+    ))
+}
@@ -0,0 +1,46 @@
+use alloc::collections::VecDeque;
+
+use crate::Hachi;
+
+// Only the most recent transitions are kept if the host never collects them.
+const MAX_SOUND_EVENTS: usize = 64;
+
+pub(crate) type SoundEvents = VecDeque<SoundEvent>;
+
+// An edge of the buzzer, queued when the sound timer becomes non-zero or
+// runs out. A beep shorter than a host frame still produces both events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundEvent {
+    Started,
+    Stopped,
+}
+
+impl Hachi {
+    // Takes the oldest transition not yet seen.
+    pub fn next_sound_event(&mut self) -> Option<SoundEvent> {
+        self.sound_events.pop_front()
+    }
+
+    pub fn is_sound_on(&self) -> bool {
+        self.sound_on
+    }
+
+    // Called wherever the sound timer may have changed.
+    pub(crate) fn update_sound(&mut self) {
+        let on = self.sound_timer > 0;
+        if on == self.sound_on {
+            return;
+        }
+
+        self.sound_on = on;
+        self.notify(|observer| observer.on_sound_change(on));
+        if self.sound_events.len() == MAX_SOUND_EVENTS {
+            self.sound_events.pop_front();
+        }
+        self.sound_events.push_back(if on {
+            SoundEvent::Started
+        } else {
+            SoundEvent::Stopped
+        });
+    }
+}
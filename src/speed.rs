@@ -0,0 +1,162 @@
+use alloc::vec;
+
+use crate::{Hachi, Key};
+
+pub const DEFAULT_INSTRUCTIONS_PER_FRAME: u32 = 10;
+
+const MIN_INSTRUCTIONS_PER_FRAME: u32 = 4;
+const MAX_INSTRUCTIONS_PER_FRAME: u32 = 30;
+
+// The profiler runs the ROM flat out at this rate so that draw-bound games
+// show up clearly in the counts.
+const PROBE_INSTRUCTIONS_PER_FRAME: u32 = 30;
+const PROBE_FRAMES: u32 = 60;
+
+// Most CHIP-8 game loops erase and redraw a sprite or two per iteration, and
+// were written for roughly one iteration per 60 Hz frame on the VIP.
+const TARGET_DRAWS_PER_FRAME: f32 = 2.0;
+
+// Games that poll the delay timer pace themselves, so they stay playable at
+// any reasonably fast rate; favour responsiveness for those.
+const TIMER_PACED_INSTRUCTIONS_PER_FRAME: u32 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpeedProfile {
+    pub frames: u32,
+    pub instructions: u32,
+    pub draws: u32,
+    pub timer_polls: u32,
+    pub key_wait_frames: u32,
+    pub suggested_instructions_per_frame: u32,
+}
+
+impl SpeedProfile {
+    fn suggest(&mut self) {
+        let active_frames = self.frames - self.key_wait_frames;
+
+        self.suggested_instructions_per_frame = if active_frames == 0
+            || active_frames < self.frames / 4
+        {
+            // Sat on a key prompt for most of the run, not enough to go on.
+            DEFAULT_INSTRUCTIONS_PER_FRAME
+        } else if self.timer_polls >= active_frames {
+            TIMER_PACED_INSTRUCTIONS_PER_FRAME
+        } else if self.draws == 0 {
+            DEFAULT_INSTRUCTIONS_PER_FRAME
+        } else {
+            let draws_per_frame = self.draws as f32 / active_frames as f32;
+            let scaled =
+                PROBE_INSTRUCTIONS_PER_FRAME as f32 * TARGET_DRAWS_PER_FRAME / draws_per_frame;
+            ((scaled + 0.5) as u32).clamp(MIN_INSTRUCTIONS_PER_FRAME, MAX_INSTRUCTIONS_PER_FRAME)
+        };
+    }
+}
+
+impl Hachi {
+    pub fn instructions_per_frame(&self) -> u32 {
+        self.instructions_per_frame
+    }
+
+    // Also drops any clock rate set with `set_instructions_per_second`.
+    pub fn set_instructions_per_frame(&mut self, ipf: u32) {
+        self.instructions_per_frame = ipf.max(1);
+        self.clock_hz = None;
+    }
+
+    // Runs `rom` for one emulated second on a scratch machine set up like
+    // this one, and measures how it paces itself. Profiling stops early if
+    // the ROM faults.
+    pub fn profile_speed(&self, rom: &[u8]) -> SpeedProfile {
+        let mut probe = self.scratch_machine();
+
+        let mut profile = SpeedProfile {
+            frames: 0,
+            instructions: 0,
+            draws: 0,
+            timer_polls: 0,
+            key_wait_frames: 0,
+            suggested_instructions_per_frame: DEFAULT_INSTRUCTIONS_PER_FRAME,
+        };
+        if probe.load(rom).is_err() {
+            return profile;
+        }
+
+        'frames: for _ in 0..PROBE_FRAMES {
+            let mut waiting_for_key = false;
+            profile.frames += 1;
+
+            for _ in 0..PROBE_INSTRUCTIONS_PER_FRAME {
+                let op = match probe.peek_opcode() {
+                    Some(op) => op,
+                    None => break 'frames,
+                };
+                match op & 0xF0FF {
+                    0xF007 => profile.timer_polls += 1,
+                    0xF00A => waiting_for_key = true,
+                    _ if op & 0xF000 == 0xD000 => profile.draws += 1,
+                    _ => (),
+                }
+
+                if probe.tick().is_err() {
+                    break 'frames;
+                }
+                profile.instructions += 1;
+            }
+
+            if waiting_for_key && Key::ALL.iter().all(|key| !probe.keys[key.index()]) {
+                profile.key_wait_frames += 1;
+            }
+
+            probe.tick_timers();
+        }
+
+        profile.suggest();
+        profile
+    }
+
+    // A fresh machine with this one's variant, quirks, memory layout and
+    // input handling, but none of its state or attachments.
+    fn scratch_machine(&self) -> Hachi {
+        let mut probe = Hachi::new();
+        probe.custom_ram_size = self.custom_ram_size;
+        probe.set_variant(self.variant);
+        probe.start_address = self.start_address;
+        probe.quirks = self.quirks;
+        probe.stack = vec![0; self.stack.len()];
+        probe.input_mode = self.input_mode;
+        probe.zero_opcode_policy = self.zero_opcode_policy;
+        probe.two_page_detection = self.two_page_detection;
+        probe
+    }
+
+    // Profiles `rom` and adopts the suggested instructions-per-frame value.
+    pub fn auto_tune_speed(&mut self, rom: &[u8]) -> u32 {
+        let ipf = self.profile_speed(rom).suggested_instructions_per_frame;
+        self.set_instructions_per_frame(ipf);
+        ipf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Variant;
+
+    // Switches to hi-res, then draws in a loop.
+    const HIRES_ROM: [u8; 6] = [0x00, 0xFF, 0xD0, 0x15, 0x12, 0x02];
+
+    #[test]
+    fn profiles_on_the_callers_variant() {
+        let chip8 = Hachi::new().profile_speed(&HIRES_ROM);
+        assert_eq!(chip8.instructions, 0);
+
+        let mut hachi = Hachi::new();
+        hachi.set_variant(Variant::SuperChip);
+        let schip = hachi.profile_speed(&HIRES_ROM);
+        assert_eq!(
+            schip.instructions,
+            PROBE_FRAMES * PROBE_INSTRUCTIONS_PER_FRAME
+        );
+        assert!(schip.draws > 0);
+    }
+}
@@ -0,0 +1,103 @@
+use alloc::vec::Vec;
+
+use crate::Hachi;
+
+pub const SPRITE_WIDTH: usize = 8;
+
+// Sprite data read straight out of RAM and expanded into pixels, without
+// going through the display.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sprite {
+    addr: u16,
+    rows: Vec<u8>,
+}
+
+impl Sprite {
+    pub fn addr(&self) -> u16 {
+        self.addr
+    }
+
+    pub fn width(&self) -> usize {
+        SPRITE_WIDTH
+    }
+
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    // One byte per row, most significant bit leftmost, as stored in RAM.
+    pub fn rows(&self) -> &[u8] {
+        &self.rows
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < SPRITE_WIDTH
+            && self
+                .rows
+                .get(y)
+                .is_some_and(|row| row & (0b1000_0000 >> x) != 0)
+    }
+
+    // Row-major pixels, `width() * height()` long.
+    pub fn pixels(&self) -> Vec<bool> {
+        self.rows
+            .iter()
+            .flat_map(|row| (0..SPRITE_WIDTH).map(move |x| row & (0b1000_0000 >> x) != 0))
+            .collect()
+    }
+}
+
+impl Hachi {
+    // Renders `rows` bytes starting at `addr` the way DXYN would draw them.
+    // Returns `None` if the range runs past the end of RAM.
+    pub fn render_sprite(&self, addr: u16, rows: usize) -> Option<Sprite> {
+        let start = addr as usize;
+        if start + rows > self.ram.len() {
+            return None;
+        }
+
+        Some(Sprite {
+            addr,
+            rows: self.ram[start..start + rows].to_vec(),
+        })
+    }
+
+    // Renders the sprite DXYN would draw next, i.e. `rows` bytes at I.
+    pub fn render_sprite_at_i(&self, rows: usize) -> Option<Sprite> {
+        self.render_sprite(self.i_register, rows)
+    }
+
+    // Width and height in pixels of the sprite DXYN draws. SUPER-CHIP
+    // draws 16x16, two bytes per row, for N=0; on the HP 48 that became
+    // 8x16 in low resolution.
+    pub(crate) fn sprite_size(&self, n: u8) -> (usize, usize) {
+        if n != 0 || !self.variant.has_schip_opcodes() {
+            (8, n as usize)
+        } else if self.quirks.lores_dxy0_draws_8x16 && !self.is_hires() {
+            (8, 16)
+        } else {
+            (16, 16)
+        }
+    }
+
+    // Whether a DXYN has to keep waiting for the vertical blank under the
+    // display-wait quirk. The first call starts the wait; the draw goes
+    // ahead once the timers have ticked since.
+    pub(crate) fn awaiting_vblank(&mut self) -> bool {
+        if !self.quirks.display_wait {
+            return false;
+        }
+
+        match self.display_wait {
+            Some(frame) if frame != self.frame_count => {
+                self.display_wait = None;
+                false
+            }
+            Some(_) => true,
+            None => {
+                self.display_wait = Some(self.frame_count);
+                true
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use crate::Hachi;
+
+// The stack pointer is 16 bits wide, and so is the depth in save states.
+pub const MAX_STACK_DEPTH: usize = u16::MAX as usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StackStats {
+    // Entries currently on the stack.
+    pub depth: usize,
+    // Maximum number of entries the stack can hold.
+    pub capacity: usize,
+    // Deepest the stack has been since the last reset.
+    pub high_water: usize,
+}
+
+impl Hachi {
+    pub fn stack_depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    // Changes how many nested calls are allowed. The original interpreter
+    // allowed 12 and most later ones 16; some allow many more. Entries that
+    // no longer fit are discarded. Returns false, changing nothing, if
+    // `depth` is over `MAX_STACK_DEPTH`.
+    pub fn set_stack_depth(&mut self, depth: usize) -> bool {
+        if depth > MAX_STACK_DEPTH {
+            return false;
+        }
+
+        let depth = depth.max(1);
+        self.stack.resize(depth, 0);
+        self.stack_pointer = self.stack_pointer.min(depth as u16);
+        true
+    }
+
+    pub fn stack_stats(&self) -> StackStats {
+        StackStats {
+            depth: self.stack_pointer as usize,
+            capacity: self.stack.len(),
+            high_water: self.stack_high_water as usize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BuildError, DEFAULT_STACK_DEPTH};
+
+    #[test]
+    fn rejects_a_depth_the_stack_pointer_cannot_reach() {
+        let mut hachi = Hachi::new();
+        assert!(!hachi.set_stack_depth(MAX_STACK_DEPTH + 1));
+        assert_eq!(hachi.stack_depth(), DEFAULT_STACK_DEPTH);
+        assert!(hachi.set_stack_depth(MAX_STACK_DEPTH));
+        assert_eq!(hachi.stack_depth(), MAX_STACK_DEPTH);
+
+        let depth = MAX_STACK_DEPTH + 1;
+        assert_eq!(
+            Hachi::builder().stack_depth(depth).build().err(),
+            Some(BuildError::StackDepthOutOfRange { depth })
+        );
+    }
+}
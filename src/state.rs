@@ -0,0 +1,149 @@
+use alloc::vec::Vec;
+
+use crate::schip::NUM_RPL_FLAGS;
+use crate::shared::Arc;
+use crate::xochip::AUDIO_PATTERN_SIZE;
+use crate::{Hachi, Quirks, StateError, Variant, NUM_KEYS, NUM_REGISTERS};
+
+// The machine's state as plain data: everything a save state holds plus
+// the quirks, without the host-side attachments (random source,
+// peripherals, storage, trace sinks) that keep `Hachi` itself from being
+// cloned or compared. Tests can snapshot, tick, and compare snapshots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HachiState {
+    pub program_counter: u16,
+    pub i: usize,
+    pub v_registers: [u8; NUM_REGISTERS],
+    pub stack: Vec<u16>,
+    pub stack_pointer: u16,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub variant: Variant,
+    pub quirks: Quirks,
+    pub display_width: usize,
+    pub display_height: usize,
+    pub display: Vec<bool>,
+    pub second_plane: Vec<bool>,
+    pub plane_mask: u8,
+    pub rpl_flags: [u8; NUM_RPL_FLAGS],
+    pub pitch: u8,
+    pub audio_pattern: [u8; AUDIO_PATTERN_SIZE],
+    pub keys: [bool; NUM_KEYS],
+    pub frame_count: u64,
+    pub ram: Vec<u8>,
+}
+
+impl Hachi {
+    pub fn state(&self) -> HachiState {
+        HachiState {
+            program_counter: self.program_counter,
+            i: self.i_address(),
+            v_registers: self.v_registers,
+            stack: self.stack.clone(),
+            stack_pointer: self.stack_pointer,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            variant: self.variant,
+            quirks: self.quirks,
+            display_width: self.display_width,
+            display_height: self.display_height,
+            display: self.display.to_vec(),
+            second_plane: self.second_plane.clone(),
+            plane_mask: self.plane_mask,
+            rpl_flags: self.rpl_flags,
+            pitch: self.pitch,
+            audio_pattern: self.audio_pattern,
+            keys: self.keys,
+            frame_count: self.frame_count,
+            ram: self.ram.clone(),
+        }
+    }
+
+    // Puts the machine into `state`. Fails with `StateError::Corrupt`, and
+    // changes nothing, if the display size isn't one of the variant's
+    // modes, the display buffers don't match the dimensions,
+    // the stack pointer is past the stack, or the RAM is too small for the
+    // fonts and start address or too big for the variant.
+    pub fn set_state(&mut self, state: &HachiState) -> Result<(), StateError> {
+        let display_len = state.display_width * state.display_height;
+        if !state
+            .variant
+            .has_display_mode(state.display_width, state.display_height)
+            || state.display.len() != display_len
+            || state.second_plane.len() != display_len
+            || state.stack.is_empty()
+            || state.stack_pointer as usize > state.stack.len()
+            || !self.fits_ram_size(state.ram.len())
+            || state.ram.len() > state.variant.max_ram_size()
+        {
+            return Err(StateError::Corrupt);
+        }
+
+        self.program_counter = state.program_counter;
+        self.set_i_address(state.i);
+        self.v_registers = state.v_registers;
+        self.stack.clone_from(&state.stack);
+        self.stack_pointer = state.stack_pointer;
+        self.stack_high_water = self.stack_high_water.max(state.stack_pointer);
+        self.delay_timer = state.delay_timer;
+        self.sound_timer = state.sound_timer;
+        self.variant = state.variant;
+        self.quirks = state.quirks;
+        self.plane_mask = state.plane_mask;
+        self.rpl_flags = state.rpl_flags;
+        self.pitch = state.pitch;
+        self.audio_pattern = state.audio_pattern;
+        self.keys = state.keys;
+        self.frame_count = state.frame_count;
+        self.ram.clone_from(&state.ram);
+        self.clamp_battery_ram(self.ram.len());
+
+        self.display_width = state.display_width;
+        self.display_height = state.display_height;
+        match Arc::get_mut(&mut self.display) {
+            Some(display) => display.clone_from(&state.display),
+            None => self.display = Arc::new(state.display.clone()),
+        }
+        self.second_plane.clone_from(&state.second_plane);
+        self.mark_all_rows_dirty();
+        self.publish_display();
+        self.update_sound();
+        self.publish_timers();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn round_trips_a_snapshot() {
+        let mut hachi = Hachi::new();
+        hachi.load(&[0x60, 0x07, 0x12, 0x00]).unwrap();
+        hachi.tick().unwrap();
+        let state = hachi.state();
+
+        let mut other = Hachi::new();
+        assert_eq!(other.set_state(&state), Ok(()));
+        assert_eq!(other.state(), state);
+    }
+
+    #[test]
+    fn rejects_a_display_size_the_variant_has_no_mode_for() {
+        let mut hachi = Hachi::new();
+        let before = hachi.state();
+
+        for (width, height) in [(128, 16), (128, 64), (0, 32)] {
+            let mut state = before.clone();
+            state.display_width = width;
+            state.display_height = height;
+            state.display = vec![false; width * height];
+            state.second_plane = vec![false; width * height];
+            assert_eq!(hachi.set_state(&state), Err(StateError::Corrupt));
+            assert_eq!(hachi.state(), before);
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use crate::{HaltReason, MemoryAccess};
+
+// What a successful `tick` did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickStatus {
+    // An instruction was executed.
+    Executed,
+    // PC reached a breakpoint and the instruction there was not executed.
+    // Ticking again runs it.
+    Breakpoint {
+        pc: u16,
+    },
+    // The instruction at `pc` was executed and touched `addr`, the first
+    // watched address in its access.
+    Watchpoint {
+        pc: u16,
+        addr: usize,
+        access: MemoryAccess,
+    },
+    // The machine is halted, either by the instruction just executed or
+    // from before; nothing more will happen until `resume` or a reset.
+    Halted {
+        reason: HaltReason,
+    },
+}
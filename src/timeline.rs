@@ -0,0 +1,116 @@
+use alloc::vec::Vec;
+
+use crate::{Frame, Hachi};
+
+// Something an encoder needs to know about, stamped with the index of the
+// video frame (timer tick) it takes effect on. Divide by the timeline's
+// `timer_hz` for seconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AvEvent {
+    // The display from this frame onward. Only emitted when the picture
+    // differs from the previous event's, so an encoder should repeat the
+    // last image for frames in between.
+    Video { frame: u64, image: Frame },
+    AudioOn { frame: u64 },
+    AudioOff { frame: u64 },
+}
+
+impl AvEvent {
+    pub fn frame(&self) -> u64 {
+        match *self {
+            AvEvent::Video { frame, .. } => frame,
+            AvEvent::AudioOn { frame } => frame,
+            AvEvent::AudioOff { frame } => frame,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timeline {
+    pub timer_hz: u32,
+    pub events: Vec<AvEvent>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TimelineRecorder {
+    events: Vec<AvEvent>,
+    last_image: Option<Frame>,
+    sound_on: bool,
+}
+
+impl Hachi {
+    // Starts recording audio and video events at every `tick_timers` call,
+    // discarding anything recorded before.
+    pub fn start_timeline(&mut self) {
+        self.timeline = Some(TimelineRecorder {
+            events: Vec::new(),
+            last_image: None,
+            sound_on: false,
+        });
+    }
+
+    pub fn is_recording_timeline(&self) -> bool {
+        self.timeline.is_some()
+    }
+
+    // Hands over the events recorded so far and keeps recording.
+    pub fn drain_timeline(&mut self) -> Timeline {
+        let events = match &mut self.timeline {
+            Some(recorder) => core::mem::take(&mut recorder.events),
+            None => Vec::new(),
+        };
+
+        Timeline {
+            timer_hz: self.timer_hz,
+            events,
+        }
+    }
+
+    // Stops recording and returns everything not yet drained.
+    pub fn stop_timeline(&mut self) -> Option<Timeline> {
+        let recorder = self.timeline.take()?;
+
+        Some(Timeline {
+            timer_hz: self.timer_hz,
+            events: recorder.events,
+        })
+    }
+
+    // Called at the end of each frame, before the frame counter advances.
+    pub(crate) fn record_timeline(&mut self) {
+        let recorder = match &mut self.timeline {
+            Some(recorder) => recorder,
+            None => return,
+        };
+        let frame = self.frame_count;
+
+        let image = Frame::pack(
+            &self.display[..],
+            self.display_width,
+            self.display_height,
+            frame,
+            true,
+        );
+        let changed = recorder
+            .last_image
+            .as_ref()
+            .is_none_or(|last| last.pixels() != image.pixels());
+        if changed {
+            recorder.events.push(AvEvent::Video {
+                frame,
+                image: image.clone(),
+            });
+            recorder.last_image = Some(image);
+        }
+
+        let sound_on = self.sound_timer > 0;
+        if sound_on != recorder.sound_on {
+            recorder.events.push(if sound_on {
+                AvEvent::AudioOn { frame }
+            } else {
+                AvEvent::AudioOff { frame }
+            });
+            recorder.sound_on = sound_on;
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use core::time::Duration;
+
+use crate::Hachi;
+
+pub const DEFAULT_TIMER_HZ: u32 = 60;
+
+impl Hachi {
+    // Rate at which the host calls `tick_timers`. The delay and sound timers
+    // count down once per call, so this is also their decrement rate.
+    pub fn timer_frequency(&self) -> u32 {
+        self.timer_hz
+    }
+
+    pub fn set_timer_frequency(&mut self, hz: u32) {
+        self.timer_hz = hz.max(1);
+    }
+
+    // How long the host should wait between calls to `tick_timers`.
+    pub fn timer_period(&self) -> Duration {
+        Duration::from_secs(1) / self.timer_hz
+    }
+
+    // Effective CPU speed given the configured clock rate or instructions
+    // per frame.
+    pub fn instructions_per_second(&self) -> u32 {
+        self.clock_hz
+            .unwrap_or(self.instructions_per_frame * self.timer_hz)
+    }
+
+    // Time left on the current beep, or zero when the sound timer is idle.
+    pub fn sound_remaining(&self) -> Duration {
+        self.timer_period() * self.sound_timer as u32
+    }
+
+    // Time left before the delay timer reaches zero.
+    pub fn delay_remaining(&self) -> Duration {
+        self.timer_period() * self.delay_timer as u32
+    }
+}
+
+// Carries the fractional part of a timer tick between calls so hosts running
+// at rates that don't divide evenly into the timer frequency (e.g. 144 Hz
+// vsync) decrement the timers exactly the right number of times over time.
+// The remainder is kept as an exact fraction of a tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerAccumulator {
+    numerator: u64,
+    denominator: u64,
+}
+
+impl TimerAccumulator {
+    pub fn new() -> Self {
+        Self {
+            numerator: 0,
+            denominator: 1,
+        }
+    }
+
+    // Adds `num / den` ticks and returns how many whole ticks are now due.
+    pub fn add(&mut self, num: u64, den: u64) -> u64 {
+        if den == 0 {
+            return 0;
+        }
+
+        let (mut n, mut d) = if den == self.denominator {
+            (self.numerator as u128 + num as u128, den as u128)
+        } else {
+            (
+                self.numerator as u128 * den as u128 + num as u128 * self.denominator as u128,
+                self.denominator as u128 * den as u128,
+            )
+        };
+
+        let divisor = gcd(n, d);
+        n /= divisor;
+        d /= divisor;
+
+        let ticks = n / d;
+        self.numerator = (n % d) as u64;
+        self.denominator = d as u64;
+        ticks as u64
+    }
+
+    // Fraction of the next tick already accumulated, in the range [0, 1).
+    pub fn fraction(&self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl Default for TimerAccumulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a.max(1)
+}
+
+impl Hachi {
+    // Advances the timers by a span of host wall-clock time, ticking them as
+    // many times as the configured frequency calls for. Returns the number of
+    // ticks applied.
+    pub fn advance_timers(&mut self, elapsed: Duration) -> u64 {
+        let ticks = self.timer_accumulator.add(
+            elapsed.as_nanos() as u64 * self.timer_hz as u64,
+            1_000_000_000,
+        );
+        self.apply_timer_ticks(ticks)
+    }
+
+    // Advances the timers by one host frame for a host refreshing at
+    // `host_hz`, e.g. once per vsync on a 144 Hz display.
+    pub fn advance_timers_for_host_frame(&mut self, host_hz: u32) -> u64 {
+        let ticks = self
+            .timer_accumulator
+            .add(self.timer_hz as u64, host_hz as u64);
+        self.apply_timer_ticks(ticks)
+    }
+
+    fn apply_timer_ticks(&mut self, ticks: u64) -> u64 {
+        for _ in 0..ticks {
+            self.tick_timers();
+        }
+        ticks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn carries_fractions_between_frames() {
+        // 60 Hz timers on a 144 Hz host tick 5 times every 12 frames
+        let mut acc = TimerAccumulator::new();
+        let ticks: Vec<u64> = (0..12).map(|_| acc.add(60, 144)).collect();
+        assert_eq!(ticks.iter().sum::<u64>(), 5);
+        assert!(ticks.iter().all(|&n| n <= 1));
+        assert_eq!(acc.fraction(), 0.0);
+
+        assert_eq!(acc.add(1, 3), 0);
+        assert_eq!(acc.add(1, 2), 0);
+        assert_eq!(acc.add(1, 6), 1);
+        assert_eq!(acc, TimerAccumulator::new());
+    }
+
+    #[test]
+    fn ignores_a_zero_denominator() {
+        let mut acc = TimerAccumulator::new();
+        acc.add(1, 2);
+        assert_eq!(acc.add(5, 0), 0);
+        assert_eq!(acc.fraction(), 0.5);
+    }
+
+    #[test]
+    fn advances_timers_by_wall_clock_time() {
+        let mut hachi = Hachi::new();
+        hachi.delay_timer = 100;
+        assert_eq!(hachi.advance_timers(Duration::from_millis(10)), 0);
+        assert_eq!(hachi.advance_timers(Duration::from_millis(10)), 1);
+        assert_eq!(hachi.advance_timers(Duration::from_secs(1)), 60);
+        assert_eq!(hachi.delay_timer, 39);
+
+        for _ in 0..144 {
+            hachi.advance_timers_for_host_frame(144);
+        }
+        assert_eq!(hachi.delay_timer, 0);
+    }
+}
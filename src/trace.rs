@@ -0,0 +1,146 @@
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{Hachi, Instruction, Register};
+
+// One executed instruction and the registers it changed, with their values
+// afterwards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub pc: u16,
+    pub opcode: u16,
+    pub instruction: Instruction,
+    pub registers: Vec<(Register, u16)>,
+    // The narrowest label containing `pc`, if any.
+    pub label: Option<String>,
+}
+
+impl TraceEntry {
+    pub fn disassembly(&self) -> String {
+        self.instruction.to_string()
+    }
+}
+
+// Formats as a log line, e.g. `204: 8014  ADD V0, V1  V0=0C  VF=00`,
+// followed by `  ; name` inside a labelled range.
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:03X}: {:04X}  {}",
+            self.pc, self.opcode, self.instruction
+        )?;
+        for &(reg, value) in &self.registers {
+            match reg {
+                Register::V(x) => write!(f, "  V{:X}={:02X}", x, value)?,
+                Register::I => write!(f, "  I={:03X}", value)?,
+                Register::Pc => write!(f, "  PC={:03X}", value)?,
+                Register::Sp => write!(f, "  SP={}", value)?,
+                Register::DelayTimer => write!(f, "  DT={}", value)?,
+                Register::SoundTimer => write!(f, "  ST={}", value)?,
+            }
+        }
+        if let Some(label) = &self.label {
+            write!(f, "  ; {}", label)?;
+        }
+        Ok(())
+    }
+}
+
+// Receives trace entries as they happen, e.g. to stream them to a file.
+pub trait TraceSink {
+    fn record(&mut self, entry: &TraceEntry);
+}
+
+pub(crate) enum Tracer {
+    Buffer {
+        entries: VecDeque<TraceEntry>,
+        capacity: usize,
+    },
+    Sink(Box<dyn TraceSink>),
+}
+
+impl Hachi {
+    // Keeps the last `capacity` executed instructions for `take_trace`.
+    pub fn trace_to_buffer(&mut self, capacity: usize) {
+        self.trace = Some(Tracer::Buffer {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        });
+    }
+
+    // Hands every executed instruction to `sink` instead of buffering.
+    pub fn trace_to_sink(&mut self, sink: Box<dyn TraceSink>) {
+        self.trace = Some(Tracer::Sink(sink));
+    }
+
+    // Turns tracing off, giving back the sink if there was one.
+    pub fn stop_trace(&mut self) -> Option<Box<dyn TraceSink>> {
+        match self.trace.take() {
+            Some(Tracer::Sink(sink)) => Some(sink),
+            _ => None,
+        }
+    }
+
+    pub fn is_tracing(&self) -> bool {
+        self.trace.is_some()
+    }
+
+    // Buffered entries, oldest first. Empty when tracing to a sink.
+    pub fn take_trace(&mut self) -> Vec<TraceEntry> {
+        match &mut self.trace {
+            Some(Tracer::Buffer { entries, .. }) => entries.drain(..).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub(crate) fn register_value(&self, reg: Register) -> u16 {
+        match reg {
+            Register::V(x) => self.v_registers[x as usize & 0xF] as u16,
+            Register::I => self.i_register,
+            Register::Pc => self.program_counter,
+            Register::Sp => self.stack_pointer,
+            Register::DelayTimer => self.delay_timer as u16,
+            Register::SoundTimer => self.sound_timer as u16,
+        }
+    }
+
+    // Called after `op` has executed successfully.
+    pub(crate) fn trace_instruction(&mut self, op: u16) {
+        if self.trace.is_none() {
+            return;
+        }
+
+        let instruction = Instruction::decode_for(op, self.variant);
+        let entry = TraceEntry {
+            pc: self.instruction_pc,
+            opcode: op,
+            instruction,
+            registers: instruction
+                .writes()
+                .into_iter()
+                .map(|reg| (reg, self.register_value(reg)))
+                .collect(),
+            label: self
+                .label_at(self.instruction_pc)
+                .map(|label| label.name.clone()),
+        };
+
+        match &mut self.trace {
+            Some(Tracer::Buffer { entries, capacity }) => {
+                if *capacity == 0 {
+                    return;
+                }
+                if entries.len() == *capacity {
+                    entries.pop_front();
+                }
+                entries.push_back(entry);
+            }
+            Some(Tracer::Sink(sink)) => sink.record(&entry),
+            None => (),
+        }
+    }
+}
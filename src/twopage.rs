@@ -0,0 +1,43 @@
+use crate::{Hachi, Variant, DISPLAY_WIDTH, START_ADDRESS};
+
+// The VIP's two-page hi-res CHIP-8 doubles the display to 64x64. Its ROMs
+// open with a jump over the patched interpreter to 0x260, and the program
+// proper starts at 0x2C0.
+pub const TWO_PAGE_DISPLAY_HEIGHT: usize = 64;
+const TWO_PAGE_HEADER: [u8; 2] = [0x12, 0x60];
+const TWO_PAGE_ENTRY: u16 = 0x2C0;
+
+impl Hachi {
+    // Off by default, since any ROM that happens to open with a jump to
+    // 0x260 would otherwise be taken for a two-page one. Takes effect at
+    // the next `load`.
+    pub fn set_two_page_detection(&mut self, enabled: bool) {
+        self.two_page_detection = enabled;
+    }
+
+    pub fn is_detecting_two_page_hires(&self) -> bool {
+        self.two_page_detection
+    }
+
+    // Whether the display is in the 64x64 two-page mode.
+    pub fn is_two_page_hires(&self) -> bool {
+        (self.display_width, self.display_height) == (DISPLAY_WIDTH, TWO_PAGE_DISPLAY_HEIGHT)
+    }
+
+    // Called once a ROM is in place. With detection on, a plain CHIP-8 ROM
+    // carrying the hi-res header switches the display to 64x64 and starts
+    // past the header.
+    pub(crate) fn detect_two_page_hires(&mut self) {
+        let start = START_ADDRESS as usize;
+        if !self.two_page_detection
+            || self.variant != Variant::Chip8
+            || self.start_address != START_ADDRESS
+            || self.ram.get(start..start + 2) != Some(&TWO_PAGE_HEADER[..])
+        {
+            return;
+        }
+
+        self.resize_display(DISPLAY_WIDTH, TWO_PAGE_DISPLAY_HEIGHT);
+        self.program_counter = TWO_PAGE_ENTRY;
+    }
+}
@@ -0,0 +1,131 @@
+use crate::schip::{HIRES_DISPLAY_HEIGHT, HIRES_DISPLAY_WIDTH};
+use crate::twopage::TWO_PAGE_DISPLAY_HEIGHT;
+use crate::{Hachi, DISPLAY_HEIGHT, DISPLAY_WIDTH, START_ADDRESS, XO_RAM_SIZE};
+
+// The instruction set the machine implements. Opcodes from a variant other
+// than the selected one raise `HachiError::UnknownOpcode` as before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Variant {
+    #[default]
+    Chip8,
+    // SUPER-CHIP 1.1: 128x64 high-resolution mode, scrolling, 16x16
+    // sprites, the large font and the RPL user flags.
+    SuperChip,
+    // Octo's XO-CHIP: everything in SUPER-CHIP plus 64 KB of RAM, a second
+    // display plane, an audio pattern buffer and the new opcodes.
+    XoChip,
+    // CHIP-8X for the VIP with the VP-590 color board: CHIP-8 with
+    // foreground color zones, a cycling background, a second keypad and a
+    // tone generator.
+    Chip8X,
+    // MEGA-CHIP 8: SUPER-CHIP plus a 256x192 color mode with 24-bit
+    // addressing, palettes, blended sprites and sampled sound.
+    #[cfg(feature = "megachip")]
+    MegaChip,
+}
+
+impl Variant {
+    pub fn has_schip_opcodes(self) -> bool {
+        match self {
+            Variant::Chip8 | Variant::Chip8X => false,
+            Variant::SuperChip | Variant::XoChip => true,
+            #[cfg(feature = "megachip")]
+            Variant::MegaChip => true,
+        }
+    }
+
+    pub fn has_xochip_opcodes(self) -> bool {
+        matches!(self, Variant::XoChip)
+    }
+
+    // Where programs for this variant are loaded and start: 0x200, except
+    // CHIP-8X, whose larger interpreter pushed them up to 0x300.
+    pub fn start_address(self) -> u16 {
+        match self {
+            Variant::Chip8X => 0x300,
+            _ => START_ADDRESS,
+        }
+    }
+
+    // The most RAM a machine running this variant can have, custom sizes
+    // included.
+    pub(crate) fn max_ram_size(self) -> usize {
+        match self {
+            #[cfg(feature = "megachip")]
+            Variant::MegaChip => crate::megachip::MEGA_RAM_SIZE,
+            _ => XO_RAM_SIZE,
+        }
+    }
+
+    // Whether a machine running this variant can have a `width` x `height`
+    // display: 64x32 always, 64x64 for two-page CHIP-8, 128x64 with the
+    // SUPER-CHIP opcodes and 256x192 for MEGA-CHIP.
+    pub(crate) fn has_display_mode(self, width: usize, height: usize) -> bool {
+        match (width, height) {
+            (DISPLAY_WIDTH, DISPLAY_HEIGHT) => true,
+            (DISPLAY_WIDTH, TWO_PAGE_DISPLAY_HEIGHT) => self == Variant::Chip8,
+            (HIRES_DISPLAY_WIDTH, HIRES_DISPLAY_HEIGHT) => self.has_schip_opcodes(),
+            #[cfg(feature = "megachip")]
+            (crate::megachip::MEGA_DISPLAY_WIDTH, crate::megachip::MEGA_DISPLAY_HEIGHT) => {
+                self == Variant::MegaChip
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn id(self) -> u8 {
+        match self {
+            Variant::Chip8 => 0,
+            Variant::SuperChip => 1,
+            Variant::XoChip => 2,
+            Variant::Chip8X => 4,
+            #[cfg(feature = "megachip")]
+            Variant::MegaChip => 3,
+        }
+    }
+
+    pub(crate) fn from_id(id: u8) -> Option<Variant> {
+        match id {
+            0 => Some(Variant::Chip8),
+            1 => Some(Variant::SuperChip),
+            2 => Some(Variant::XoChip),
+            4 => Some(Variant::Chip8X),
+            #[cfg(feature = "megachip")]
+            3 => Some(Variant::MegaChip),
+            _ => None,
+        }
+    }
+}
+
+impl Hachi {
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    // Switching to a variant without a high-resolution mode drops the
+    // display back to 64x32, and RAM grows to 64 KB for XO-CHIP or shrinks
+    // back to 4 KB when leaving it. The start address goes back to the
+    // variant's own, so set a custom one afterwards.
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+        self.start_address = variant.start_address();
+        // A custom RAM size too small for the new start address is dropped
+        // rather than leaving nowhere to load a ROM.
+        if let Some(len) = self.custom_ram_size {
+            if !self.fits_ram_size(len) {
+                self.custom_ram_size = None;
+            }
+        }
+        if !variant.has_schip_opcodes() {
+            self.set_hires(false);
+        }
+        if !variant.has_xochip_opcodes() {
+            self.plane_mask = 1;
+        }
+        #[cfg(feature = "megachip")]
+        if variant != Variant::MegaChip && self.is_megachip_mode() {
+            self.set_megachip_mode(false);
+        }
+        self.resize_ram(self.ram_size_for_variant());
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{Hachi, HaltReason, MemoryAccess};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome {
+    // All requested instructions were executed.
+    Completed,
+    // The watchdog fired: this many instructions ran back to back without
+    // touching the display, the timers or the keypad.
+    Runaway {
+        instructions: u64,
+    },
+    // Stopped before the instruction at a breakpoint.
+    Breakpoint {
+        pc: u16,
+    },
+    // The instruction at `pc` touched a watched address.
+    Watchpoint {
+        pc: u16,
+        addr: usize,
+        access: MemoryAccess,
+    },
+    // The machine halted, e.g. on a jump to itself.
+    Halted {
+        reason: HaltReason,
+    },
+}
+
+impl Hachi {
+    pub fn watchdog(&self) -> Option<u64> {
+        self.watchdog_limit
+    }
+
+    // Sets the number of instructions that may run without any display,
+    // timer or input activity before `tick_n` gives up with
+    // `RunOutcome::Runaway`. `None` disables the watchdog.
+    pub fn set_watchdog(&mut self, limit: Option<u64>) {
+        self.watchdog_limit = limit;
+        self.idle_instructions = 0;
+    }
+
+    pub(crate) fn watch_for_activity(&mut self, op: u16) {
+        let active = match op & 0xF0FF {
+            0x00E0 | 0xE09E | 0xE0A1 | 0xF007 | 0xF00A | 0xF015 | 0xF018 => true,
+            // SUPER-CHIP scrolling and resolution changes
+            0x00FB | 0x00FC | 0x00FE | 0x00FF => true,
+            _ => op & 0xF000 == 0xD000 || matches!(op & 0xFFF0, 0x00C0 | 0x00D0),
+        };
+
+        if active {
+            self.idle_instructions = 0;
+        } else {
+            self.idle_instructions += 1;
+        }
+    }
+
+    pub(crate) fn check_watchdog(&mut self) -> Option<RunOutcome> {
+        match self.watchdog_limit {
+            Some(limit) if self.idle_instructions > limit => {
+                let instructions = self.idle_instructions;
+                self.idle_instructions = 0;
+                Some(RunOutcome::Runaway { instructions })
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use core::ops::Range;
+
+use crate::{Hachi, Instruction, TickStatus};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryAccess {
+    Read,
+    Write,
+}
+
+// Which kinds of access to a watched range stop execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl WatchKind {
+    fn matches(self, access: MemoryAccess) -> bool {
+        matches!(
+            (self, access),
+            (WatchKind::ReadWrite, _)
+                | (WatchKind::Read, MemoryAccess::Read)
+                | (WatchKind::Write, MemoryAccess::Write)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub range: Range<usize>,
+    pub kind: WatchKind,
+}
+
+impl Hachi {
+    // Stops `tick` after any instruction that reads or writes (per `kind`)
+    // RAM inside `range` through I: FX33, FX55, FX65, DXYN and the XO-CHIP
+    // transfers. Fetching instructions doesn't count.
+    pub fn add_watchpoint(&mut self, range: Range<usize>, kind: WatchKind) {
+        self.watchpoints.push(Watchpoint { range, kind });
+    }
+
+    // Watches the range of the label called `name`, e.g. a score variable
+    // named with `add_label`. Returns false if there is no such label.
+    pub fn add_label_watchpoint(&mut self, name: &str, kind: WatchKind) -> bool {
+        match self.labels.iter().find(|label| label.name == name) {
+            Some(label) => {
+                let range = label.range.start as usize..label.range.end as usize;
+                self.add_watchpoint(range, kind);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove_watchpoint(&mut self, range: Range<usize>) -> bool {
+        let before = self.watchpoints.len();
+        self.watchpoints.retain(|watch| watch.range != range);
+        self.watchpoints.len() != before
+    }
+
+    pub fn clear_watchpoints(&mut self) {
+        self.watchpoints.clear();
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    // Works out before `op` runs which watched addresses it will touch,
+    // since I may change as it executes.
+    pub(crate) fn pending_watch_hit(&self, op: u16) -> Option<TickStatus> {
+        if self.watchpoints.is_empty() {
+            return None;
+        }
+
+        let (read, written) = self.memory_access(Instruction::decode_for(op, self.variant));
+        let accesses = [(read, MemoryAccess::Read), (written, MemoryAccess::Write)];
+
+        for (range, access) in accesses {
+            let range = match range {
+                Some(range) => range,
+                None => continue,
+            };
+            for watch in &self.watchpoints {
+                let start = range.start.max(watch.range.start);
+                if watch.kind.matches(access) && start < range.end.min(watch.range.end) {
+                    return Some(TickStatus::Watchpoint {
+                        pc: self.instruction_pc,
+                        addr: start,
+                        access,
+                    });
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RunOutcome, Variant};
+
+    #[test]
+    fn watches_through_a_long_i_load() {
+        let mut hachi = Hachi::new();
+        hachi.set_variant(Variant::XoChip);
+        // i := long 0xFFF0, load v1
+        hachi.load(&[0xF0, 0x00, 0xFF, 0xF0, 0xF1, 0x65]).unwrap();
+        hachi.add_watchpoint(0xFFF1..0xFFF2, WatchKind::Read);
+
+        assert_eq!(
+            hachi.tick_n(2),
+            Ok(RunOutcome::Watchpoint {
+                pc: 0x204,
+                addr: 0xFFF1,
+                access: MemoryAccess::Read,
+            })
+        );
+    }
+
+    #[cfg(feature = "megachip")]
+    #[test]
+    fn watches_above_64k_in_megachip_mode() {
+        let mut hachi = Hachi::new();
+        hachi.set_variant(Variant::MegaChip);
+        // i := 0x012340, save v0
+        hachi.load(&[0x01, 0x01, 0x23, 0x40, 0xF0, 0x55]).unwrap();
+        hachi.add_watchpoint(0x2340..0x2341, WatchKind::Write);
+        hachi.add_watchpoint(0x1_2340..0x1_2341, WatchKind::Write);
+
+        assert_eq!(
+            hachi.tick_n(2),
+            Ok(RunOutcome::Watchpoint {
+                pc: 0x204,
+                addr: 0x1_2340,
+                access: MemoryAccess::Write,
+            })
+        );
+    }
+}
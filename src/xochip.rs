@@ -0,0 +1,289 @@
+use crate::shared::Arc;
+use crate::{Hachi, HachiError, RAM_SIZE};
+
+pub const XO_RAM_SIZE: usize = 0x10000;
+
+pub(crate) const AUDIO_PATTERN_SIZE: usize = 16;
+pub(crate) const DEFAULT_PITCH: u8 = 64;
+
+// The buzzer tone for CHIP-8 and SUPER-CHIP, which have no pattern buffer: a
+// 500 Hz square wave at the default pitch.
+const BUZZER_PATTERN: [u8; AUDIO_PATTERN_SIZE] = [0xF0; AUDIO_PATTERN_SIZE];
+
+impl Hachi {
+    // The second XO-CHIP bitplane, laid out like `get_display`.
+    pub fn second_plane(&self) -> &[bool] {
+        &self.second_plane
+    }
+
+    // Colour index of a pixel with the first plane as bit 0 and the second
+    // as bit 1, so 0-3 in XO-CHIP and 0-1 everywhere else.
+    pub fn pixel_color(&self, x: usize, y: usize) -> u8 {
+        if x >= self.display_width || y >= self.display_height {
+            return 0;
+        }
+        let idx = x + self.display_width * y;
+        self.display[idx] as u8 | (self.second_plane[idx] as u8) << 1
+    }
+
+    // Bitmask of the planes drawing, clearing and scrolling act on (FN01).
+    pub fn selected_planes(&self) -> u8 {
+        self.plane_mask
+    }
+
+    // The 1-bit, 128-sample waveform loaded by F002.
+    pub fn audio_pattern(&self) -> [u8; AUDIO_PATTERN_SIZE] {
+        self.audio_pattern
+    }
+
+    // The FX3A pitch register; 64 plays the pattern at 4000 samples/s.
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    // 4000 * 2^((pitch - 64) / 48), built from whole octaves and 48ths of
+    // an octave since `powf` isn't available without std.
+    pub fn audio_playback_rate(&self) -> f32 {
+        const STEP: f32 = 1.014_545_3;
+        let steps = self.pitch as i32 - 64;
+        let mut rate = 4000.0;
+        for _ in 0..steps.rem_euclid(48) {
+            rate *= STEP;
+        }
+        let octaves = steps.div_euclid(48);
+        if octaves >= 0 {
+            rate * (1u32 << octaves) as f32
+        } else {
+            rate / (1u32 << -octaves) as f32
+        }
+    }
+
+    // Fills `out` with the buzzer's output at `sample_rate`, +1.0 or -1.0
+    // per sample following the pattern bits, or silence while the sound
+    // timer is idle. The position in the pattern carries over between
+    // calls, so hosts can feed an audio callback one buffer at a time.
+    pub fn fill_audio(&mut self, out: &mut [f32], sample_rate: u32) {
+        if self.sound_timer == 0 {
+            out.fill(0.0);
+            return;
+        }
+
+        let pattern = if self.variant.has_xochip_opcodes() {
+            &self.audio_pattern
+        } else {
+            &BUZZER_PATTERN
+        };
+        // The phase is in 1/65536ths of a pattern bit.
+        let wrap = (AUDIO_PATTERN_SIZE as u32 * 8) << 16;
+        let step = (self.audio_playback_rate() * 65536.0 / sample_rate.max(1) as f32) as u32;
+
+        for sample in out {
+            let bit = (self.audio_phase >> 16) as usize;
+            let on = pattern[bit / 8] & (0x80 >> (bit % 8)) != 0;
+            *sample = if on { 1.0 } else { -1.0 };
+            self.audio_phase = (self.audio_phase + step) % wrap;
+        }
+    }
+
+    pub fn ram_size(&self) -> usize {
+        self.ram.len()
+    }
+
+    // Runs `f` over each selected plane and publishes the result.
+    pub(crate) fn for_each_selected_plane(&mut self, mut f: impl FnMut(&mut [bool])) {
+        if self.plane_mask & 1 != 0 {
+            f(Arc::make_mut(&mut self.display).as_mut_slice());
+        }
+        if self.plane_mask & 2 != 0 {
+            f(&mut self.second_plane);
+        }
+        self.mark_all_rows_dirty();
+        self.publish_display();
+    }
+
+    // 00E0 only clears the selected planes.
+    pub(crate) fn clear_selected_planes(&mut self) {
+        if self.plane_mask == 3 {
+            self.clear_display();
+            return;
+        }
+        self.for_each_selected_plane(|plane| plane.fill(false));
+    }
+
+    // 00DN: the counterpart of 00CN.
+    pub(crate) fn scroll_up(&mut self, rows: usize) {
+        let shift = rows * self.display_width;
+        self.for_each_selected_plane(|plane| {
+            let len = plane.len();
+            let shift = shift.min(len);
+            plane.copy_within(shift.., 0);
+            plane[len - shift..].fill(false);
+        });
+    }
+
+    // 5XY2 and 5XY3 walk VX to VY in either direction.
+    pub(crate) fn register_range(x: usize, y: usize) -> impl Iterator<Item = usize> {
+        let ascending = x <= y;
+        let (lo, hi) = if ascending { (x, y) } else { (y, x) };
+        (lo..=hi).map(move |idx| if ascending { idx } else { hi + lo - idx })
+    }
+
+    pub(crate) fn save_register_range(&mut self, x: usize, y: usize) -> Result<(), HachiError> {
+        let i = self.i_address();
+        self.check_range(i, x.abs_diff(y) + 1)?;
+        for (offset, reg) in Self::register_range(x, y).enumerate() {
+            self.bus_write(i + offset, self.v_registers[reg]);
+        }
+        self.note_ram_write(i, x.abs_diff(y) + 1);
+        Ok(())
+    }
+
+    pub(crate) fn load_register_range(&mut self, x: usize, y: usize) -> Result<(), HachiError> {
+        let i = self.i_address();
+        self.check_range(i, x.abs_diff(y) + 1)?;
+        for (offset, reg) in Self::register_range(x, y).enumerate() {
+            self.v_registers[reg] = self.bus_read(i + offset);
+        }
+        Ok(())
+    }
+
+    // Grows or shrinks RAM when the variant changes, keeping the battery
+    // region inside it.
+    pub(crate) fn resize_ram(&mut self, len: usize) {
+        self.ram.resize(len, 0);
+        self.clamp_battery_ram(len);
+    }
+
+    pub(crate) fn ram_size_for_variant(&self) -> usize {
+        self.ram_size_for(self.variant)
+    }
+
+    // The RAM the machine would have after switching to `variant`.
+    pub(crate) fn ram_size_for(&self, variant: crate::Variant) -> usize {
+        if let Some(len) = self.custom_ram_size {
+            if len > variant.start_address() as usize {
+                return len;
+            }
+        }
+        #[cfg(feature = "megachip")]
+        if variant == crate::Variant::MegaChip {
+            return crate::megachip::MEGA_RAM_SIZE;
+        }
+        if variant.has_xochip_opcodes() {
+            XO_RAM_SIZE
+        } else {
+            RAM_SIZE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Variant;
+
+    fn xochip(rom: &[u8]) -> Hachi {
+        let mut hachi = Hachi::new();
+        hachi.set_variant(Variant::XoChip);
+        hachi.load(rom).unwrap();
+        hachi
+    }
+
+    #[test]
+    fn scrolls_up_only_the_selected_plane() {
+        let mut hachi = xochip(&[
+            0xF3, 0x01, // plane 3
+            0xA2, 0x10, // i := 0x210
+            0x60, 0x00, // v0 := 0
+            0x61, 0x05, // v1 := 5
+            0xD0, 0x11, // sprite v0 v1 1
+            0xF1, 0x01, // plane 1
+            0x00, 0xD1, // scroll-up 1
+            0x12, 0x0E, // spin
+            0x80, 0x80, // a pixel in each plane
+        ]);
+        hachi.tick_n(7).unwrap();
+
+        assert_eq!(hachi.pixel_color(0, 4), 1);
+        assert_eq!(hachi.pixel_color(0, 5), 2);
+    }
+
+    #[test]
+    fn loads_a_long_i_and_steps_past_it() {
+        let mut hachi = xochip(&[0xF0, 0x00, 0x12, 0x34, 0x60, 0x01]);
+        hachi.tick().unwrap();
+
+        assert_eq!(hachi.i(), 0x1234);
+        assert_eq!(hachi.pc(), 0x204);
+    }
+
+    #[test]
+    fn draws_only_on_the_selected_plane() {
+        let mut hachi = xochip(&[
+            0xF2, 0x01, // plane 2
+            0xA2, 0x08, // i := 0x208
+            0xD0, 0x01, // sprite v0 v0 1
+            0x12, 0x06, // spin
+            0x80,
+        ]);
+        hachi.tick_n(3).unwrap();
+
+        assert_eq!(hachi.selected_planes(), 2);
+        assert_eq!(hachi.pixel_color(0, 0), 2);
+    }
+
+    #[test]
+    fn loads_the_audio_pattern_from_i() {
+        let pattern: [u8; AUDIO_PATTERN_SIZE] = core::array::from_fn(|idx| idx as u8 * 17);
+        let mut rom = alloc::vec![
+            0xA2, 0x06, // i := 0x206
+            0xF0, 0x02, // audio
+            0x12, 0x04, // spin
+        ];
+        rom.extend_from_slice(&pattern);
+        let mut hachi = xochip(&rom);
+        hachi.tick_n(2).unwrap();
+
+        assert_eq!(hachi.audio_pattern(), pattern);
+    }
+
+    #[test]
+    fn saves_and_loads_register_ranges_either_way() {
+        let mut hachi = xochip(&[
+            0xA3, 0x00, // i := 0x300
+            0x62, 0x01, // v2 := 1
+            0x63, 0x02, // v3 := 2
+            0x64, 0x03, // v4 := 3
+            0x52, 0x42, // save v2 - v4
+            0x54, 0x23, // load v4 - v2
+        ]);
+        hachi.tick_n(6).unwrap();
+
+        assert_eq!(hachi.ram()[0x300..0x303], [1, 2, 3]);
+        assert_eq!(hachi.registers()[2..5], [3, 2, 1]);
+        assert_eq!(hachi.i(), 0x300);
+    }
+
+    #[test]
+    fn addresses_all_64k() {
+        let mut rom = alloc::vec![0; 0x2000];
+        rom[..8].copy_from_slice(&[
+            0xF0, 0x00, 0xFF, 0xF0, // i := long 0xFFF0
+            0x60, 0xAB, // v0 := 0xAB
+            0xF0, 0x55, // save v0
+        ]);
+        assert_eq!(
+            Hachi::new().load(&rom),
+            Err(crate::LoadError::RomTooLarge {
+                size: rom.len(),
+                max: RAM_SIZE - 0x200,
+            })
+        );
+
+        let mut hachi = xochip(&rom);
+        hachi.tick_n(3).unwrap();
+
+        assert_eq!(hachi.ram_size(), XO_RAM_SIZE);
+        assert_eq!(hachi.ram()[0xFFF0], 0xAB);
+    }
+}